@@ -0,0 +1,199 @@
+//! Compares the LZW encoder of `GifImage` with the original one, which looked sequences
+//! up in a `Vec<Vec<usize>>`. Run with `cargo bench --bench lzw`.
+
+use std::{
+    cell::RefCell,
+    hint::black_box,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use gas::{common::types::Pixel, format::gif::gif_image::GifImage};
+
+const CODE_TABLE_MAX_SIZE: usize = 4096;
+const RUNS: u32 = 5;
+
+struct Case {
+    name: &'static str,
+    size: usize,
+    color_count: usize,
+    /// Color index of a pixel
+    color: fn(usize, usize) -> usize,
+}
+
+const CASES: [Case; 3] = [
+    Case {
+        name: "flat shapes 500x500",
+        size: 500,
+        color_count: 4,
+        color: |x, y| match (x / 125, y / 125) {
+            (1 | 2, 1 | 2) => 1,
+            (column, row) if (column + row) % 2 == 0 => 2,
+            _ => 3,
+        },
+    },
+    Case {
+        name: "bands 500x500",
+        size: 500,
+        color_count: 16,
+        color: |x, y| (x / 25 + y / 25) % 16,
+    },
+    Case {
+        name: "noise 200x200",
+        size: 200,
+        color_count: 256,
+        color: |x, y| ((x * x + y * 3) / 7 + (x ^ y)) % 256,
+    },
+];
+
+fn main() {
+    println!(
+        "{:<22} {:>12} {:>12} {:>9}",
+        "image", "original", "hash map", "speedup"
+    );
+
+    for case in &CASES {
+        let colors: Vec<Pixel> = (0..case.color_count)
+            .map(|i| Pixel::new(i as u8, (i >> 8) as u8, 0))
+            .collect();
+        let indexes: Vec<u8> = (0..case.size * case.size)
+            .map(|i| (case.color)(i % case.size, i / case.size) as u8)
+            .collect();
+
+        let mut image = GifImage::new(
+            case.size as u16,
+            case.size as u16,
+            Some(Rc::new(RefCell::new(colors.clone()))),
+        );
+        image.set_pixels(indexes.iter().map(|index| &colors[usize::from(*index)]));
+
+        let (data, current) = time(|| image.get_image_data());
+        let (original_data, original) = time(|| original_encode(&indexes, case.color_count));
+        assert_eq!(
+            sub_blocks(&data),
+            original_data,
+            "{}: the encoders disagree",
+            case.name
+        );
+
+        println!(
+            "{:<22} {:>9.2} ms {:>9.2} ms {:>8.0}x",
+            case.name,
+            original.as_secs_f64() * 1000.0,
+            current.as_secs_f64() * 1000.0,
+            original.as_secs_f64() / current.as_secs_f64()
+        );
+    }
+}
+
+/// Result of `run` and its fastest time over a few runs
+fn time<T>(run: impl Fn() -> T) -> (T, Duration) {
+    let mut fastest = Duration::MAX;
+    let mut result = None;
+
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        result = Some(black_box(run()));
+        fastest = fastest.min(start.elapsed());
+    }
+
+    (result.unwrap(), fastest)
+}
+
+/// LZW minimum code size followed by the content of the data sub-blocks
+fn sub_blocks(image_data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![image_data[0]];
+    let mut position = 1;
+
+    while image_data[position] != 0 {
+        let length = usize::from(image_data[position]);
+        bytes.extend_from_slice(&image_data[position + 1..position + 1 + length]);
+        position += length + 1;
+    }
+
+    bytes
+}
+
+/// The original encoder, in the same format as `sub_blocks`
+fn original_encode(indexes: &[u8], num_unique_code: usize) -> Vec<u8> {
+    let mut table: Vec<Vec<usize>> = Vec::with_capacity(CODE_TABLE_MAX_SIZE);
+    for i in 0..num_unique_code {
+        table.push(vec![i]);
+    }
+
+    let (cc, eoi) = (num_unique_code, num_unique_code + 1);
+    table.push(vec![cc]);
+    table.push(vec![eoi]);
+
+    let mut lzw_min_code_size = 0u8;
+    while 1 << (lzw_min_code_size + 1) < table.len() {
+        lzw_min_code_size += 1;
+    }
+
+    let mut curr_code_size = lzw_min_code_size + 1;
+
+    let mut encoded = BitCoder::default();
+    encoded.write_code(cc, curr_code_size);
+
+    let mut index_buffer = vec![usize::from(indexes[0])];
+
+    for k in indexes[1..].iter().map(|k| usize::from(*k)) {
+        let mut index_buffer_plus_k = index_buffer.clone();
+        index_buffer_plus_k.push(k);
+
+        if table.contains(&index_buffer_plus_k) {
+            index_buffer.push(k);
+            continue;
+        }
+
+        table.push(index_buffer_plus_k);
+
+        let code = table.iter().position(|code| *code == index_buffer).unwrap();
+        encoded.write_code(code, curr_code_size);
+
+        if (1 << curr_code_size) < table.len() {
+            curr_code_size += 1;
+        } else if table.len() == CODE_TABLE_MAX_SIZE - 1 {
+            encoded.write_code(cc, curr_code_size);
+            table.truncate(eoi + 1);
+            curr_code_size = lzw_min_code_size + 1;
+        }
+
+        index_buffer = vec![k];
+    }
+
+    let last_index_value = table.iter().position(|code| *code == index_buffer).unwrap();
+    encoded.write_code(last_index_value, curr_code_size);
+    encoded.write_code(eoi, curr_code_size);
+    encoded.flush();
+
+    let mut bytes = vec![lzw_min_code_size];
+    bytes.extend(encoded.buffer);
+    bytes
+}
+
+#[derive(Default)]
+struct BitCoder {
+    buffer: Vec<u8>,
+    curr_byte: u8,
+    bit_pos: u8,
+}
+
+impl BitCoder {
+    fn write_code(&mut self, code: usize, length: u8) {
+        for i in 0..length {
+            self.curr_byte |= (((code >> i) & 1) as u8) << self.bit_pos;
+            self.bit_pos += 1;
+
+            if self.bit_pos == 8 {
+                self.flush();
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        self.buffer.push(self.curr_byte);
+        self.curr_byte = 0;
+        self.bit_pos = 0;
+    }
+}
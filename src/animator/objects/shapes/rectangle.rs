@@ -1,7 +1,7 @@
 use crate::animator::{
     scene::{Draw, Frame},
-    transition::{Rotate, Scale, Transition, Transitionable, Translate},
-    types::{Color, CoordinateValue, Length, Point, Rotation},
+    transition::{Recolor, Rotate, Scale, Transition, Transitionable, Translate},
+    types::{Color, ColorChannels, CoordinateValue, Length, Point, Rotation},
 };
 
 #[derive(Clone)]
@@ -41,7 +41,7 @@ impl Rectangle {
 
         let center = self.center();
 
-        let rotated_corners = corners
+        corners
             .iter()
             .map(|&corner| {
                 let relative_x = corner.x - center.x;
@@ -57,9 +57,7 @@ impl Rectangle {
                     y: rotated_relative_y + center.y,
                 }
             })
-            .collect();
-
-        rotated_corners
+            .collect()
     }
 }
 
@@ -84,6 +82,9 @@ impl Transitionable for Rectangle {
             Transition::Rotate(descriptor) => {
                 self.apply_rotate_transition(descriptor, frame_count);
             }
+            Transition::Recolor(descriptor) => {
+                self.apply_recolor_transition(descriptor, frame_count);
+            }
         }
     }
 }
@@ -94,6 +95,11 @@ impl Draw for Rectangle {
     }
 
     fn draw(&self, _frame_count: u32, frame: &mut Frame) {
+        // Nothing to draw when the rectangle is entirely outside of the frame
+        if self.right() < 0.0 || self.bottom() < 0.0 {
+            return;
+        }
+
         // TODO apply rotation
         let min_x = self.left().max(0.0) as usize;
         let max_x = self.right().min(frame.xsize() as f64) as usize;
@@ -192,3 +198,13 @@ impl Rotate for Rectangle {
         self.rotation = r;
     }
 }
+
+impl Recolor for Rectangle {
+    fn color(&self) -> ColorChannels {
+        ColorChannels::from(self.fill_color)
+    }
+
+    fn recolor(&mut self, c: ColorChannels) {
+        self.fill_color = Color::from(c);
+    }
+}
@@ -18,26 +18,35 @@ impl Scene {
     }
 
     pub fn add_object(&mut self, object: impl Draw + 'static) {
-        self.objects.push(Box::from(object));
+        // Objects are kept sorted by z-index, in the order they were added for equal ones
+        let index = self
+            .objects
+            .partition_point(|other| other.zindex() <= object.zindex());
+        self.objects.insert(index, Box::from(object));
     }
 
-    pub fn render(mut self) -> Vec<Frame> {
-        let mut frames = vec![];
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
 
-        self.objects
-            .sort_by(|obj1, obj2| obj1.zindex().cmp(&obj2.zindex()));
+    pub fn render(&self) -> Vec<Frame> {
+        self.frames().collect()
+    }
 
-        for frame_counter in 0..self.frame_count {
-            let mut frame = Frame::new(self.xsize, self.ysize);
+    /// Frames drawn one at a time as they are iterated over
+    pub fn frames(&self) -> impl Iterator<Item = Frame> + '_ {
+        (0..self.frame_count).map(|frame_counter| self.render_frame(frame_counter))
+    }
 
-            for object in &self.objects {
-                object.draw(frame_counter, &mut frame);
-            }
+    /// Render a single frame without rendering the ones before it
+    pub fn render_frame(&self, frame_counter: u32) -> Frame {
+        let mut frame = Frame::new(self.xsize, self.ysize);
 
-            frames.push(frame);
+        for object in &self.objects {
+            object.draw(frame_counter, &mut frame);
         }
 
-        frames
+        frame
     }
 }
 
@@ -54,7 +63,7 @@ impl Frame {
     }
 
     pub fn xsize(&self) -> usize {
-        self.buffer.get(0).unwrap_or(&vec![]).len()
+        self.buffer.first().map_or(0, |row| row.len())
     }
 
     pub fn ysize(&self) -> usize {
@@ -62,10 +71,8 @@ impl Frame {
     }
 
     pub fn put_pixel(&mut self, x: usize, y: usize, c: Color) {
-        if let Some(row) = self.buffer.get_mut(y) {
-            if let Some(value) = row.get_mut(x) {
-                *value = c;
-            }
+        if let Some(value) = self.buffer.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *value = c;
         }
     }
 }
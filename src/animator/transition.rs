@@ -2,7 +2,7 @@ use std::ops::{Add, Mul, Sub};
 
 use super::{
     scene::Draw,
-    types::{CoordinateValue, Direction, EasingFunction, Point, Rotation},
+    types::{ColorChannels, CoordinateValue, Direction, EasingFunction, Point, Rotation},
 };
 
 #[derive(Clone, Copy)]
@@ -13,6 +13,7 @@ pub enum Transition {
     ScaleLeft(TransitionDescriptor<CoordinateValue>),
     ScaleRight(TransitionDescriptor<CoordinateValue>),
     Rotate(TransitionDescriptor<Rotation>),
+    Recolor(TransitionDescriptor<ColorChannels>),
 }
 
 #[derive(Clone, Copy)]
@@ -153,6 +154,20 @@ pub trait Rotate {
         descriptor: &TransitionDescriptor<Rotation>,
         frame_count: u32,
     ) {
-        self.rotate(descriptor.calculate_value_at_frame(&self.rotation(), frame_count));
+        self.rotate(descriptor.calculate_value_at_frame(self.rotation(), frame_count));
+    }
+}
+
+pub trait Recolor {
+    fn color(&self) -> ColorChannels;
+
+    fn recolor(&mut self, c: ColorChannels);
+
+    fn apply_recolor_transition(
+        &mut self,
+        descriptor: &TransitionDescriptor<ColorChannels>,
+        frame_count: u32,
+    ) {
+        self.recolor(descriptor.calculate_value_at_frame(&self.color(), frame_count));
     }
 }
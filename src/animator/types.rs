@@ -53,6 +53,116 @@ pub enum Color {
     HSLA(u32, u32, u32, u32),
 }
 
+impl Color {
+    /// Red, green, blue and alpha components of the color. HSL hue is in degrees while
+    /// the saturation, lightness and alpha are percentages.
+    pub fn to_rgba(&self) -> [u8; 4] {
+        match *self {
+            Self::RGB(r, g, b) => [r, g, b, 255],
+            Self::RGBA(r, g, b, a) => [r, g, b, a],
+            Self::HEX(value) => {
+                let [_, r, g, b] = value.to_be_bytes();
+                [r, g, b, 255]
+            }
+            Self::HSL(h, s, l) => Self::HSLA(h, s, l, 100).to_rgba(),
+            Self::HSLA(h, s, l, a) => {
+                let h = f64::from(h % 360);
+                let s = f64::from(s.min(100)) / 100.0;
+                let l = f64::from(l.min(100)) / 100.0;
+
+                let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+                let x = chroma * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+                let m = l - chroma / 2.0;
+
+                let (r, g, b) = match h as u32 / 60 {
+                    0 => (chroma, x, 0.0),
+                    1 => (x, chroma, 0.0),
+                    2 => (0.0, chroma, x),
+                    3 => (0.0, x, chroma),
+                    4 => (x, 0.0, chroma),
+                    _ => (chroma, 0.0, x),
+                };
+                let to_byte = |value: f64| ((value + m) * 255.0).round() as u8;
+                let alpha = (f64::from(a.min(100)) / 100.0 * 255.0).round() as u8;
+
+                [to_byte(r), to_byte(g), to_byte(b), alpha]
+            }
+        }
+    }
+}
+
+/// Color components as floating point values, which allows interpolating between colors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorChannels {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl From<Color> for ColorChannels {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.to_rgba();
+        Self {
+            r: f64::from(r),
+            g: f64::from(g),
+            b: f64::from(b),
+            a: f64::from(a),
+        }
+    }
+}
+
+impl From<ColorChannels> for Color {
+    fn from(channels: ColorChannels) -> Self {
+        let to_byte = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+        Color::RGBA(
+            to_byte(channels.r),
+            to_byte(channels.g),
+            to_byte(channels.b),
+            to_byte(channels.a),
+        )
+    }
+}
+
+impl Add for ColorChannels {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self {
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
+            a: self.a + other.a,
+        }
+    }
+}
+
+impl Sub for ColorChannels {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self {
+            r: self.r - other.r,
+            g: self.g - other.g,
+            b: self.b - other.b,
+            a: self.a - other.a,
+        }
+    }
+}
+
+impl Mul<f64> for ColorChannels {
+    type Output = Self;
+
+    fn mul(self, scalar: f64) -> Self::Output {
+        Self {
+            r: self.r * scalar,
+            g: self.g * scalar,
+            b: self.b * scalar,
+            a: self.a * scalar,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Length {
     Pixel(u32),
@@ -62,7 +172,7 @@ pub enum Length {
 impl Add for Length {
     type Output = Self;
 
-    fn add(self, other: Self) -> Self::Output {
+    fn add(self, _other: Self) -> Self::Output {
         todo!()
     }
 }
@@ -70,7 +180,7 @@ impl Add for Length {
 impl Sub for Length {
     type Output = Self;
 
-    fn sub(self, other: Self) -> Self::Output {
+    fn sub(self, _other: Self) -> Self::Output {
         todo!()
     }
 }
@@ -78,7 +188,7 @@ impl Sub for Length {
 impl Mul<f64> for Length {
     type Output = Self;
 
-    fn mul(self, scalar: f64) -> Self::Output {
+    fn mul(self, _scalar: f64) -> Self::Output {
         todo!()
     }
 }
@@ -198,6 +308,7 @@ pub fn cubic_bezier_derivative(t: f64, p0: Point, p1: Point, p2: Point, p3: Poin
     Point { x, y }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn newton_root_finding<F, D>(
     function: F,
     derivative: D,
@@ -293,6 +404,45 @@ impl EasingFunction {
             }
         }
     }
+
+    /// Lowest and highest progress the easing gives, which a cubic Bézier curve may take
+    /// past 0 and 1
+    pub fn range(&self) -> (f64, f64) {
+        let mut values = vec![0.0, 1.0, self.apply(0.0), self.apply(1.0)];
+
+        // Extremes of y(t) = 3(1 - t)²t y1 + 3(1 - t)t² y2 + t³, where its derivative
+        // a t² + b t + c is 0
+        if let Self::CubicBezier(_, y1, _, y2) = *self {
+            let (a, b, c) = (3.0 * y1 - 3.0 * y2 + 1.0, 2.0 * y2 - 4.0 * y1, y1);
+            let roots = if a.abs() < f64::EPSILON {
+                vec![-c / b]
+            } else {
+                let discriminant = b * b - 4.0 * a * c;
+                if discriminant < 0.0 {
+                    vec![]
+                } else {
+                    let root = discriminant.sqrt();
+                    vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+                }
+            };
+            values.extend(
+                roots
+                    .into_iter()
+                    .filter(|t| (0.0..=1.0).contains(t))
+                    .map(|t| {
+                        3.0 * (1.0 - t) * (1.0 - t) * t * y1
+                            + 3.0 * (1.0 - t) * t * t * y2
+                            + t * t * t
+                    }),
+            );
+        }
+
+        values
+            .into_iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), value| {
+                (low.min(value), high.max(value))
+            })
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,166 @@
+use std::str::FromStr;
+
+/// Option accepted by a command, eg. `-o, --output <file>`
+pub struct OptionSpec {
+    pub long: &'static str,
+    pub short: Option<&'static str>,
+    pub takes_value: bool,
+}
+
+impl OptionSpec {
+    pub const fn value(long: &'static str, short: Option<&'static str>) -> Self {
+        OptionSpec {
+            long,
+            short,
+            takes_value: true,
+        }
+    }
+
+    pub const fn flag(long: &'static str, short: Option<&'static str>) -> Self {
+        OptionSpec {
+            long,
+            short,
+            takes_value: false,
+        }
+    }
+}
+
+/// Arguments of a command once matched against its options
+#[derive(Debug, Default)]
+pub struct Args {
+    /// Options with their value, stored under their long name
+    values: Vec<(&'static str, String)>,
+    flags: Vec<&'static str>,
+    pub positionals: Vec<String>,
+}
+
+impl Args {
+    pub fn parse(arguments: &[String], specs: &[OptionSpec]) -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut arguments = arguments.iter();
+
+        while let Some(argument) = arguments.next() {
+            // `-` alone stands for the standard input or output
+            if argument == "-" || !argument.starts_with('-') {
+                args.positionals.push(argument.clone());
+                continue;
+            }
+            if argument == "--" {
+                args.positionals.extend(arguments.by_ref().cloned());
+                break;
+            }
+
+            // Accept both `--width 10` and `--width=10`
+            let (name, inline_value) = match argument.split_once('=') {
+                Some((name, value)) if argument.starts_with("--") => (name, Some(value)),
+                _ => (argument.as_str(), None),
+            };
+
+            let spec = specs
+                .iter()
+                .find(|spec| spec.long == name || spec.short == Some(name))
+                .ok_or_else(|| format!("unknown option `{name}`"))?;
+
+            if !spec.takes_value {
+                if inline_value.is_some() {
+                    return Err(format!("option `{}` does not take a value", spec.long));
+                }
+                args.flags.push(spec.long);
+                continue;
+            }
+
+            let value = match inline_value {
+                Some(value) => value.to_string(),
+                None => arguments
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| format!("option `{}` expects a value", spec.long))?,
+            };
+            args.values.push((spec.long, value));
+        }
+
+        Ok(args)
+    }
+
+    /// Last value given to an option
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == long)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether a flag was given
+    pub fn flag(&self, long: &str) -> bool {
+        self.flags.contains(&long)
+    }
+
+    /// Value of an option converted to `T`
+    pub fn parsed<T: FromStr>(&self, long: &str) -> Result<Option<T>, String> {
+        self.value(long)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid value `{value}` for `{long}`"))
+            })
+            .transpose()
+    }
+
+    /// The only positional argument of the command
+    pub fn single_positional(&self, name: &str) -> Result<&str, String> {
+        match self.positionals.as_slice() {
+            [positional] => Ok(positional),
+            [] => Err(format!("missing {name}")),
+            [_, extra, ..] => Err(format!("unexpected argument `{extra}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPECS: [OptionSpec; 3] = [
+        OptionSpec::value("--output", Some("-o")),
+        OptionSpec::value("--width", None),
+        OptionSpec::flag("--stats", None),
+    ];
+
+    fn parse(arguments: &[&str]) -> Result<Args, String> {
+        let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+        Args::parse(&arguments, &SPECS)
+    }
+
+    #[test]
+    fn args_parse_test() {
+        let args = parse(&["a.gas", "-o", "out.gif", "--width=10", "--stats"]).unwrap();
+
+        assert_eq!(args.positionals, vec!["a.gas"]);
+        assert_eq!(args.value("--output"), Some("out.gif"));
+        assert_eq!(args.parsed::<u16>("--width"), Ok(Some(10)));
+        assert!(args.flag("--stats"));
+        assert_eq!(args.parsed::<u16>("--height"), Ok(None));
+    }
+
+    #[test]
+    fn args_parse_errors_test() {
+        assert!(parse(&["--height", "10"]).is_err());
+        assert!(parse(&["--width"]).is_err());
+        assert!(parse(&["--stats=yes"]).is_err());
+        assert!(
+            parse(&["--width", "ten"])
+                .unwrap()
+                .parsed::<u16>("--width")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn args_positionals_test() {
+        let args = parse(&["-", "--", "--stats"]).unwrap();
+
+        assert_eq!(args.positionals, vec!["-", "--stats"]);
+        assert!(!args.flag("--stats"));
+    }
+}
@@ -0,0 +1,243 @@
+use gas::{
+    format::gif::gif::MAX_COLORS,
+    script::{
+        diagnostic::{Severity, has_errors},
+        loader::Loaded,
+    },
+};
+
+use super::{
+    Failure, Reporter,
+    args::{Args, OptionSpec},
+    help_requested, json, read_script,
+};
+
+pub const USAGE: &str = "\
+Usage: gas check <scripts...> [options]
+
+Parse, compile and schedule the scripts without rendering them, then print their
+diagnostics and a summary of each one.
+
+Options:
+        --format <format>    `text` or `json` (one object per script and line), defaults to text";
+
+const OPTIONS: [OptionSpec; 1] = [OptionSpec::value("--format", None)];
+
+enum Format {
+    Text,
+    Json,
+}
+
+struct Summary {
+    objects: usize,
+    duration: f64,
+    frames: u32,
+    colors: usize,
+}
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let args = Args::parse(arguments, &OPTIONS).map_err(Failure::Usage)?;
+    let format = match args.value("--format") {
+        None | Some("text") => Format::Text,
+        Some("json") => Format::Json,
+        Some(other) => {
+            return Err(Failure::Usage(format!(
+                "unknown format `{other}`, expected `text` or `json`"
+            )));
+        }
+    };
+    if args.positionals.is_empty() {
+        return Err(Failure::Usage("missing <scripts...>".to_string()));
+    }
+
+    let mut unreadable = 0;
+    let mut failed = 0;
+
+    for script in &args.positionals {
+        let loaded = match read_script(script) {
+            Ok(loaded) => loaded,
+            Err(message) => {
+                match format {
+                    Format::Text => reporter.failure(&Failure::Io(message)),
+                    Format::Json => println!(
+                        "{{\"file\":{},\"ok\":false,\"error\":{},\"diagnostics\":[],\"summary\":null}}",
+                        json::string(script),
+                        json::string(&message),
+                    ),
+                }
+                unreadable += 1;
+                continue;
+            }
+        };
+
+        if has_errors(&loaded.diagnostics) {
+            failed += 1;
+        }
+
+        match format {
+            Format::Text => report_text(script, &loaded, reporter),
+            Format::Json => report_json(script, &loaded),
+        }
+    }
+
+    if let Format::Text = format
+        && args.positionals.len() > 1
+    {
+        println!(
+            "Checked {} scripts: {} passed, {} failed",
+            args.positionals.len(),
+            args.positionals.len() - failed - unreadable,
+            failed + unreadable,
+        );
+    }
+
+    if unreadable > 0 {
+        Err(Failure::Io(format!(
+            "{unreadable} script(s) could not be read"
+        )))
+    } else if failed > 0 {
+        Err(Failure::Script)
+    } else {
+        Ok(())
+    }
+}
+
+fn summary(loaded: &Loaded) -> Option<Summary> {
+    loaded.program.as_ref().map(|program| Summary {
+        objects: program.shapes.len(),
+        duration: program.duration,
+        frames: program.frame_count(),
+        colors: program.estimated_color_count(),
+    })
+}
+
+fn report_text(script: &str, loaded: &Loaded, reporter: &Reporter) {
+    reporter.diagnostics(&loaded.sources, &loaded.diagnostics);
+    print!("{}", text_summary(script, loaded));
+}
+
+/// Summary line of a script, followed by a note when its colors do not fit in a table
+fn text_summary(script: &str, loaded: &Loaded) -> String {
+    let count = |severity| {
+        loaded
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+
+    match summary(loaded) {
+        Some(summary) => {
+            let mut text = format!(
+                "{script}: ok, {} object(s), {}s, {} frame(s), at most {} color(s), {warnings} warning(s)\n",
+                summary.objects, summary.duration, summary.frames, summary.colors,
+            );
+            if summary.colors > MAX_COLORS {
+                text.push_str(&format!(
+                    "{script}: note: a GIF color table holds {MAX_COLORS} colors, frames using \
+                     others get a local color table\n"
+                ));
+            }
+            text
+        }
+        None => format!("{script}: failed, {errors} error(s), {warnings} warning(s)\n"),
+    }
+}
+
+fn report_json(script: &str, loaded: &Loaded) {
+    println!("{}", json_summary(script, loaded));
+}
+
+/// Object of a script with its diagnostics and summary
+fn json_summary(script: &str, loaded: &Loaded) -> String {
+    let diagnostics: Vec<String> = loaded
+        .diagnostics
+        .iter()
+        .map(|diagnostic| json::diagnostic(&loaded.sources, diagnostic))
+        .collect();
+
+    let summary = match summary(loaded) {
+        Some(summary) => format!(
+            "{{\"objects\":{},\"duration\":{},\"frames\":{},\"colors\":{}}}",
+            summary.objects, summary.duration, summary.frames, summary.colors,
+        ),
+        None => "null".to_string(),
+    };
+
+    format!(
+        "{{\"file\":{},\"ok\":{},\"diagnostics\":[{}],\"summary\":{summary}}}",
+        json::string(script),
+        loaded.program.is_some(),
+        diagnostics.join(","),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use gas::script::loader::load_source;
+
+    use super::*;
+    use crate::cli::MessageFormat;
+
+    fn loaded(source: &str) -> Loaded {
+        load_source(PathBuf::from("a.gas"), source.to_string())
+    }
+
+    #[test]
+    fn summary_test() {
+        let ok = loaded("---\nfps: 10\n---\nfade ANIM\n    color red\nr RECT\nfade(r)\n");
+        assert_eq!(
+            text_summary("a.gas", &ok),
+            "a.gas: ok, 1 object(s), 1s, 11 frame(s), at most 12 color(s), 0 warning(s)\n"
+        );
+        assert_eq!(
+            json_summary("a.gas", &ok),
+            "{\"file\":\"a.gas\",\"ok\":true,\"diagnostics\":[],\"summary\":\
+             {\"objects\":1,\"duration\":1,\"frames\":11,\"colors\":12}}"
+        );
+
+        let failing = loaded("fade ANIM\n    color red\nfade(r)\n");
+        assert_eq!(
+            text_summary("a.gas", &failing),
+            "a.gas: failed, 1 error(s), 0 warning(s)\n"
+        );
+        let json = json_summary("a.gas", &failing);
+        assert!(json.starts_with("{\"file\":\"a.gas\",\"ok\":false,\"diagnostics\":[{"));
+        assert!(json.ends_with("\"summary\":null}"));
+    }
+
+    #[test]
+    fn run_test() {
+        let directory = std::env::temp_dir().join("gas_check_run_test");
+        fs::create_dir_all(&directory).unwrap();
+        let script = |name: &str, source: &str| {
+            let path = directory.join(name);
+            fs::write(&path, source).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let ok = script("ok.gas", "fade ANIM\n    color red\nr RECT\nfade(r)\n");
+        let failing = script("failing.gas", "fade ANIM\n    color red\nfade(r)\n");
+        let missing = directory.join("missing.gas").to_string_lossy().into_owned();
+
+        let reporter = Reporter::new(MessageFormat::Json);
+        let check = |arguments: &[&str]| {
+            let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+            run(&arguments, &reporter).map_err(|failure| failure.exit_code())
+        };
+
+        assert_eq!(check(&[&ok]), Ok(()));
+        assert_eq!(check(&[&ok, &failing]), Err(1));
+        assert_eq!(check(&[&ok, "--format", "json", &failing]), Err(1));
+        // An unreadable script takes over failing ones
+        assert_eq!(check(&[&failing, &missing]), Err(3));
+        assert_eq!(check(&[&missing, "--format", "json"]), Err(3));
+        assert_eq!(check(&[&ok, "--format", "yaml"]), Err(2));
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use gas::render::{frame_to_gif, frame_to_ppm};
+
+use super::{
+    Failure, Reporter, STDIO,
+    args::{Args, OptionSpec},
+    help_requested, load_program, output_name,
+    render::{OPTIONS as RENDER_OPTIONS, apply_overrides, render_options},
+    write_output,
+};
+
+pub const USAGE: &str = "\
+Usage: gas frame <script> (--at <time> | --frame <index>) [options]
+
+Render a single frame of the script to an image, without rendering the frames before it.
+
+Options:
+        --at <time>        Time of the frame, eg. `1.2s` or `300ms`
+        --frame <index>    Index of the frame, starting at 0
+    -o, --output <file>    Output image, `.ppm` or `.gif`, defaults to the script name
+                           followed by the frame index. `-` writes a PPM to the standard
+                           output
+        --width <px>       Override the canvas width
+        --height <px>      Override the canvas height
+        --fps <fps>        Override the frame rate
+        --colors <policy>  `local`, `quantize` or `error` for a GIF with more than 256
+                           colors, as for `gas render`
+        --kmeans <rounds>  Rounds of k-means refining the quantized palette
+        --dither <method>  Dithering of a quantized GIF: `floyd-steinberg` (the default),
+                           `bayer4`, `bayer8` or `none`
+        --interlace        Write the rows of a GIF interlaced
+        --pixel-aspect <ratio>
+                           Width of the pixels of a GIF over their height, eg. `8:7`";
+
+/// Options of `gas render` about the frames of an animation, which a single frame has no
+/// use for
+const ANIMATION_OPTIONS: [&str; 4] = ["--palette", "--dither-lock", "--delta", "--metadata"];
+
+enum ImageFormat {
+    Ppm,
+    Gif,
+}
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let options: Vec<OptionSpec> = RENDER_OPTIONS
+        .into_iter()
+        .filter(|option| !ANIMATION_OPTIONS.contains(&option.long))
+        .chain([
+            OptionSpec::value("--at", None),
+            OptionSpec::value("--frame", None),
+        ])
+        .collect();
+    let args = Args::parse(arguments, &options).map_err(Failure::Usage)?;
+    let script = args.single_positional("script").map_err(Failure::Usage)?;
+
+    let mut program = load_program(script, reporter)?;
+    apply_overrides(&mut program, &args)?;
+    let options = render_options(&args)?;
+
+    let index = match (args.value("--at"), args.parsed::<u32>("--frame")) {
+        (Some(time), Ok(None)) => program.to_frame(parse_time(time).map_err(Failure::Usage)?),
+        (None, Ok(Some(index))) => index,
+        (None, Ok(None)) => {
+            return Err(Failure::Usage("expected `--at` or `--frame`".to_string()));
+        }
+        (Some(_), Ok(Some(_))) => {
+            return Err(Failure::Usage(
+                "`--at` and `--frame` cannot be used together".to_string(),
+            ));
+        }
+        (_, Err(message)) => return Err(Failure::Usage(message)),
+    };
+
+    let frame_count = program.frame_count();
+    if index >= frame_count {
+        return Err(Failure::Usage(format!(
+            "frame {index} is past the end of the animation, which has {frame_count} frames"
+        )));
+    }
+
+    let output = match args.value("--output") {
+        Some(output) => output.to_string(),
+        None => default_output(script, index),
+    };
+    let format = image_format(&output).map_err(Failure::Usage)?;
+
+    let frame = program.scene().render_frame(index);
+    match format {
+        ImageFormat::Ppm => {
+            let ppm = frame_to_ppm(&frame, &program.settings);
+            write_output(&output, |writer| ppm.write_to(writer))?;
+        }
+        ImageFormat::Gif => {
+            let mut gif = frame_to_gif(&frame, &program.settings);
+            gif.set_color_policy(options.colors)
+                .set_quantizer(options.quantizer)
+                .set_dither(options.dither(1))
+                .set_interlaced(options.interlaced)
+                .set_pixel_aspect_ratio(options.pixel_aspect_ratio);
+            write_output(&output, |writer| gif.write_to(writer))?;
+        }
+    }
+    reporter.status(&format!(
+        "Rendered frame {index} to {}",
+        output_name(&output)
+    ));
+
+    Ok(())
+}
+
+/// Time in seconds, given in `s` or `ms`, seconds being the default
+fn parse_time(value: &str) -> Result<f64, String> {
+    let (number, scale) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 0.001)
+    } else {
+        (value.strip_suffix('s').unwrap_or(value), 1.0)
+    };
+
+    match number.parse::<f64>() {
+        Ok(time) if time.is_finite() && time >= 0.0 => Ok(time * scale),
+        _ => Err(format!(
+            "invalid time `{value}`, expected eg. `1.2s` or `300ms`"
+        )),
+    }
+}
+
+fn image_format(output: &str) -> Result<ImageFormat, String> {
+    if output == STDIO {
+        return Ok(ImageFormat::Ppm);
+    }
+
+    let extension = Path::new(output)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    match extension.as_deref() {
+        Some("ppm") => Ok(ImageFormat::Ppm),
+        Some("gif") => Ok(ImageFormat::Gif),
+        _ => Err(format!(
+            "cannot guess the format of `{output}`, expected a `.ppm` or `.gif` file"
+        )),
+    }
+}
+
+/// `<script>-<index>.ppm` next to the script
+fn default_output(script_path: &str, index: u32) -> String {
+    let script_path = Path::new(script_path);
+    let stem = script_path
+        .file_stem()
+        .map_or("frame".into(), |stem| stem.to_string_lossy());
+
+    script_path
+        .with_file_name(format!("{stem}-{index}.ppm"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::MessageFormat;
+
+    #[test]
+    fn parse_time_test() {
+        assert_eq!(parse_time("1.2s"), Ok(1.2));
+        assert_eq!(parse_time("300ms"), Ok(0.3));
+        assert_eq!(parse_time("2"), Ok(2.0));
+        assert!(parse_time("-1s").is_err());
+        assert!(parse_time("soon").is_err());
+    }
+
+    #[test]
+    fn run_animation_options_test() {
+        let reporter = Reporter::new(MessageFormat::Human);
+        for option in ["--delta", "--metadata", "--palette"] {
+            let arguments: Vec<String> = ["a.gas", "--frame", "0", option, "full"]
+                .iter()
+                .map(|a| a.to_string())
+                .collect();
+            assert!(matches!(
+                run(&arguments, &reporter),
+                Err(Failure::Usage(message)) if message.contains(option)
+            ));
+        }
+    }
+
+    #[test]
+    fn default_output_test() {
+        assert_eq!(default_output("demo/intro.gas", 36), "demo/intro-36.ppm");
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Files matching a pattern, sorted. `*` matches any part of a name and `?` a single
+/// character, while `**` matches any number of directories. Hidden files are only
+/// matched by patterns starting with a `.`.
+pub fn glob(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let (base, pattern) = match pattern.strip_prefix('/') {
+        Some(pattern) => (PathBuf::from("/"), pattern),
+        None => (PathBuf::new(), pattern),
+    };
+    let components: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+
+    let mut paths = Vec::new();
+    walk(&base, &components, &mut paths)?;
+    paths.sort();
+    paths.dedup();
+
+    Ok(paths)
+}
+
+fn walk(directory: &Path, components: &[&str], paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    let Some((component, rest)) = components.split_first() else {
+        if directory.is_file() {
+            paths.push(directory.to_path_buf());
+        }
+        return Ok(());
+    };
+
+    if *component == "**" {
+        // Either no directory at all, or one more directory still matched by `**`
+        walk(directory, rest, paths)?;
+        for entry in entries(directory)? {
+            if entry.is_dir() && !is_hidden(&entry) {
+                walk(&entry, components, paths)?;
+            }
+        }
+    } else if !component.contains(['*', '?']) {
+        let path = directory.join(component);
+        if path.exists() {
+            walk(&path, rest, paths)?;
+        }
+    } else {
+        for entry in entries(directory)? {
+            let name = entry.file_name().unwrap_or_default().to_string_lossy();
+            if (component.starts_with('.') || !name.starts_with('.')) && matches(component, &name) {
+                walk(&entry, rest, paths)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Entries of a directory, `.` standing for the current one
+fn entries(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let read = if directory.as_os_str().is_empty() {
+        fs::read_dir(".")?
+    } else {
+        match fs::read_dir(directory) {
+            Ok(read) => read,
+            // Matching names against a file
+            Err(error) if error.kind() == io::ErrorKind::NotADirectory => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        }
+    };
+
+    read.map(|entry| entry.map(|entry| directory.join(entry.file_name())))
+        .collect()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Whether a name matches a pattern made of `*`, `?` and plain characters
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Position after the last `*` and the character of the name it matched up to, to
+    // backtrack to when the rest does not match
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some('?') => (p, n) = (p + 1, n + 1),
+            Some(c) if *c == name[n] => (p, n) = (p + 1, n + 1),
+            _ => match star {
+                Some((after_star, matched)) => {
+                    (p, n) = (after_star, matched + 1);
+                    star = Some((after_star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_test() {
+        assert!(matches("*.gas", "intro.gas"));
+        assert!(matches("in?ro*", "intro.gas"));
+        assert!(matches("*a*s", "abcas"));
+        assert!(!matches("*.gas", "intro.gif"));
+        assert!(!matches("a?", "a"));
+    }
+
+    #[test]
+    fn glob_test() {
+        let directory = std::env::temp_dir().join("gas_glob_test");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("a/b")).unwrap();
+        fs::create_dir_all(directory.join(".hidden")).unwrap();
+        for file in [
+            "one.gas",
+            "a/two.gas",
+            "a/b/three.gas",
+            "a/b/skip.gif",
+            ".hidden/four.gas",
+        ] {
+            fs::write(directory.join(file), "").unwrap();
+        }
+
+        let pattern = format!("{}/**/*.gas", directory.display());
+        assert_eq!(
+            glob(&pattern).unwrap(),
+            vec![
+                directory.join("a/b/three.gas"),
+                directory.join("a/two.gas"),
+                directory.join("one.gas"),
+            ]
+        );
+
+        let pattern = format!("{}/a/*.gas", directory.display());
+        assert_eq!(glob(&pattern).unwrap(), vec![directory.join("a/two.gas")]);
+    }
+}
@@ -0,0 +1,171 @@
+use gas::{
+    format::gif::{decoder::decode_without_images, metadata::Metadata},
+    render::Provenance,
+};
+
+use super::{
+    Failure, Reporter,
+    args::{Args, OptionSpec},
+    help_requested, json,
+};
+
+pub const USAGE: &str = "\
+Usage: gas inspect <gifs...> [options]
+
+Print the comments, application data and plain texts of GIFs, including the provenance
+`gas render` writes: the version of GAS, the script, a hash of its sources and the
+settings of the render.
+
+Options:
+        --format <format>    `text` or `json` (one object per GIF and line), defaults to text";
+
+const OPTIONS: [OptionSpec; 1] = [OptionSpec::value("--format", None)];
+
+enum Format {
+    Text,
+    Json,
+}
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let args = Args::parse(arguments, &OPTIONS).map_err(Failure::Usage)?;
+    let format = match args.value("--format") {
+        None | Some("text") => Format::Text,
+        Some("json") => Format::Json,
+        Some(other) => {
+            return Err(Failure::Usage(format!(
+                "unknown format `{other}`, expected `text` or `json`"
+            )));
+        }
+    };
+    if args.positionals.is_empty() {
+        return Err(Failure::Usage("missing <gifs...>".to_string()));
+    }
+
+    let mut unreadable = 0;
+    for gif in &args.positionals {
+        let metadata = std::fs::read(gif)
+            .map_err(|error| format!("cannot read {gif}: {error}"))
+            .and_then(|bytes| {
+                decode_without_images(&bytes)
+                    .map(|gif| gif.metadata().clone())
+                    .map_err(|error| format!("cannot read {gif}: {error}"))
+            });
+        match (metadata, &format) {
+            (Ok(metadata), Format::Text) => report_metadata_text(gif, &metadata),
+            (Ok(metadata), Format::Json) => report_metadata_json(gif, &metadata),
+            (Err(message), Format::Text) => {
+                reporter.failure(&Failure::Io(message));
+                unreadable += 1;
+            }
+            (Err(message), Format::Json) => {
+                println!(
+                    "{{\"file\":{},\"ok\":false,\"error\":{},\"comments\":[],\"applications\":[],\"provenance\":null}}",
+                    json::string(gif),
+                    json::string(&message),
+                );
+                unreadable += 1;
+            }
+        }
+    }
+
+    if unreadable > 0 {
+        Err(Failure::Io(format!(
+            "{unreadable} GIF(s) could not be read"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Provenance of the GIF, from its GAS application data or else from a comment
+fn provenance(metadata: &Metadata) -> Option<Provenance> {
+    metadata
+        .applications
+        .iter()
+        .filter(|application| application.is_gas())
+        .map(|application| String::from_utf8_lossy(&application.data).into_owned())
+        .chain(metadata.comments.iter().cloned())
+        .find_map(|text| Provenance::parse(&text))
+}
+
+fn report_metadata_text(gif: &str, metadata: &Metadata) {
+    if metadata.comments.is_empty()
+        && metadata.applications.is_empty()
+        && metadata.plain_texts.is_empty()
+    {
+        println!("{gif}: no metadata");
+        return;
+    }
+
+    println!("{gif}:");
+    for comment in &metadata.comments {
+        println!("  comment:");
+        for line in comment.lines() {
+            println!("    {line}");
+        }
+    }
+    for application in &metadata.applications {
+        if application.is_gas() {
+            println!("  application {}:", application.name());
+            for line in String::from_utf8_lossy(&application.data).lines() {
+                println!("    {line}");
+            }
+        } else {
+            println!(
+                "  application {}: {} byte(s)",
+                application.name(),
+                application.data.len()
+            );
+        }
+    }
+    for plain_text in &metadata.plain_texts {
+        println!("  plain text: {}", plain_text.text);
+    }
+}
+
+fn report_metadata_json(gif: &str, metadata: &Metadata) {
+    let comments: Vec<String> = metadata
+        .comments
+        .iter()
+        .map(|comment| json::string(comment))
+        .collect();
+    let applications: Vec<String> = metadata
+        .applications
+        .iter()
+        .map(|application| {
+            format!(
+                "{{\"name\":{},\"size\":{}}}",
+                json::string(&application.name()),
+                application.data.len()
+            )
+        })
+        .collect();
+    let provenance = match provenance(metadata) {
+        Some(provenance) => {
+            let settings: Vec<String> = provenance
+                .settings
+                .iter()
+                .map(|(name, value)| format!("{}:{}", json::string(name), json::string(value)))
+                .collect();
+            format!(
+                "{{\"version\":{},\"script\":{},\"source_hash\":\"{:016x}\",\"settings\":{{{}}}}}",
+                json::string(&provenance.version),
+                json::string(&provenance.script),
+                provenance.source_hash,
+                settings.join(","),
+            )
+        }
+        None => "null".to_string(),
+    };
+
+    println!(
+        "{{\"file\":{},\"ok\":true,\"comments\":[{}],\"applications\":[{}],\"provenance\":{provenance}}}",
+        json::string(gif),
+        comments.join(","),
+        applications.join(","),
+    );
+}
@@ -0,0 +1,95 @@
+use gas::script::{
+    diagnostic::{Diagnostic, line_column},
+    source::SourceMap,
+};
+
+/// Quote a string as a JSON string
+pub fn string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+
+    for character in value.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            control if control.is_control() => {
+                quoted.push_str(&format!("\\u{:04x}", u32::from(control)));
+            }
+            other => quoted.push(other),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// JSON object of a message that does not point to a script
+pub fn message(severity: &str, code: &str, message: &str) -> String {
+    format!(
+        "{{\"severity\":{},\"code\":{},\"message\":{},\"file\":null,\"span\":null}}",
+        string(severity),
+        string(code),
+        string(message),
+    )
+}
+
+/// JSON object of a diagnostic, with its span relative to the file it points to
+pub fn diagnostic(sources: &SourceMap, diagnostic: &Diagnostic) -> String {
+    let span = sources.local_span(diagnostic.span);
+    let (file, (line, column)) = match sources.file(diagnostic.span.start) {
+        Some(file) => (
+            string(&file.path.to_string_lossy()),
+            line_column(&file.source, span.start),
+        ),
+        None => ("null".to_string(), (0, 0)),
+    };
+
+    format!(
+        "{{\"severity\":\"{}\",\"code\":\"{}\",\"message\":{},\"file\":{file},\"line\":{line},\"column\":{column},\"span\":{{\"start\":{},\"end\":{}}}}}",
+        diagnostic.severity,
+        diagnostic.code,
+        string(&diagnostic.message),
+        span.start,
+        span.end,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use gas::script::diagnostic::{Code, Span};
+
+    use super::*;
+
+    #[test]
+    fn string_test() {
+        assert_eq!(string("a \"b\"\\\n\u{1}"), "\"a \\\"b\\\"\\\\\\n\\u0001\"");
+    }
+
+    #[test]
+    fn message_test() {
+        assert_eq!(
+            message("error", "io", "cannot read `a.gas`"),
+            "{\"severity\":\"error\",\"code\":\"io\",\"message\":\"cannot read `a.gas`\",\"file\":null,\"span\":null}"
+        );
+    }
+
+    #[test]
+    fn diagnostic_test() {
+        let mut sources = SourceMap::new();
+        sources.add(PathBuf::from("a.gas"), "abc".to_string());
+        sources.add(PathBuf::from("b.gas"), "de\nf".to_string());
+
+        assert_eq!(
+            diagnostic(
+                &sources,
+                &Diagnostic::warning(Code::Syntax, "oops", Span::new(7, 8))
+            ),
+            "{\"severity\":\"warning\",\"code\":\"syntax\",\"message\":\"oops\",\"file\":\"b.gas\",\"line\":2,\"column\":1,\"span\":{\"start\":3,\"end\":4}}"
+        );
+    }
+}
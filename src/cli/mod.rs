@@ -0,0 +1,426 @@
+use std::{
+    any::Any,
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use gas::{
+    format::gif::gif::GifError,
+    script::{
+        compiler::Program,
+        diagnostic::Diagnostic,
+        loader::{Loaded, load, load_source},
+        source::SourceMap,
+    },
+};
+
+pub mod args;
+pub mod check;
+pub mod frame;
+pub mod glob;
+pub mod inspect;
+pub mod json;
+pub mod new;
+pub mod preview;
+pub mod render;
+pub mod render_all;
+pub mod validate;
+pub mod watch;
+
+pub const USAGE: &str = "\
+Usage: gas <command> [options]
+
+Commands:
+    new <name>         Create a project from a template
+    render <script>    Render a script to a GIF
+    render-all         Render the scripts of a manifest or matching a pattern
+    watch <script>     Render a script again every time it changes
+    frame <script>     Render a single frame to an image
+    preview <script>   Play a script in the terminal
+    check <scripts>    Report the errors and a summary of scripts without rendering them
+    validate <gifs>    Check GIFs against the GIF89a spec
+    inspect <gifs>     Print the metadata of GIFs, such as the script they were rendered from
+    help               Print this message
+
+Options of every command:
+    --message-format <format>    `human` (the default) or `json` to print the errors
+                                 as JSON objects, one per line
+
+Run `gas <command> --help` for the options of a command.";
+
+/// Script or output path standing for the standard input or output
+pub const STDIO: &str = "-";
+
+/// Path of a script read from the standard input, as shown in diagnostics
+const STDIN_PATH: &str = "<stdin>";
+
+/// Reason a command failed
+#[derive(Debug)]
+pub enum Failure {
+    /// Invalid command line
+    Usage(String),
+    /// The script has errors, already reported while loading it
+    Script,
+    /// Reading or writing a file failed
+    Io(String),
+    /// The animation goes beyond what the output format can hold, eg. too many colors
+    Encoder(String),
+    /// A GIF given to `validate` breaks the spec
+    Invalid(String),
+    /// The renderer crashed, which is a bug of GAS
+    Internal(String),
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Script => 1,
+            Failure::Usage(_) => 2,
+            Failure::Io(_) => 3,
+            Failure::Encoder(_) => 4,
+            Failure::Invalid(_) => 5,
+            // Same as an uncaught panic
+            Failure::Internal(_) => 101,
+        }
+    }
+
+    /// Stable name of the failure in machine-readable messages
+    pub fn code(&self) -> &'static str {
+        match self {
+            Failure::Script => "script",
+            Failure::Usage(_) => "usage",
+            Failure::Io(_) => "io",
+            Failure::Encoder(_) => "encoder",
+            Failure::Invalid(_) => "invalid",
+            Failure::Internal(_) => "internal",
+        }
+    }
+}
+
+/// Format of the messages printed on the standard error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageFormat {
+    Human,
+    /// One JSON object per line
+    Json,
+}
+
+/// Prints diagnostics, failures and progress on the standard error
+pub struct Reporter {
+    format: MessageFormat,
+}
+
+impl Reporter {
+    pub fn new(format: MessageFormat) -> Self {
+        Reporter { format }
+    }
+
+    pub fn format(&self) -> MessageFormat {
+        self.format
+    }
+
+    pub fn diagnostics(&self, sources: &SourceMap, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            match self.format {
+                MessageFormat::Human => eprintln!("{}\n", sources.display(diagnostic)),
+                MessageFormat::Json => eprintln!("{}", json::diagnostic(sources, diagnostic)),
+            }
+        }
+    }
+
+    /// Report a failure, script failures being reported by their diagnostics
+    pub fn failure(&self, failure: &Failure) {
+        let message = match failure {
+            Failure::Usage(message)
+            | Failure::Io(message)
+            | Failure::Encoder(message)
+            | Failure::Invalid(message)
+            | Failure::Internal(message) => message,
+            Failure::Script => return,
+        };
+
+        match self.format {
+            MessageFormat::Human => eprintln!("error: {message}"),
+            MessageFormat::Json => eprintln!("{}", json::message("error", failure.code(), message)),
+        }
+    }
+
+    /// Report the progress of a command
+    pub fn status(&self, message: &str) {
+        match self.format {
+            MessageFormat::Human => eprintln!("{message}"),
+            MessageFormat::Json => eprintln!("{}", json::message("note", "status", message)),
+        }
+    }
+}
+
+pub fn run(arguments: &[String]) -> ExitCode {
+    let (format, arguments) = match message_format(arguments) {
+        Ok(split) => split,
+        Err(message) => {
+            let failure = Failure::Usage(message);
+            Reporter::new(MessageFormat::Human).failure(&failure);
+            return ExitCode::from(failure.exit_code());
+        }
+    };
+    let reporter = Reporter::new(format);
+
+    let Some((command, arguments)) = arguments.split_first() else {
+        let failure = Failure::Usage(format!("missing command\n\n{USAGE}"));
+        reporter.failure(&failure);
+        return ExitCode::from(failure.exit_code());
+    };
+
+    let result = match command.as_str() {
+        "new" => new::run(arguments, &reporter),
+        "render" => render::run(arguments, &reporter),
+        "render-all" => render_all::run(arguments, &reporter),
+        "watch" => watch::run(arguments, &reporter),
+        "frame" => frame::run(arguments, &reporter),
+        "preview" => preview::run(arguments, &reporter),
+        "check" => check::run(arguments, &reporter),
+        "validate" => validate::run(arguments, &reporter),
+        "inspect" => inspect::run(arguments, &reporter),
+        "help" | "-h" | "--help" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        "-V" | "--version" => {
+            println!("gas {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        other => Err(Failure::Usage(format!(
+            "unknown command `{other}`\n\n{USAGE}"
+        ))),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            reporter.failure(&failure);
+            ExitCode::from(failure.exit_code())
+        }
+    }
+}
+
+/// Take `--message-format` out of the arguments since every command accepts it
+fn message_format(arguments: &[String]) -> Result<(MessageFormat, Vec<String>), String> {
+    let mut format = None;
+    let mut remaining = Vec::with_capacity(arguments.len());
+    let mut arguments = arguments.iter();
+
+    while let Some(argument) = arguments.next() {
+        let value = match argument.as_str() {
+            "--" => {
+                remaining.push(argument.clone());
+                remaining.extend(arguments.by_ref().cloned());
+                break;
+            }
+            "--message-format" => arguments
+                .next()
+                .ok_or("option `--message-format` expects a value")?
+                .as_str(),
+            other => match other.strip_prefix("--message-format=") {
+                Some(value) => value,
+                None => {
+                    remaining.push(argument.clone());
+                    continue;
+                }
+            },
+        };
+
+        format = Some(match value {
+            "human" => MessageFormat::Human,
+            "json" => MessageFormat::Json,
+            other => {
+                return Err(format!(
+                    "unknown message format `{other}`, expected `human` or `json`"
+                ));
+            }
+        });
+    }
+
+    Ok((format.unwrap_or(MessageFormat::Human), remaining))
+}
+
+/// Whether the command was asked for its help, in which case `usage` is printed
+pub fn help_requested(arguments: &[String], usage: &str) -> bool {
+    let requested = arguments.iter().any(|a| a == "-h" || a == "--help");
+    if requested {
+        println!("{usage}");
+    }
+    requested
+}
+
+/// Read and compile a script and its imports, reporting its diagnostics
+pub fn load_script(path: &str, reporter: &Reporter) -> Result<Loaded, Failure> {
+    let loaded = read_script(path).map_err(Failure::Io)?;
+
+    reporter.diagnostics(&loaded.sources, &loaded.diagnostics);
+
+    Ok(loaded)
+}
+
+/// Read and compile a script and its imports, the script being read from the standard
+/// input when `path` is `-`
+pub fn read_script(path: &str) -> Result<Loaded, String> {
+    if path != STDIO {
+        return load(Path::new(path)).map_err(|error| format!("cannot read `{path}`: {error}"));
+    }
+
+    let mut source = String::new();
+    io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|error| format!("cannot read the standard input: {error}"))?;
+    // Imports are resolved relative to the working directory
+    Ok(load_source(PathBuf::from(STDIN_PATH), source))
+}
+
+/// Read and compile a script, reporting its diagnostics
+pub fn load_program(path: &str, reporter: &Reporter) -> Result<Program, Failure> {
+    load_script(path, reporter)?.program.ok_or(Failure::Script)
+}
+
+/// Write a file next to `output` with `write` then move it in place, so that `output` is
+/// either the previous file or the complete new one.
+pub fn write_atomically(
+    output: &str,
+    write: impl FnOnce(&str) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Failure> {
+    let output_path = Path::new(output);
+    let file_name = output_path
+        .file_name()
+        .ok_or_else(|| Failure::Io(format!("`{output}` is not a file path")))?;
+
+    let mut temporary_name = std::ffi::OsString::from(".");
+    temporary_name.push(file_name);
+    temporary_name.push(".tmp");
+    let temporary = output_path.with_file_name(temporary_name);
+
+    let result = write(&temporary.to_string_lossy())
+        .map_err(|error| write_failure(output, &*error))
+        .and_then(|()| {
+            fs::rename(&temporary, output_path)
+                .map_err(|error| Failure::Io(format!("cannot write `{output}`: {error}")))
+        });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+/// Write `output` with `write`, atomically for a file or to the standard output for `-`
+pub fn write_output(
+    output: &str,
+    write: impl FnOnce(&mut dyn Write) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Failure> {
+    if output != STDIO {
+        return write_atomically(output, |path| {
+            let mut file = BufWriter::new(File::create(path)?);
+            write(&mut file)?;
+            Ok(file.flush()?)
+        });
+    }
+
+    let mut stdout = io::stdout().lock();
+    if stdout.is_terminal() {
+        return Err(Failure::Usage(
+            "refusing to write binary data to a terminal, redirect the standard output or \
+             use `--output <file>`"
+                .to_string(),
+        ));
+    }
+
+    write(&mut stdout)
+        .and_then(|()| Ok(stdout.flush()?))
+        .map_err(|error| write_failure(output, &*error))
+}
+
+/// Name of an output in messages
+pub fn output_name(output: &str) -> String {
+    match output {
+        STDIO => "the standard output".to_string(),
+        output => output.to_string(),
+    }
+}
+
+/// Failure to write `output`, encoding errors being told apart from IO ones
+fn write_failure(output: &str, error: &(dyn Error + 'static)) -> Failure {
+    let target = match output {
+        STDIO => "to the standard output".to_string(),
+        output => format!("`{output}`"),
+    };
+
+    match error.downcast_ref::<GifError>() {
+        Some(GifError::Io(_)) | None => Failure::Io(format!("cannot write {target}: {error}")),
+        Some(error) => Failure::Encoder(format!("cannot write {target}: {error}")),
+    }
+}
+
+/// Message a panic was raised with
+fn panic_message(payload: &Box<dyn Any + Send>) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("unknown error", String::as_str),
+    }
+}
+
+/// Output path of a script: the script's `filename` relative to the script, or the script
+/// path with a `.gif` extension. A script read from the standard input without a `filename`
+/// is written to the standard output.
+pub fn default_output(script_path: &str, program: &Program) -> String {
+    if script_path == STDIO && program.settings.filename.is_none() {
+        return STDIO.to_string();
+    }
+    let script_path = Path::new(script_path);
+
+    match &program.settings.filename {
+        Some(filename) => script_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(filename)
+            // Drop the `.` of `./animation.gif`
+            .components()
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .into_owned(),
+        None => script_path
+            .with_extension("gif")
+            .to_string_lossy()
+            .into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn message_format_test() {
+        let (format, remaining) =
+            message_format(&strings(&["render", "--message-format", "json", "a.gas"])).unwrap();
+        assert_eq!(format, MessageFormat::Json);
+        assert_eq!(remaining, strings(&["render", "a.gas"]));
+
+        let (format, remaining) =
+            message_format(&strings(&["check", "--", "--message-format=json"])).unwrap();
+        assert_eq!(format, MessageFormat::Human);
+        assert_eq!(
+            remaining,
+            strings(&["check", "--", "--message-format=json"])
+        );
+
+        assert!(message_format(&strings(&["--message-format=xml"])).is_err());
+        assert!(message_format(&strings(&["--message-format"])).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::{fs, path::Path};
+
+use super::{
+    Failure, Reporter,
+    args::{Args, OptionSpec},
+    help_requested,
+};
+
+pub const USAGE: &str = "\
+Usage: gas new <name> [options]
+
+Create the directory <name> with a starter script and a README.
+
+Options:
+        --template <template>    `bounce` (the default), `loader` or `slideshow`";
+
+const OPTIONS: [OptionSpec; 1] = [OptionSpec::value("--template", None)];
+
+/// Replaced by the name of the project in the templates
+const NAME_PLACEHOLDER: &str = "{{name}}";
+
+struct Template {
+    name: &'static str,
+    description: &'static str,
+    script: &'static str,
+}
+
+const TEMPLATES: [Template; 3] = [
+    Template {
+        name: "bounce",
+        description: "a ball falling on the ground then bouncing back up",
+        script: include_str!("templates/bounce.gas"),
+    },
+    Template {
+        name: "loader",
+        description: "three dots lighting up one after the other, as a looping loader",
+        script: include_str!("templates/loader.gas"),
+    },
+    Template {
+        name: "slideshow",
+        description: "slides entering one after the other with a progress marker",
+        script: include_str!("templates/slideshow.gas"),
+    },
+];
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let args = Args::parse(arguments, &OPTIONS).map_err(Failure::Usage)?;
+    let directory = Path::new(args.single_positional("name").map_err(Failure::Usage)?);
+    let template_name = args.value("--template").unwrap_or(TEMPLATES[0].name);
+    let template = TEMPLATES
+        .iter()
+        .find(|template| template.name == template_name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = TEMPLATES.iter().map(|template| template.name).collect();
+            Failure::Usage(format!(
+                "unknown template `{template_name}`, expected one of {}",
+                names.join(", ")
+            ))
+        })?;
+
+    let name = directory
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| Failure::Usage(format!("invalid name `{}`", directory.display())))?;
+
+    if directory.exists() {
+        return Err(Failure::Io(format!(
+            "`{}` already exists",
+            directory.display()
+        )));
+    }
+
+    let script_name = format!("{name}.gas");
+    let files = [
+        (script_name.clone(), script(template, &name)),
+        ("README.md".to_string(), readme(template, &name)),
+    ];
+
+    fs::create_dir_all(directory)
+        .and_then(|()| {
+            files
+                .iter()
+                .try_for_each(|(file, content)| fs::write(directory.join(file), content))
+        })
+        .map_err(|error| {
+            Failure::Io(format!("cannot create `{}`: {error}", directory.display()))
+        })?;
+
+    reporter.status(&format!(
+        "Created {} from the `{}` template, render it with `gas render {}`",
+        directory.display(),
+        template.name,
+        directory.join(script_name).display()
+    ));
+
+    Ok(())
+}
+
+fn script(template: &Template, name: &str) -> String {
+    template.script.replace(NAME_PLACEHOLDER, name)
+}
+
+fn readme(template: &Template, name: &str) -> String {
+    format!(
+        "\
+# {name}
+
+Animation made with GAS from the `{}` template: {}.
+
+## Usage
+
+```
+gas preview {name}.gas    # play it in the terminal
+gas render {name}.gas     # write {name}.gif
+gas watch {name}.gas      # render it again on every change
+```
+",
+        template.name, template.description
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use gas::script::compile_source;
+
+    use super::*;
+
+    #[test]
+    fn templates_compile_test() {
+        for template in &TEMPLATES {
+            let (program, diagnostics) = compile_source(&script(template, "demo"));
+
+            assert!(
+                diagnostics.is_empty(),
+                "`{}` template: {diagnostics:?}",
+                template.name
+            );
+            let program = program.unwrap();
+            assert_eq!(program.settings.filename.as_deref(), Some("./demo.gif"));
+            assert!(program.frame_count() > 1);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use gas::{common::types::Pixel, render::frame_pixels};
+
+use super::{
+    Failure, Reporter,
+    args::{Args, OptionSpec},
+    help_requested, load_program,
+    render::apply_overrides,
+};
+
+pub const USAGE: &str = "\
+Usage: gas preview <script> [options]
+
+Play the animation in the terminal, two pixels per character. Requires a terminal
+supporting truecolor.
+
+Keys:
+    space      Pause or resume
+    n, right   Pause and show the next frame
+    b, left    Pause and show the previous frame
+    q          Quit
+
+Options:
+        --width <px>     Override the canvas width
+        --height <px>    Override the canvas height
+        --fps <fps>      Override the frame rate
+        --once           Stop after the last frame instead of looping";
+
+const OPTIONS: [OptionSpec; 4] = [
+    OptionSpec::value("--width", None),
+    OptionSpec::value("--height", None),
+    OptionSpec::value("--fps", None),
+    OptionSpec::flag("--once", None),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    TogglePause,
+    Next,
+    Previous,
+    Quit,
+}
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let args = Args::parse(arguments, &OPTIONS).map_err(Failure::Usage)?;
+    let script = args.single_positional("script").map_err(Failure::Usage)?;
+
+    let mut program = load_program(script, reporter)?;
+    apply_overrides(&mut program, &args)?;
+
+    let settings = &program.settings;
+    let frames: Vec<String> = program
+        .scene()
+        .render()
+        .iter()
+        .map(|frame| draw(&frame_pixels(frame, settings.background), settings.width))
+        .collect();
+    let frame_duration = Duration::from_secs_f64(1.0 / f64::from(settings.fps));
+
+    let terminal = Terminal::enter();
+    let keys = terminal.interactive.then(read_keys);
+
+    play(&frames, frame_duration, keys.as_ref(), args.flag("--once"))
+        .map_err(|error| Failure::Io(format!("cannot write to the terminal: {error}")))
+}
+
+fn play(
+    frames: &[String],
+    frame_duration: Duration,
+    keys: Option<&Receiver<Key>>,
+    once: bool,
+) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    let mut index = 0;
+    let mut paused = false;
+    let mut next = Instant::now() + frame_duration;
+
+    loop {
+        let state = if paused { "paused " } else { "playing" };
+        write!(stdout, "\x1B[H{}", frames[index])?;
+        write!(stdout, "frame {}/{} {state}", index + 1, frames.len())?;
+        if keys.is_some() {
+            write!(stdout, "  space: pause  n: next  b: previous  q: quit")?;
+        }
+        write!(stdout, "\x1B[K")?;
+        stdout.flush()?;
+
+        // Wait for the next frame, or for a key when paused
+        let timeout = next.saturating_duration_since(Instant::now());
+        let key = match keys {
+            // Nothing can resume the playback once the standard input is closed
+            Some(keys) if paused => Some(keys.recv().unwrap_or(Key::Quit)),
+            Some(keys) => match keys.recv_timeout(timeout) {
+                Ok(key) => Some(key),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(timeout);
+                    None
+                }
+            },
+            None => {
+                thread::sleep(timeout);
+                None
+            }
+        };
+
+        match key {
+            Some(Key::Quit) => break,
+            Some(Key::TogglePause) => {
+                paused = !paused;
+                next = Instant::now() + frame_duration;
+            }
+            Some(Key::Next) => {
+                paused = true;
+                index = (index + 1) % frames.len();
+            }
+            Some(Key::Previous) => {
+                paused = true;
+                index = (index + frames.len() - 1) % frames.len();
+            }
+            None if Instant::now() < next => {}
+            None => {
+                if index + 1 == frames.len() && once {
+                    break;
+                }
+                index = (index + 1) % frames.len();
+
+                // Keep the frame rate exact, unless the terminal cannot keep up
+                next += frame_duration;
+                if next < Instant::now() {
+                    next = Instant::now() + frame_duration;
+                }
+            }
+        }
+    }
+
+    writeln!(stdout)
+}
+
+/// Draw pixels as rows of half blocks, the upper pixel of each character being its
+/// foreground color and the lower one its background color.
+fn draw(pixels: &[Pixel], width: usize) -> String {
+    let rows: Vec<&[Pixel]> = pixels.chunks(width.max(1)).collect();
+    let mut output = String::new();
+
+    for pair in rows.chunks(2) {
+        let mut previous = None;
+
+        for (x, top) in pair[0].iter().enumerate() {
+            let bottom = pair.get(1).map(|row| &row[x]);
+            if previous == Some((top, bottom)) {
+                output.push('▀');
+                continue;
+            }
+            previous = Some((top, bottom));
+
+            let _ = write!(output, "\x1B[38;2;{};{};{}m", top.r, top.g, top.b);
+            match bottom {
+                Some(bottom) => {
+                    let _ = write!(output, "\x1B[48;2;{};{};{}m", bottom.r, bottom.g, bottom.b);
+                }
+                // Odd number of rows, the last one only fills the upper half
+                None => output.push_str("\x1B[49m"),
+            }
+            output.push('▀');
+        }
+
+        output.push_str("\x1B[0m\n");
+    }
+
+    output
+}
+
+/// Read keys from the standard input on a separate thread
+fn read_keys() -> Receiver<Key> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut escape = Vec::new();
+
+        for byte in io::stdin().lock().bytes() {
+            let Ok(byte) = byte else { break };
+
+            // Arrow keys are sent as `ESC [ C` and `ESC [ D`
+            if byte == 0x1B || !escape.is_empty() {
+                escape.push(byte);
+                let key = match escape.as_slice() {
+                    [0x1B] | [0x1B, b'['] => continue,
+                    [0x1B, b'[', b'C'] => Some(Key::Next),
+                    [0x1B, b'[', b'D'] => Some(Key::Previous),
+                    _ => None,
+                };
+                escape.clear();
+                if let Some(key) = key
+                    && sender.send(key).is_err()
+                {
+                    break;
+                }
+                continue;
+            }
+
+            let key = match byte {
+                b' ' | b'p' => Key::TogglePause,
+                b'n' | b'l' => Key::Next,
+                b'b' | b'h' => Key::Previous,
+                // Ctrl-C is a plain byte once signals are disabled
+                b'q' | 3 => Key::Quit,
+                _ => continue,
+            };
+            if sender.send(key).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+/// Terminal switched to reading keys one by one without echoing them, restored on drop
+struct Terminal {
+    /// Settings to restore, as printed by `stty -g`
+    saved: Option<String>,
+    interactive: bool,
+}
+
+impl Terminal {
+    fn enter() -> Terminal {
+        let saved = stty(&["-g"]).map(|settings| settings.trim().to_string());
+        let interactive =
+            saved.is_some() && stty(&["-icanon", "-echo", "-isig", "min", "1"]).is_some();
+
+        // Clear the screen and hide the cursor
+        print!("\x1B[2J\x1B[?25l");
+
+        Terminal { saved, interactive }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1B[0m\x1B[?25h");
+        let _ = io::stdout().flush();
+
+        if let Some(saved) = &self.saved {
+            stty(&[saved]);
+        }
+    }
+}
+
+/// Run `stty` on the terminal of the standard input, `None` when it is not a terminal
+fn stty(arguments: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(arguments)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_test() {
+        let red = Pixel::new(255, 0, 0);
+        let blue = Pixel::new(0, 0, 255);
+        let pixels = [
+            red.clone(),
+            red.clone(),
+            blue.clone(),
+            red,
+            blue.clone(),
+            blue,
+        ];
+
+        assert_eq!(
+            draw(&pixels, 2),
+            "\x1B[38;2;255;0;0m\x1B[48;2;0;0;255m▀\x1B[38;2;255;0;0m\x1B[48;2;255;0;0m▀\x1B[0m\n\
+             \x1B[38;2;0;0;255m\x1B[49m▀▀\x1B[0m\n"
+        );
+    }
+}
@@ -0,0 +1,828 @@
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use gas::{
+    animator::types::Color,
+    common::types::Pixel,
+    format::{
+        dither::Dither,
+        gif::{
+            encoder::{Delta, GifEncoder},
+            gif::{
+                ColorPolicy, Loop, MAX_COLORS, ScreenDescriptor, background_index,
+                pixel_aspect_ratio,
+            },
+            gif_image::Disposal,
+            metadata::ApplicationData,
+        },
+        quantize::Quantizer,
+    },
+    render::{
+        Provenance, background_pixel, frame_delay, frame_pixels_with_transparency,
+        frames_histogram, is_transparent,
+    },
+    script::{
+        compiler::{MAX_FPS, Program},
+        source::SourceMap,
+    },
+};
+
+use super::{
+    Failure, MessageFormat, Reporter,
+    args::{Args, OptionSpec},
+    default_output, help_requested, json, load_script, output_name, write_output,
+};
+
+pub const USAGE: &str = "\
+Usage: gas render <script> [options]
+
+Render a script to a GIF, the script being read from the standard input when it is `-`.
+
+Options:
+    -o, --output <file>    Output GIF, defaults to the script's `filename`, `-` for the
+                           standard output
+        --width <px>       Override the canvas width
+        --height <px>      Override the canvas height
+        --fps <fps>        Override the frame rate
+        --colors <policy>  What to do beyond 256 colors: `local` (the default) gives the
+                           frames using other colors a local color table, `quantize`
+                           replaces colors by the closest of 256, `error` fails
+        --palette <scope>  Colors `quantize` reduces: `animation` (the default) for a
+                           palette shared by all frames, or `frame` for a palette per
+                           frame using more than 256 colors
+        --kmeans <rounds>  Rounds of k-means refining the quantized palette, 0 by default
+        --dither <method>  Dithering of quantized frames: `none`, `floyd-steinberg`,
+                           `bayer4` or `bayer8`, defaults to `bayer8` for an animation
+        --dither-lock      Keep the colors of the pixels that did not change since the
+                           previous frame with `floyd-steinberg`, which is then the default
+        --delta <mode>     What each frame covers: `changes` (the default) for the
+                           rectangle that changed since the previous frame, `transparent`
+                           for the same rectangle with its unchanged pixels transparent,
+                           or `full` for the whole canvas
+        --interlace        Write the rows of the frames interlaced, for a coarse first
+                           frame to show while the rest is downloading
+        --pixel-aspect <ratio>
+                           Width of the pixels over their height, eg. `8:7` or `0.5`, for
+                           displays whose pixels are not square
+        --metadata <what>  Provenance written in the GIF: `comment` (the default) for a
+                           Comment Extension, `all` for an Application Extension of GAS
+                           as well, or `none`. `gas inspect` reads it back
+        --stats            Print statistics about the render";
+
+pub const OPTIONS: [OptionSpec; 13] = [
+    OptionSpec::value("--output", Some("-o")),
+    OptionSpec::value("--width", None),
+    OptionSpec::value("--height", None),
+    OptionSpec::value("--fps", None),
+    OptionSpec::value("--colors", None),
+    OptionSpec::value("--palette", None),
+    OptionSpec::value("--kmeans", None),
+    OptionSpec::value("--dither", None),
+    OptionSpec::flag("--dither-lock", None),
+    OptionSpec::value("--delta", None),
+    OptionSpec::flag("--interlace", None),
+    OptionSpec::value("--pixel-aspect", None),
+    OptionSpec::value("--metadata", None),
+];
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let options: Vec<OptionSpec> = OPTIONS
+        .into_iter()
+        .chain([OptionSpec::flag("--stats", None)])
+        .collect();
+    let args = Args::parse(arguments, &options).map_err(Failure::Usage)?;
+    let script = args.single_positional("script").map_err(Failure::Usage)?;
+
+    let loaded = load_script(script, reporter)?;
+    let mut program = loaded.program.ok_or(Failure::Script)?;
+    apply_overrides(&mut program, &args)?;
+
+    let output = match args.value("--output") {
+        Some(output) => output.to_string(),
+        None => default_output(script, &program),
+    };
+
+    let options = render_options(&args)?;
+    let stats = render_to_file(&program, &loaded.sources, &options, &output)?;
+    reporter.status(&format!(
+        "Rendered {} frames to {}",
+        stats.frame_sizes.len(),
+        output_name(&output)
+    ));
+
+    if args.flag("--stats") {
+        match reporter.format() {
+            MessageFormat::Human => eprintln!("{}", stats.human()),
+            MessageFormat::Json => eprintln!("{}", stats.json()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Figures about a render
+pub struct Stats {
+    pub width: usize,
+    pub height: usize,
+    /// Delay between two frames in centiseconds
+    pub delay: u16,
+    /// Number of distinct colors in the frames
+    pub colors: usize,
+    /// Number of colors of the global color table written
+    pub palette: usize,
+    /// Whether the colors were reduced to fit in the palette
+    pub quantized: bool,
+    /// Number of frames with colors missing from the global color table
+    pub local_color_tables: usize,
+    /// Number of frames showing the same as the frame before them, merged into its delay
+    pub merged_frames: usize,
+    /// Encoded size of each frame in bytes
+    pub frame_sizes: Vec<usize>,
+    pub file_size: usize,
+    /// Time spent drawing the frames and mapping their pixels to the palette
+    pub rasterization: Duration,
+    pub encoding: Duration,
+}
+
+impl Stats {
+    /// Played duration in seconds, as rounded by the GIF delays
+    fn duration(&self) -> f64 {
+        self.frame_sizes.len() as f64 * f64::from(self.delay) / 100.0
+    }
+
+    /// Size of the frames relative to one byte per pixel
+    fn compression_ratio(&self) -> f64 {
+        let raw = self.width * self.height * self.frame_sizes.len();
+        raw as f64 / self.frame_sizes.iter().sum::<usize>().max(1) as f64
+    }
+
+    fn human(&self) -> String {
+        let frames = self.frame_sizes.len();
+        let smallest = self.frame_sizes.iter().min().copied().unwrap_or(0);
+        let largest = self.frame_sizes.iter().max().copied().unwrap_or(0);
+        let average = self.frame_sizes.iter().sum::<usize>() / frames.max(1);
+
+        format!(
+            "\
+Frames:        {frames}, {:.2}s at {}cs per frame, {} merged into longer delays
+Palette:       {} colors, {}, from {} colors in the frames, {} frames with a local color table
+Frame size:    {average} B on average, from {smallest} B to {largest} B
+File size:     {} B
+LZW ratio:     {:.1}:1 compared to one byte per pixel
+Rasterizing:   {:.1} ms
+Encoding:      {:.1} ms",
+            self.duration(),
+            self.delay,
+            self.merged_frames,
+            self.palette,
+            if self.quantized { "quantized" } else { "exact" },
+            self.colors,
+            self.local_color_tables,
+            self.file_size,
+            self.compression_ratio(),
+            self.rasterization.as_secs_f64() * 1000.0,
+            self.encoding.as_secs_f64() * 1000.0,
+        )
+    }
+
+    fn json(&self) -> String {
+        let frame_sizes: Vec<String> = self.frame_sizes.iter().map(usize::to_string).collect();
+
+        format!(
+            "{{\"severity\":\"note\",\"code\":\"stats\",\"message\":{},\"file\":null,\"span\":null,\
+             \"stats\":{{\"frames\":{},\"duration\":{},\"delay\":{},\"merged_frames\":{},\"palette\":{},\"colors\":{},\"quantized\":{},\"local_color_tables\":{},\
+             \"frame_sizes\":[{}],\"file_size\":{},\"compression_ratio\":{:.3},\
+             \"rasterization_ms\":{:.3},\"encoding_ms\":{:.3}}}}}",
+            json::string("render statistics"),
+            self.frame_sizes.len(),
+            self.duration(),
+            self.delay,
+            self.merged_frames,
+            self.palette,
+            self.colors,
+            self.quantized,
+            self.local_color_tables,
+            frame_sizes.join(","),
+            self.file_size,
+            self.compression_ratio(),
+            self.rasterization.as_secs_f64() * 1000.0,
+            self.encoding.as_secs_f64() * 1000.0,
+        )
+    }
+}
+
+/// How a render is encoded, set from the command line
+#[derive(Debug, Default)]
+pub struct RenderOptions {
+    pub colors: ColorPolicy,
+    pub quantizer: Quantizer,
+    /// Dithering of quantized frames, `None` for the default of the animation
+    pub dither: Option<Dither>,
+    /// What each frame covers, `None` for only the changes
+    pub delta: Option<Delta>,
+    pub interlaced: bool,
+    /// Pixel aspect ratio as written in the GIF, 0 for square pixels
+    pub pixel_aspect_ratio: u8,
+    pub metadata: MetadataBlocks,
+}
+
+/// Blocks holding the provenance of a render
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MetadataBlocks {
+    None,
+    /// A Comment Extension, which tools showing GIF comments display
+    #[default]
+    Comment,
+    /// A Comment Extension and an Application Extension of GAS
+    All,
+}
+
+impl RenderOptions {
+    /// Dithering of quantized frames: ordered for animations since it does not change
+    /// from a frame to the next, and error diffusion for a still image
+    pub fn dither(&self, frame_count: u32) -> Dither {
+        self.dither.unwrap_or(if frame_count > 1 {
+            Dither::Bayer8
+        } else {
+            Dither::FloydSteinberg {
+                lock_unchanged: false,
+            }
+        })
+    }
+
+    /// What each frame covers, only the changes since the previous frame by default
+    pub fn delta(&self) -> Delta {
+        self.delta.unwrap_or(Delta::Changes {
+            transparent_unchanged: false,
+        })
+    }
+}
+
+/// Options of the encoding given by `--colors`, `--palette`, `--kmeans`, `--dither`,
+/// `--delta`, `--interlace`, `--pixel-aspect` and `--metadata`
+pub fn render_options(args: &Args) -> Result<RenderOptions, Failure> {
+    let mut colors = match args.value("--colors") {
+        None | Some("local") => ColorPolicy::LocalTables,
+        Some("quantize") => ColorPolicy::Quantize,
+        Some("error") => ColorPolicy::Error,
+        Some(other) => {
+            return Err(Failure::Usage(format!(
+                "unknown color policy `{other}`, expected `local`, `quantize` or `error`"
+            )));
+        }
+    };
+    let kmeans_iterations = args.parsed::<u32>("--kmeans").map_err(Failure::Usage)?;
+
+    if colors != ColorPolicy::Quantize
+        && let Some(option) = ["--palette", "--kmeans", "--dither", "--dither-lock"]
+            .into_iter()
+            .find(|option| args.value(option).is_some() || args.flag(option))
+    {
+        return Err(Failure::Usage(format!(
+            "`{option}` only applies to `--colors quantize`"
+        )));
+    }
+    match args.value("--palette") {
+        None | Some("animation") => {}
+        Some("frame") => colors = ColorPolicy::QuantizeFrames,
+        Some(other) => {
+            return Err(Failure::Usage(format!(
+                "unknown palette scope `{other}`, expected `animation` or `frame`"
+            )));
+        }
+    }
+
+    let lock_unchanged = args.flag("--dither-lock");
+    let dither = match args.value("--dither") {
+        None if lock_unchanged => Some(Dither::FloydSteinberg { lock_unchanged }),
+        None => None,
+        Some("floyd-steinberg") => Some(Dither::FloydSteinberg { lock_unchanged }),
+        Some(_) if lock_unchanged => {
+            return Err(Failure::Usage(
+                "`--dither-lock` only applies to `--dither floyd-steinberg`".to_string(),
+            ));
+        }
+        Some("none") => Some(Dither::None),
+        Some("bayer4") => Some(Dither::Bayer4),
+        Some("bayer8") => Some(Dither::Bayer8),
+        Some(other) => {
+            return Err(Failure::Usage(format!(
+                "unknown dithering `{other}`, expected `none`, `floyd-steinberg`, `bayer4` \
+                 or `bayer8`"
+            )));
+        }
+    };
+
+    let delta = match args.value("--delta") {
+        None => None,
+        Some("full") => Some(Delta::Full),
+        Some("changes") => Some(Delta::Changes {
+            transparent_unchanged: false,
+        }),
+        Some("transparent") => Some(Delta::Changes {
+            transparent_unchanged: true,
+        }),
+        Some(other) => {
+            return Err(Failure::Usage(format!(
+                "unknown delta `{other}`, expected `changes`, `transparent` or `full`"
+            )));
+        }
+    };
+
+    let pixel_aspect_ratio = match args.value("--pixel-aspect") {
+        None => 0,
+        Some(value) => value
+            .split_once(':')
+            .map_or_else(
+                || value.parse().ok(),
+                |(width, height)| Some(width.parse::<f64>().ok()? / height.parse::<f64>().ok()?),
+            )
+            .and_then(pixel_aspect_ratio)
+            .ok_or_else(|| {
+                Failure::Usage(format!(
+                    "invalid pixel aspect ratio `{value}`, expected a width over a height \
+                     between 1/4 and 4, eg. `8:7`"
+                ))
+            })?,
+    };
+
+    let metadata = match args.value("--metadata") {
+        None | Some("comment") => MetadataBlocks::Comment,
+        Some("all") => MetadataBlocks::All,
+        Some("none") => MetadataBlocks::None,
+        Some(other) => {
+            return Err(Failure::Usage(format!(
+                "unknown metadata `{other}`, expected `comment`, `all` or `none`"
+            )));
+        }
+    };
+
+    Ok(RenderOptions {
+        colors,
+        quantizer: Quantizer {
+            kmeans_iterations: kmeans_iterations.unwrap_or(0),
+        },
+        dither,
+        delta,
+        interlaced: args.flag("--interlace"),
+        pixel_aspect_ratio,
+        metadata,
+    })
+}
+
+/// Render the program and write it to `output`, `-` being the standard output. Frames
+/// are encoded as they are drawn, so only one of them is held in memory. The provenance
+/// written in the GIF is the script of `sources`.
+pub fn render_to_file(
+    program: &Program,
+    sources: &SourceMap,
+    options: &RenderOptions,
+    output: &str,
+) -> Result<Stats, Failure> {
+    let settings = &program.settings;
+    let scene = program.scene();
+    let delay = frame_delay(settings.fps);
+
+    // The color table is written before the frames, which are drawn twice
+    let start = Instant::now();
+    let histogram = frames_histogram(scene.frames(), settings.background);
+    let colors = histogram.len();
+    // A transparent background keeps the first index of the color table for its pixels,
+    // as do the unchanged pixels of the frames with `--delta transparent`
+    let delta = options.delta();
+    let transparent = is_transparent(settings.background)
+        || delta
+            == Delta::Changes {
+                transparent_unchanged: true,
+            };
+    let max_colors = MAX_COLORS - usize::from(transparent);
+    let mut quantized = colors > max_colors && options.colors == ColorPolicy::Quantize;
+    let mut palette = match options.colors {
+        ColorPolicy::Quantize => options.quantizer.palette(&histogram, max_colors),
+        _ => most_used_first(histogram),
+    };
+    // Frames using the colors left out get a local color table, while the encoder
+    // refuses too many colors before writing anything when they are an error
+    if options.colors != ColorPolicy::Error {
+        palette.truncate(max_colors);
+    }
+    if transparent {
+        palette.insert(0, Pixel::new(0, 0, 0));
+    }
+    let palette_size = palette.len();
+    let mut rasterization = start.elapsed();
+    let mut encoding = Duration::ZERO;
+    let mut local_color_tables = 0;
+    let mut merged_frames = 0;
+
+    let mut frame_sizes = Vec::with_capacity(scene.frame_count() as usize);
+    let mut file_size = 0;
+
+    write_output(output, |writer| {
+        let mut writer = CountingWriter::new(writer);
+        let screen = ScreenDescriptor {
+            background_index: if is_transparent(settings.background) {
+                0
+            } else {
+                background_index(
+                    &palette,
+                    &background_pixel(settings.background),
+                    transparent.then_some(0),
+                )
+            },
+            pixel_aspect_ratio: options.pixel_aspect_ratio,
+            ..ScreenDescriptor::new(settings.height as u16, settings.width as u16)
+        };
+        let mut encoder =
+            GifEncoder::with_screen(&mut writer, screen, palette, Some(Loop::Forever))?;
+        encoder
+            .set_color_policy(options.colors)
+            .set_quantizer(options.quantizer)
+            .set_dither(options.dither(scene.frame_count()))
+            .set_transparent_index(transparent.then_some(0))
+            .set_delta(delta)
+            .set_interlaced(options.interlaced)
+            .set_disposal(disposal(settings.background, delta));
+
+        if options.metadata != MetadataBlocks::None {
+            let provenance = Provenance::new(sources, render_settings(program, options));
+            encoder.write_comment(&provenance.to_text())?;
+            if options.metadata == MetadataBlocks::All {
+                encoder.write_application_data(&ApplicationData::gas(&provenance.to_text()))?;
+            }
+        }
+
+        let mut frames = scene.frames();
+        loop {
+            let start = Instant::now();
+            let Some(frame) = frames.next() else { break };
+            let pixels = frame_pixels_with_transparency(&frame, settings.background);
+            let mut image = encoder.image_with_transparency(&pixels)?;
+            image.add_delay(delay);
+            local_color_tables += usize::from(image.has_local_color_table());
+            rasterization += start.elapsed();
+
+            let start = Instant::now();
+            frame_sizes.push(encoder.write_image(&image)?);
+            encoding += start.elapsed();
+        }
+        quantized |= encoder.quantized_images() > 0;
+        merged_frames = encoder.merged_images();
+        encoder.finish()?;
+
+        file_size = writer.count;
+        Ok(())
+    })?;
+
+    Ok(Stats {
+        width: settings.width,
+        height: settings.height,
+        delay,
+        colors,
+        palette: palette_size,
+        quantized,
+        local_color_tables,
+        merged_frames,
+        frame_sizes,
+        file_size,
+        rasterization,
+        encoding,
+    })
+}
+
+/// Colors of a histogram from the most used to the least used, so that the global color
+/// table keeps the colors covering most pixels. Colors used as much keep their order.
+fn most_used_first(mut histogram: Vec<(Pixel, usize)>) -> Vec<Pixel> {
+    histogram.sort_by(|(_, a), (_, b)| b.cmp(a));
+    histogram.into_iter().map(|(color, _)| color).collect()
+}
+
+/// Settings of a render as written in its provenance, the ones given on the command line
+/// with the names of their options
+fn render_settings(program: &Program, options: &RenderOptions) -> Vec<(String, String)> {
+    let settings = &program.settings;
+    let [r, g, b, a] = settings.background.to_rgba();
+    let (colors, palette) = match options.colors {
+        ColorPolicy::Error => ("error", "animation"),
+        ColorPolicy::LocalTables => ("local", "animation"),
+        ColorPolicy::Quantize => ("quantize", "animation"),
+        ColorPolicy::QuantizeFrames => ("quantize", "frame"),
+    };
+    let dither = match options.dither(program.frame_count()) {
+        Dither::None => "none",
+        Dither::FloydSteinberg {
+            lock_unchanged: false,
+        } => "floyd-steinberg",
+        Dither::FloydSteinberg {
+            lock_unchanged: true,
+        } => "floyd-steinberg-lock",
+        Dither::Bayer4 => "bayer4",
+        Dither::Bayer8 => "bayer8",
+    };
+    let delta = match options.delta() {
+        Delta::Full => "full",
+        Delta::Changes {
+            transparent_unchanged: false,
+        } => "changes",
+        Delta::Changes {
+            transparent_unchanged: true,
+        } => "transparent",
+    };
+
+    [
+        ("width", settings.width.to_string()),
+        ("height", settings.height.to_string()),
+        ("fps", settings.fps.to_string()),
+        ("background", format!("#{r:02X}{g:02X}{b:02X}{a:02X}")),
+        ("colors", colors.to_string()),
+        ("palette", palette.to_string()),
+        ("kmeans", options.quantizer.kmeans_iterations.to_string()),
+        ("dither", dither.to_string()),
+        ("delta", delta.to_string()),
+        ("interlace", options.interlaced.to_string()),
+        (
+            "pixel-aspect",
+            match options.pixel_aspect_ratio {
+                0 => "1".to_string(),
+                ratio => ((f64::from(ratio) + 15.0) / 64.0).to_string(),
+            },
+        ),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+/// Disposal of the frames: a transparent background is cleared before each frame so that
+/// the previous one does not show through, while the changes are drawn over the previous
+/// frame
+fn disposal(background: Color, delta: Delta) -> Disposal {
+    if is_transparent(background) {
+        Disposal::RestoreToBackground
+    } else if delta == Delta::Full {
+        Disposal::Unspecified
+    } else {
+        Disposal::DoNotDispose
+    }
+}
+
+/// Writer counting the bytes written through it
+struct CountingWriter<W: Write> {
+    inner: W,
+    count: usize,
+}
+
+impl<W: Write> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        CountingWriter { inner, count: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Program settings replaced from the command line
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Overrides {
+    width: Option<usize>,
+    height: Option<usize>,
+    fps: Option<u32>,
+}
+
+impl Overrides {
+    pub fn apply(&self, program: &mut Program) {
+        let settings = &mut program.settings;
+        settings.width = self.width.unwrap_or(settings.width);
+        settings.height = self.height.unwrap_or(settings.height);
+        settings.fps = self.fps.unwrap_or(settings.fps);
+    }
+}
+
+/// Read the `--width`, `--height` and `--fps` options
+pub fn overrides(args: &Args) -> Result<Overrides, Failure> {
+    let size = |option: &str| -> Result<Option<usize>, Failure> {
+        match args.parsed::<u16>(option).map_err(Failure::Usage)? {
+            Some(0) => Err(Failure::Usage(format!("`{option}` must be positive"))),
+            size => Ok(size.map(usize::from)),
+        }
+    };
+    let (width, height) = (size("--width")?, size("--height")?);
+
+    let fps = args.parsed::<u32>("--fps").map_err(Failure::Usage)?;
+    if let Some(fps) = fps
+        && !(1..=MAX_FPS).contains(&fps)
+    {
+        return Err(Failure::Usage(format!(
+            "`--fps` must be between 1 and {MAX_FPS}"
+        )));
+    }
+
+    Ok(Overrides { width, height, fps })
+}
+
+/// Apply the `--width`, `--height` and `--fps` options to the program settings
+pub fn apply_overrides(program: &mut Program, args: &Args) -> Result<(), Failure> {
+    overrides(args)?.apply(program);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use gas::{format::gif::validator::assert_valid, script::loader::load_source};
+
+    use super::*;
+
+    /// Two rectangles fading from black to red and green, a color a frame each, for more
+    /// than 256 colors over 10 rows
+    const SCRIPT: &str = "\
+---
+width: 6
+height: 10
+fps: 100
+---
+r ANIM
+    color #000000 -> #FF0000
+    delay 2s
+g ANIM
+    color #000000 -> #00FF00
+    delay 2s
+a RECT
+    width 2
+    height 4
+b RECT
+    width 3
+    height 4
+    position [5, 2]
+[r(a), g(b)]
+";
+
+    /// Bytes of the script rendered with the options of the command line `arguments`
+    fn render_bytes(name: &str, arguments: &[&str]) -> Vec<u8> {
+        let loaded = load_source(PathBuf::from("fade.gas"), SCRIPT.to_string());
+        let program = loaded.program.unwrap();
+        let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+        let options = render_options(&Args::parse(&arguments, &OPTIONS).unwrap()).unwrap();
+
+        let output = std::env::temp_dir().join(format!("gas_render_{name}.gif"));
+        let output = output.to_string_lossy();
+        render_to_file(&program, &loaded.sources, &options, &output).unwrap();
+        let bytes = fs::read(&*output).unwrap();
+        fs::remove_file(&*output).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn render_to_file_test() {
+        for delta in ["changes", "transparent", "full"] {
+            assert_valid(&render_bytes(
+                &format!("delta_{delta}"),
+                &["--delta", delta],
+            ));
+        }
+        assert_valid(&render_bytes("interlace", &["--interlace"]));
+        assert_valid(&render_bytes("quantize", &["--colors", "quantize"]));
+        assert_valid(&render_bytes(
+            "quantize_frames",
+            &["--colors", "quantize", "--palette", "frame"],
+        ));
+
+        let bytes = render_bytes("metadata", &["--metadata", "all"]);
+        assert_valid(&bytes);
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("GAS") && text.contains("fade.gas"));
+    }
+
+    #[test]
+    fn most_used_first_test() {
+        let (red, green, blue) = (
+            Pixel::new(255, 0, 0),
+            Pixel::new(0, 255, 0),
+            Pixel::new(0, 0, 255),
+        );
+
+        assert_eq!(
+            most_used_first(vec![
+                (red.clone(), 1),
+                (green.clone(), 5),
+                (blue.clone(), 5)
+            ]),
+            vec![green, blue, red]
+        );
+    }
+
+    #[test]
+    fn stats_test() {
+        let stats = Stats {
+            width: 10,
+            height: 10,
+            delay: 4,
+            colors: 300,
+            palette: 256,
+            quantized: true,
+            local_color_tables: 0,
+            merged_frames: 1,
+            frame_sizes: vec![40, 60],
+            file_size: 130,
+            rasterization: Duration::from_millis(2),
+            encoding: Duration::from_millis(5),
+        };
+
+        assert_eq!(stats.duration(), 0.08);
+        assert_eq!(stats.compression_ratio(), 2.0);
+        assert!(stats.human().contains("50 B on average, from 40 B to 60 B"));
+        assert!(
+            stats
+                .human()
+                .contains("256 colors, quantized, from 300 colors in the frames")
+        );
+        assert!(stats.json().contains("\"palette\":256,\"colors\":300"));
+        assert!(stats.json().contains("\"frame_sizes\":[40,60]"));
+        assert!(stats.json().contains("\"merged_frames\":1"));
+    }
+
+    #[test]
+    fn render_options_test() {
+        let options = |arguments: &[&str]| {
+            let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+            render_options(&Args::parse(&arguments, &OPTIONS).unwrap())
+        };
+
+        let default = options(&[]).unwrap();
+        assert_eq!(default.colors, ColorPolicy::LocalTables);
+        assert_eq!(default.dither(10), Dither::Bayer8);
+        assert_eq!(
+            default.delta(),
+            Delta::Changes {
+                transparent_unchanged: false
+            }
+        );
+        assert_eq!(options(&["--delta", "full"]).unwrap().delta(), Delta::Full);
+        assert!(options(&["--delta", "none"]).is_err());
+        assert_eq!(default.metadata, MetadataBlocks::Comment);
+        assert_eq!(
+            options(&["--metadata", "all"]).unwrap().metadata,
+            MetadataBlocks::All
+        );
+        assert!(options(&["--metadata", "yes"]).is_err());
+        assert_eq!(default.pixel_aspect_ratio, 0);
+        assert_eq!(
+            options(&["--pixel-aspect", "8:7"])
+                .unwrap()
+                .pixel_aspect_ratio,
+            58
+        );
+        assert_eq!(
+            options(&["--pixel-aspect", "0.5"])
+                .unwrap()
+                .pixel_aspect_ratio,
+            17
+        );
+        assert!(options(&["--pixel-aspect", "8:1"]).is_err());
+        assert!(options(&["--pixel-aspect", "wide"]).is_err());
+
+        let locked = options(&[
+            "--colors",
+            "quantize",
+            "--palette",
+            "frame",
+            "--dither-lock",
+        ]);
+        let locked = locked.unwrap();
+        assert_eq!(locked.colors, ColorPolicy::QuantizeFrames);
+        assert_eq!(
+            locked.dither(10),
+            Dither::FloydSteinberg {
+                lock_unchanged: true
+            }
+        );
+
+        assert!(options(&["--dither", "bayer4"]).is_err());
+        assert!(
+            options(&[
+                "--colors",
+                "quantize",
+                "--dither",
+                "bayer4",
+                "--dither-lock"
+            ])
+            .is_err()
+        );
+    }
+}
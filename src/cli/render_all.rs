@@ -0,0 +1,380 @@
+use std::{
+    fs,
+    num::NonZero,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use gas::script::loader::load;
+
+use super::{
+    Failure, Reporter,
+    args::{Args, OptionSpec},
+    default_output,
+    glob::glob,
+    help_requested, panic_message,
+    render::{
+        OPTIONS as RENDER_OPTIONS, Overrides, RenderOptions, overrides, render_options,
+        render_to_file,
+    },
+};
+
+pub const USAGE: &str = "\
+Usage: gas render-all [<manifest>] [options]
+
+Render many scripts, listed in a manifest or matching a pattern. A script failing does
+not stop the others, and a table of the results is printed at the end.
+
+Each line of the manifest is a script, optionally followed by `->` and its output.
+Paths are relative to the manifest and lines starting with `#` are ignored:
+
+    intro.gas
+    outro.gas -> build/outro.gif
+
+Options:
+        --glob <pattern>    Render the scripts matching the pattern instead of a manifest,
+                            eg. `docs/**/*.gas`
+    -j, --jobs <count>      Number of scripts rendered at the same time, defaults to the
+                            number of processors
+
+Every option of `gas render` but `--output` and `--stats` applies to all the scripts,
+eg. `--colors quantize` or `--delta full`.";
+
+const OPTIONS: [OptionSpec; 2] = [
+    OptionSpec::value("--glob", None),
+    OptionSpec::value("--jobs", Some("-j")),
+];
+
+/// How every script of the batch is rendered
+struct Settings {
+    overrides: Overrides,
+    options: RenderOptions,
+}
+
+/// Script to render, its output defaulting to the one of a single render
+#[derive(Debug, PartialEq)]
+struct Job {
+    script: String,
+    output: Option<String>,
+}
+
+struct Outcome {
+    output: Option<String>,
+    /// Number of rendered frames
+    result: Result<usize, Failure>,
+    elapsed: Duration,
+}
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let options: Vec<OptionSpec> = RENDER_OPTIONS
+        .into_iter()
+        .filter(|option| option.long != "--output")
+        .chain(OPTIONS)
+        .collect();
+    let args = Args::parse(arguments, &options).map_err(Failure::Usage)?;
+    let settings = Settings {
+        overrides: overrides(&args)?,
+        options: render_options(&args)?,
+    };
+    let jobs = match (args.positionals.as_slice(), args.value("--glob")) {
+        ([manifest], None) => {
+            let content = fs::read_to_string(manifest)
+                .map_err(|error| Failure::Io(format!("cannot read `{manifest}`: {error}")))?;
+            let directory = Path::new(manifest).parent().unwrap_or(Path::new(""));
+            parse_manifest(&content, directory)
+                .map_err(|message| Failure::Usage(format!("{manifest}:{message}")))?
+        }
+        ([], Some(pattern)) => glob(pattern)
+            .map_err(|error| Failure::Io(format!("cannot expand `{pattern}`: {error}")))?
+            .into_iter()
+            .map(|script| Job {
+                script: script.to_string_lossy().into_owned(),
+                output: None,
+            })
+            .collect(),
+        ([], None) => {
+            return Err(Failure::Usage(
+                "expected a manifest or `--glob`".to_string(),
+            ));
+        }
+        ([_], Some(_)) => {
+            return Err(Failure::Usage(
+                "a manifest and `--glob` cannot be used together".to_string(),
+            ));
+        }
+        ([_, extra, ..], _) => {
+            return Err(Failure::Usage(format!("unexpected argument `{extra}`")));
+        }
+    };
+    if jobs.is_empty() {
+        return Err(Failure::Usage("no script to render".to_string()));
+    }
+
+    let workers = match args.parsed::<usize>("--jobs").map_err(Failure::Usage)? {
+        Some(0) => return Err(Failure::Usage("`--jobs` must be positive".to_string())),
+        Some(workers) => workers,
+        None => thread::available_parallelism().map_or(1, NonZero::get),
+    };
+
+    let outcomes = render_jobs(&jobs, &settings, workers.min(jobs.len()), reporter);
+    print_table(&jobs, &outcomes);
+
+    let failed = outcomes.iter().filter(|o| o.result.is_err()).count();
+    println!("{} rendered, {failed} failed", jobs.len() - failed);
+
+    // Exit with the class of the first failure, its message was already reported
+    match outcomes
+        .into_iter()
+        .find_map(|outcome| outcome.result.err())
+    {
+        None => Ok(()),
+        Some(failure) => {
+            let message = format!("{failed} of {} scripts failed", jobs.len());
+            Err(match failure {
+                Failure::Script => Failure::Script,
+                Failure::Usage(_) => Failure::Usage(message),
+                Failure::Io(_) => Failure::Io(message),
+                Failure::Encoder(_) => Failure::Encoder(message),
+                Failure::Invalid(_) => Failure::Invalid(message),
+                Failure::Internal(_) => Failure::Internal(message),
+            })
+        }
+    }
+}
+
+/// Jobs of a manifest, or the line number and the reason it is invalid
+fn parse_manifest(content: &str, directory: &Path) -> Result<Vec<Job>, String> {
+    let relative = |path: &str| directory.join(path).to_string_lossy().into_owned();
+    let mut jobs = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (script, output) = match line.split_once("->") {
+            Some((script, output)) => (script.trim(), Some(output.trim())),
+            None => (line, None),
+        };
+        if script.is_empty() || output == Some("") {
+            return Err(format!(
+                "{}: expected `<script>` or `<script> -> <output>`",
+                index + 1
+            ));
+        }
+
+        jobs.push(Job {
+            script: relative(script),
+            output: output.map(relative),
+        });
+    }
+
+    Ok(jobs)
+}
+
+/// Render the jobs on `workers` threads, returns their outcomes in the same order
+fn render_jobs(
+    jobs: &[Job],
+    settings: &Settings,
+    workers: usize,
+    reporter: &Reporter,
+) -> Vec<Outcome> {
+    let next = AtomicUsize::new(0);
+    // Keeps the messages of a script together
+    let print = Mutex::new(());
+
+    let mut outcomes: Vec<(usize, Outcome)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut outcomes = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(job) = jobs.get(index) else { break };
+                        outcomes.push((index, render_isolated(job, settings, reporter, &print)));
+                    }
+                    outcomes
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+/// Render a job, a crash of the renderer only failing this job
+fn render_isolated(
+    job: &Job,
+    settings: &Settings,
+    reporter: &Reporter,
+    print: &Mutex<()>,
+) -> Outcome {
+    let start = Instant::now();
+
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        render_job(job, settings, reporter, print)
+    }))
+    .unwrap_or_else(|payload| {
+        let failure = Failure::Internal(format!(
+            "rendering `{}` crashed: {}",
+            job.script,
+            panic_message(&payload)
+        ));
+        let _lock = print.lock().unwrap_or_else(PoisonError::into_inner);
+        reporter.failure(&failure);
+
+        Outcome {
+            output: None,
+            result: Err(failure),
+            elapsed: start.elapsed(),
+        }
+    })
+}
+
+fn render_job(job: &Job, settings: &Settings, reporter: &Reporter, print: &Mutex<()>) -> Outcome {
+    let start = Instant::now();
+
+    let (output, result) = match load(Path::new(&job.script)) {
+        Ok(loaded) => {
+            {
+                let _lock = print.lock().unwrap_or_else(PoisonError::into_inner);
+                reporter.diagnostics(&loaded.sources, &loaded.diagnostics);
+            }
+
+            match loaded.program {
+                Some(mut program) => {
+                    settings.overrides.apply(&mut program);
+                    let output = job
+                        .output
+                        .clone()
+                        .unwrap_or_else(|| default_output(&job.script, &program));
+                    let result =
+                        render_to_file(&program, &loaded.sources, &settings.options, &output)
+                            .map(|stats| stats.frame_sizes.len());
+                    (Some(output), result)
+                }
+                None => (None, Err(Failure::Script)),
+            }
+        }
+        Err(error) => (
+            None,
+            Err(Failure::Io(format!(
+                "cannot read `{}`: {error}",
+                job.script
+            ))),
+        ),
+    };
+
+    if let Err(failure) = &result {
+        let _lock = print.lock().unwrap_or_else(PoisonError::into_inner);
+        reporter.failure(failure);
+    }
+
+    Outcome {
+        output,
+        result,
+        elapsed: start.elapsed(),
+    }
+}
+
+fn print_table(jobs: &[Job], outcomes: &[Outcome]) {
+    let rows: Vec<[String; 4]> = jobs
+        .iter()
+        .zip(outcomes)
+        .map(|(job, outcome)| {
+            let (status, result) = match &outcome.result {
+                Ok(frames) => (
+                    "ok",
+                    format!(
+                        "{} ({frames} frames)",
+                        outcome.output.as_deref().unwrap_or_default()
+                    ),
+                ),
+                Err(failure) => ("FAILED", failure_summary(failure)),
+            };
+            [
+                status.to_string(),
+                format!("{:.2}s", outcome.elapsed.as_secs_f64()),
+                job.script.clone(),
+                result,
+            ]
+        })
+        .collect();
+
+    let header = ["STATUS", "TIME", "SCRIPT", "RESULT"].map(String::from);
+    let mut widths = [0; 4];
+    for row in rows.iter().chain([&header]) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in [&header].into_iter().chain(&rows) {
+        println!(
+            "{:<w0$}  {:>w1$}  {:<w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        );
+    }
+}
+
+fn failure_summary(failure: &Failure) -> String {
+    match failure {
+        Failure::Script => "the script has errors".to_string(),
+        Failure::Usage(message)
+        | Failure::Io(message)
+        | Failure::Encoder(message)
+        | Failure::Invalid(message)
+        | Failure::Internal(message) => message.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest_test() {
+        let manifest = "# Docs\nintro.gas\n\n  outro.gas -> build/outro.gif  \n";
+
+        assert_eq!(
+            parse_manifest(manifest, Path::new("docs")),
+            Ok(vec![
+                Job {
+                    script: "docs/intro.gas".to_string(),
+                    output: None,
+                },
+                Job {
+                    script: "docs/outro.gas".to_string(),
+                    output: Some("docs/build/outro.gif".to_string()),
+                },
+            ])
+        );
+        assert_eq!(
+            parse_manifest("a.gas\nb.gas ->\n", Path::new("")),
+            Err("2: expected `<script>` or `<script> -> <output>`".to_string())
+        );
+    }
+}
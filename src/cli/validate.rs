@@ -0,0 +1,120 @@
+use gas::format::gif::validator::{Violation, validate};
+
+use super::{
+    Failure, Reporter,
+    args::{Args, OptionSpec},
+    help_requested, json,
+};
+
+pub const USAGE: &str = "\
+Usage: gas validate <gifs...> [options]
+
+Check GIFs against the GIF89a spec: their block structure, sub-blocks, color table sizes,
+LZW code sizes and data, and the bounds of their images. Each violation is printed with
+the byte offset of the block at fault.
+
+Options:
+        --format <format>    `text` or `json` (one object per GIF and line), defaults to text";
+
+const OPTIONS: [OptionSpec; 1] = [OptionSpec::value("--format", None)];
+
+enum Format {
+    Text,
+    Json,
+}
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let args = Args::parse(arguments, &OPTIONS).map_err(Failure::Usage)?;
+    let format = match args.value("--format") {
+        None | Some("text") => Format::Text,
+        Some("json") => Format::Json,
+        Some(other) => {
+            return Err(Failure::Usage(format!(
+                "unknown format `{other}`, expected `text` or `json`"
+            )));
+        }
+    };
+    if args.positionals.is_empty() {
+        return Err(Failure::Usage("missing <gifs...>".to_string()));
+    }
+
+    let mut unreadable = 0;
+    let mut invalid = 0;
+
+    for gif in &args.positionals {
+        let bytes = match std::fs::read(gif) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                let message = format!("cannot read {gif}: {error}");
+                match format {
+                    Format::Text => reporter.failure(&Failure::Io(message)),
+                    Format::Json => println!(
+                        "{{\"file\":{},\"ok\":false,\"error\":{},\"violations\":[]}}",
+                        json::string(gif),
+                        json::string(&message),
+                    ),
+                }
+                unreadable += 1;
+                continue;
+            }
+        };
+
+        let violations = validate(&bytes);
+        if !violations.is_empty() {
+            invalid += 1;
+        }
+
+        match format {
+            Format::Text => report_text(gif, &violations),
+            Format::Json => report_json(gif, &violations),
+        }
+    }
+
+    if unreadable > 0 {
+        Err(Failure::Io(format!(
+            "{unreadable} GIF(s) could not be read"
+        )))
+    } else if invalid > 0 {
+        Err(Failure::Invalid(format!(
+            "{invalid} GIF(s) break the GIF89a spec"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+fn report_text(gif: &str, violations: &[Violation]) {
+    for violation in violations {
+        println!("{gif}: {violation}");
+    }
+    if violations.is_empty() {
+        println!("{gif}: valid");
+    } else {
+        println!("{gif}: {} violation(s)", violations.len());
+    }
+}
+
+fn report_json(gif: &str, violations: &[Violation]) {
+    let violations_json: Vec<String> = violations
+        .iter()
+        .map(|violation| {
+            format!(
+                "{{\"offset\":{},\"code\":{},\"message\":{}}}",
+                violation.offset(),
+                json::string(violation.code()),
+                json::string(&violation.to_string()),
+            )
+        })
+        .collect();
+
+    println!(
+        "{{\"file\":{},\"ok\":{},\"violations\":[{}]}}",
+        json::string(gif),
+        violations.is_empty(),
+        violations_json.join(","),
+    );
+}
@@ -0,0 +1,195 @@
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use gas::script::loader::Loaded;
+
+use super::{
+    Failure, Reporter, STDIO,
+    args::{Args, OptionSpec},
+    default_output, help_requested, load_script, panic_message,
+    render::{OPTIONS as RENDER_OPTIONS, apply_overrides, render_options, render_to_file},
+};
+
+pub const USAGE: &str = "\
+Usage: gas watch <script> [options]
+
+Render the script, then render it again every time it or one of its imports changes.
+The output is only replaced once a render succeeds.
+
+Options:
+    -o, --output <file>    Output GIF, defaults to the script's `filename`, which cannot be
+                           the standard output
+        --width <px>       Override the canvas width
+        --height <px>      Override the canvas height
+        --fps <fps>        Override the frame rate
+        --colors <policy>  `local`, `quantize` or `error`, as for `gas render`
+        --palette <scope>  `animation` or `frame`, as for `gas render`
+        --kmeans <rounds>  Rounds of k-means refining the quantized palette
+        --dither <method>  `none`, `floyd-steinberg`, `bayer4` or `bayer8`, as for
+                           `gas render`
+        --dither-lock      Keep the colors of unchanged pixels with `floyd-steinberg`
+        --delta <mode>     `changes`, `transparent` or `full`, as for `gas render`
+        --interlace        Write the rows of the frames interlaced
+        --pixel-aspect <ratio>
+                           Width of the pixels over their height, as for `gas render`
+        --metadata <what>  `comment`, `all` or `none`, as for `gas render`
+        --interval <ms>    Time between two checks for changes, defaults to 500";
+
+const DEFAULT_INTERVAL: u64 = 500;
+
+pub fn run(arguments: &[String], reporter: &Reporter) -> Result<(), Failure> {
+    if help_requested(arguments, USAGE) {
+        return Ok(());
+    }
+
+    let options: Vec<OptionSpec> = RENDER_OPTIONS
+        .into_iter()
+        .chain([OptionSpec::value("--interval", None)])
+        .collect();
+    let args = Args::parse(arguments, &options).map_err(Failure::Usage)?;
+    let script = args.single_positional("script").map_err(Failure::Usage)?;
+    if script == STDIO {
+        return Err(Failure::Usage(
+            "cannot watch the standard input, expected a script file".to_string(),
+        ));
+    }
+    // Each render would add a GIF to the ones already written
+    if args.value("--output") == Some(STDIO) {
+        return Err(Failure::Usage(
+            "cannot watch to the standard output, expected a file".to_string(),
+        ));
+    }
+    let interval = args
+        .parsed::<u64>("--interval")
+        .map_err(Failure::Usage)?
+        .unwrap_or(DEFAULT_INTERVAL);
+    let interval = Duration::from_millis(interval.max(1));
+
+    let mut watched = vec![PathBuf::from(script)];
+    loop {
+        // A crash of the renderer is reported like any other error, watching the same
+        // files as before
+        match panic::catch_unwind(AssertUnwindSafe(|| render_once(script, &args, reporter))) {
+            Ok(result) => watched = result?,
+            Err(payload) => reporter.failure(&Failure::Internal(format!(
+                "rendering `{script}` crashed: {}",
+                panic_message(&payload)
+            ))),
+        }
+        reporter.status(&format!("Watching {} file(s) for changes", watched.len()));
+
+        let stamps = modification_times(&watched);
+        while modification_times(&watched) == stamps {
+            thread::sleep(interval);
+        }
+    }
+}
+
+/// Render the script, reporting errors without stopping. Returns the files to watch.
+/// Only invalid options are fatal since they cannot be fixed by editing the script.
+fn render_once(script: &str, args: &Args, reporter: &Reporter) -> Result<Vec<PathBuf>, Failure> {
+    let loaded = match load_script(script, reporter) {
+        Ok(loaded) => loaded,
+        Err(failure @ Failure::Io(_)) => {
+            reporter.failure(&failure);
+            return Ok(vec![PathBuf::from(script)]);
+        }
+        Err(failure) => return Err(failure),
+    };
+    let watched = watched_files(&loaded);
+
+    if let Some(mut program) = loaded.program {
+        apply_overrides(&mut program, args)?;
+        let options = render_options(args)?;
+
+        let output = match args.value("--output") {
+            Some(output) => output.to_string(),
+            None => default_output(script, &program),
+        };
+
+        match render_to_file(&program, &loaded.sources, &options, &output) {
+            Ok(stats) => reporter.status(&format!(
+                "Rendered {} frames to {output}",
+                stats.frame_sizes.len()
+            )),
+            Err(failure @ (Failure::Io(_) | Failure::Encoder(_))) => reporter.failure(&failure),
+            Err(failure) => return Err(failure),
+        }
+    }
+
+    Ok(watched)
+}
+
+/// Files read to load the script, along with the imports that could not be read so that
+/// creating them renders again
+fn watched_files(loaded: &Loaded) -> Vec<PathBuf> {
+    loaded
+        .sources
+        .paths()
+        .map(Path::to_path_buf)
+        .chain(loaded.unreadable_imports.iter().cloned())
+        .collect()
+}
+
+/// Modification time of each file, `None` when it cannot be read (eg. deleted)
+fn modification_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use gas::script::loader::load;
+
+    use super::*;
+    use crate::cli::MessageFormat;
+
+    fn strings(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn run_usage_test() {
+        let reporter = Reporter::new(MessageFormat::Human);
+
+        assert!(matches!(
+            run(&strings(&["-"]), &reporter),
+            Err(Failure::Usage(_))
+        ));
+        assert!(matches!(
+            run(&strings(&["a.gas", "--output", "-"]), &reporter),
+            Err(Failure::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn watched_files_test() {
+        let directory = std::env::temp_dir().join("gas_watched_files_test");
+        fs::create_dir_all(&directory).unwrap();
+        let script = directory.join("main.gas");
+        let missing = directory.join("missing.gas");
+        fs::write(&script, "import \"missing.gas\"\n").unwrap();
+        let _ = fs::remove_file(&missing);
+
+        let loaded = load(&script).unwrap();
+        let watched = watched_files(&loaded);
+        assert_eq!(watched, vec![script, missing.clone()]);
+
+        // Creating the import changes the modification times
+        let stamps = modification_times(&watched);
+        assert_eq!(stamps[1], None);
+        fs::write(&missing, "").unwrap();
+        assert_ne!(modification_times(&watched), stamps);
+    }
+}
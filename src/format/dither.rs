@@ -0,0 +1,299 @@
+use crate::{common::types::Pixel, format::quantize::ColorMap};
+
+/// How pixels are spread over the colors of a palette missing some of them
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Dither {
+    /// Each pixel takes the closest color, gradients showing bands
+    #[default]
+    None,
+    /// The difference between a pixel and its color is passed on to the next pixels. With
+    /// `lock_unchanged`, the pixels of an image equal to the ones of the previous image keep
+    /// their color instead of shimmering as the error coming from changed pixels varies.
+    FloydSteinberg { lock_unchanged: bool },
+    /// Each pixel is offset by its threshold in a 4x4 Bayer matrix, the same from an image
+    /// to the next
+    Bayer4,
+    /// Same as `Bayer4` with a 8x8 matrix, showing a finer pattern
+    Bayer8,
+}
+
+/// Maps the pixels of successive images to a palette. Images may cover part of a canvas,
+/// their pixels being dithered according to their position in it so that a pixel drawn
+/// again by a smaller or larger image is dithered the same.
+pub struct Ditherer {
+    dither: Dither,
+    /// Pixels of the canvas last dithered while locking unchanged pixels
+    previous: Option<Previous>,
+}
+
+struct Previous {
+    canvas_width: usize,
+    palette: Vec<Pixel>,
+    /// Color and index of the last pixel dithered at each position of the canvas, row by row
+    pixels: Vec<Option<(Pixel, usize)>>,
+}
+
+impl Ditherer {
+    pub fn new(dither: Dither) -> Self {
+        Ditherer {
+            dither,
+            previous: None,
+        }
+    }
+
+    /// Index in the palette of each pixel of an image `width` pixels wide, given row by row,
+    /// whose top left pixel is at `origin` (`(left, top)`) in a canvas `canvas_width` pixels
+    /// wide
+    pub fn dither(
+        &mut self,
+        pixels: &[Pixel],
+        width: usize,
+        origin: (usize, usize),
+        canvas_width: usize,
+        palette: &[Pixel],
+    ) -> Vec<usize> {
+        let width = width.max(1);
+        let (left, top) = origin;
+        // Position in the canvas of each pixel of the image
+        let position = |index: usize| (left + index % width, top + index / width);
+        let mut color_map = ColorMap::new(palette);
+
+        match self.dither {
+            Dither::None => pixels.iter().map(|pixel| color_map.index(pixel)).collect(),
+            Dither::FloydSteinberg { lock_unchanged } => {
+                // Pixels can only keep the colors of a previous image of the same canvas
+                // using the same palette
+                let previous = self.previous.take().filter(|previous| {
+                    lock_unchanged
+                        && previous.canvas_width == canvas_width
+                        && previous.palette == palette
+                });
+                let canvas_index = |index: usize| {
+                    let (x, y) = position(index);
+                    y * canvas_width + x
+                };
+                let locked = |index: usize, pixel: &Pixel| {
+                    let (color, color_index) = previous
+                        .as_ref()?
+                        .pixels
+                        .get(canvas_index(index))?
+                        .as_ref()?;
+                    (color == pixel).then_some(*color_index)
+                };
+                let indexes = floyd_steinberg(pixels, width, &mut color_map, locked);
+
+                if lock_unchanged {
+                    let mut previous = previous.unwrap_or_else(|| Previous {
+                        canvas_width,
+                        palette: palette.to_vec(),
+                        pixels: Vec::new(),
+                    });
+                    for (index, (pixel, color_index)) in pixels.iter().zip(&indexes).enumerate() {
+                        let canvas_index = canvas_index(index);
+                        if previous.pixels.len() <= canvas_index {
+                            previous.pixels.resize(canvas_index + 1, None);
+                        }
+                        previous.pixels[canvas_index] = Some((pixel.clone(), *color_index));
+                    }
+                    self.previous = Some(previous);
+                }
+                indexes
+            }
+            Dither::Bayer4 => ordered(pixels, position, &mut color_map, 4),
+            Dither::Bayer8 => ordered(pixels, position, &mut color_map, 8),
+        }
+    }
+}
+
+fn floyd_steinberg(
+    pixels: &[Pixel],
+    width: usize,
+    color_map: &mut ColorMap,
+    locked: impl Fn(usize, &Pixel) -> Option<usize>,
+) -> Vec<usize> {
+    // Error passed on to the pixels of the current and the next row, in sixteenths
+    let mut errors = vec![[0i32; 3]; width];
+    let mut next_errors = vec![[0i32; 3]; width];
+    let mut indexes = Vec::with_capacity(pixels.len());
+
+    for (index, pixel) in pixels.iter().enumerate() {
+        let x = index % width;
+        if x == 0 && index > 0 {
+            errors = std::mem::replace(&mut next_errors, vec![[0; 3]; width]);
+        }
+
+        if let Some(color_index) = locked(index, pixel) {
+            // Error coming to a locked pixel is dropped
+            indexes.push(color_index);
+            continue;
+        }
+
+        let channels = pixel.as_bytes();
+        let target: [u8; 3] = std::array::from_fn(|channel| {
+            (i32::from(channels[channel]) + (errors[x][channel] + 8).div_euclid(16)).clamp(0, 255)
+                as u8
+        });
+        let color_index = color_map.index(&Pixel::new(target[0], target[1], target[2]));
+        indexes.push(color_index);
+
+        let color = color_map.palette()[color_index].as_bytes();
+        for channel in 0..3 {
+            let error = i32::from(target[channel]) - i32::from(color[channel]);
+            if x + 1 < width {
+                errors[x + 1][channel] += error * 7;
+                next_errors[x + 1][channel] += error;
+            }
+            if x > 0 {
+                next_errors[x - 1][channel] += error * 3;
+            }
+            next_errors[x][channel] += error * 5;
+        }
+    }
+
+    indexes
+}
+
+/// Pixels offset by the threshold of the matrix at their position in the canvas
+fn ordered(
+    pixels: &[Pixel],
+    position: impl Fn(usize) -> (usize, usize),
+    color_map: &mut ColorMap,
+    size: usize,
+) -> Vec<usize> {
+    let matrix = bayer_matrix(size);
+    // Offsets span about the distance between two colors of the palette
+    let spread = 255.0 / (color_map.palette().len().max(2) as f64).cbrt();
+
+    pixels
+        .iter()
+        .enumerate()
+        .map(|(index, pixel)| {
+            let (x, y) = position(index);
+            let threshold = (matrix[y % size][x % size] as f64 + 0.5) / (size * size) as f64;
+            let offset = ((threshold - 0.5) * spread).round() as i32;
+
+            let [r, g, b] = pixel
+                .as_bytes()
+                .map(|channel| (i32::from(channel) + offset).clamp(0, 255) as u8);
+            color_map.index(&Pixel::new(r, g, b))
+        })
+        .collect()
+}
+
+/// Bayer matrix of a power of two size, holding each number from 0 to size² once
+fn bayer_matrix(size: usize) -> Vec<Vec<usize>> {
+    let mut matrix = vec![vec![0]];
+
+    while matrix.len() < size {
+        let half = matrix.len();
+        matrix = (0..half * 2)
+            .map(|y| {
+                (0..half * 2)
+                    .map(|x| {
+                        let quadrant = [0, 2, 3, 1][(y / half) * 2 + x / half];
+                        4 * matrix[y % half][x % half] + quadrant
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(level: u8) -> Pixel {
+        Pixel::new(level, level, level)
+    }
+
+    #[test]
+    fn bayer_matrix_test() {
+        assert_eq!(bayer_matrix(2), vec![vec![0, 2], vec![3, 1]]);
+        assert_eq!(bayer_matrix(4)[0], vec![0, 8, 2, 10]);
+    }
+
+    #[test]
+    fn dither_test() {
+        let palette = [gray(0), gray(255)];
+        let pixels = vec![gray(128); 16];
+
+        for dither in [
+            Dither::FloydSteinberg {
+                lock_unchanged: false,
+            },
+            Dither::Bayer4,
+        ] {
+            let indexes = Ditherer::new(dither).dither(&pixels, 4, (0, 0), 4, &palette);
+            // Mid gray is shown as much black as white
+            assert_eq!(indexes.iter().sum::<usize>(), 8, "{dither:?}");
+        }
+        let indexes = Ditherer::new(Dither::None).dither(&pixels, 4, (0, 0), 4, &palette);
+        assert_eq!(indexes.iter().sum::<usize>(), 16);
+    }
+
+    #[test]
+    fn dither_lock_unchanged_test() {
+        let palette = [gray(0), gray(255)];
+        let mut ditherer = Ditherer::new(Dither::FloydSteinberg {
+            lock_unchanged: true,
+        });
+        let mut pixels = vec![gray(100); 16];
+
+        let first = ditherer.dither(&pixels, 4, (0, 0), 4, &palette);
+        pixels[0] = gray(250);
+        let second = ditherer.dither(&pixels, 4, (0, 0), 4, &palette);
+
+        assert_eq!(second[0], 1);
+        assert_eq!(first[1..], second[1..]);
+    }
+
+    #[test]
+    fn dither_moved_shape_test() {
+        const WIDTH: usize = 16;
+        // Only the columns the square leaves or reaches change
+        const LEFT: usize = 2;
+        const AREA_WIDTH: usize = 6;
+
+        fn area<T: Clone>(pixels: &[T]) -> Vec<T> {
+            pixels
+                .chunks(WIDTH)
+                .flat_map(|row| row[LEFT..LEFT + AREA_WIDTH].to_vec())
+                .collect()
+        }
+
+        // A red square moving right over a horizontal gradient
+        let frame = |shape: usize| -> Vec<Pixel> {
+            (0..WIDTH * 4)
+                .map(|index| match index % WIDTH {
+                    x if (shape..shape + 2).contains(&x) => Pixel::new(255, 0, 0),
+                    x => gray(x as u8 * 16),
+                })
+                .collect()
+        };
+        let (first, second) = (frame(LEFT), frame(LEFT + 4));
+        let palette = [gray(0), gray(85), gray(170), gray(255)];
+
+        for dither in [
+            Dither::FloydSteinberg {
+                lock_unchanged: true,
+            },
+            Dither::Bayer4,
+            Dither::Bayer8,
+        ] {
+            let mut ditherer = Ditherer::new(dither);
+            let full = ditherer.dither(&first, WIDTH, (0, 0), WIDTH, &palette);
+            let changes = ditherer.dither(&area(&second), AREA_WIDTH, (LEFT, 0), WIDTH, &palette);
+
+            let pixels = area(&first).into_iter().zip(area(&second));
+            let indexes = area(&full).into_iter().zip(changes);
+            for ((before, after), (index_before, index_after)) in pixels.zip(indexes) {
+                if before == after {
+                    assert_eq!(index_before, index_after, "{dither:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,801 @@
+use std::{fmt::Display, ops::Range, rc::Rc};
+
+use crate::{
+    common::types::Color,
+    format::gif::{
+        gif::{Gif, Loop, SIGNATURE, TRAILER_MARKER},
+        gif_image::{
+            Disposal, GCE_BYTE_SIZE, GRAPHIC_CONTROL_LABEL, GifImage, IMAGE_SEPARATOR,
+            interlaced_rows,
+        },
+        metadata::{
+            APPLICATION_BLOCK_SIZE, APPLICATION_LABEL, ApplicationData, COMMENT_LABEL,
+            EXTENSION_INTRODUCER, PLAIN_TEXT_BLOCK_SIZE, PLAIN_TEXT_LABEL, PlainText,
+        },
+    },
+};
+
+pub(crate) const VERSIONS: [[u8; 3]; 2] = [*b"87a", *b"89a"];
+/// Application Extensions making animations loop, the first one being the one written
+const LOOP_APPLICATIONS: [&[u8; 11]; 2] = [b"NETSCAPE2.0", b"ANIMEXTS1.0"];
+/// Number of codes of the LZW code table, codes being at most 12 bits
+const CODE_TABLE_MAX_SIZE: usize = 4096;
+const MAX_CODE_SIZE: u8 = 12;
+
+/// Reason a GIF cannot be decoded, along with the offset of the block at fault
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// The bytes do not start with the GIF signature
+    NotAGif,
+    /// A version other than `87a` and `89a`
+    UnsupportedVersion([u8; 3]),
+    /// The bytes end before the trailer, in the middle of the block starting at `offset`
+    Truncated { offset: usize },
+    /// A byte that starts no known block
+    UnknownBlock { offset: usize, byte: u8 },
+    /// An extension whose fixed-size block does not have the size the spec gives it
+    InvalidBlockSize { offset: usize, label: u8, size: u8 },
+    /// An image with neither a local nor a global color table
+    MissingColorTable { offset: usize },
+    /// An LZW minimum code size outside 2 to 8
+    InvalidCodeSize { offset: usize, size: u8 },
+    /// LZW data using a code missing from the code table
+    InvalidCode { offset: usize, code: usize },
+    /// LZW data ending before the last pixel of the image
+    MissingPixels {
+        offset: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A pixel using a color past the end of the color table of the image
+    ColorIndexOutOfTable { offset: usize, index: usize },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::NotAGif => write!(f, "not a GIF file"),
+            DecodeError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported GIF version `{}`",
+                String::from_utf8_lossy(version)
+            ),
+            DecodeError::Truncated { offset } => {
+                write!(f, "the file ends in the block at byte {offset}")
+            }
+            DecodeError::UnknownBlock { offset, byte } => {
+                write!(f, "unknown block 0x{byte:02X} at byte {offset}")
+            }
+            DecodeError::InvalidBlockSize {
+                offset,
+                label,
+                size,
+            } => write!(
+                f,
+                "extension 0x{label:02X} at byte {offset} has a block of {size} bytes"
+            ),
+            DecodeError::MissingColorTable { offset } => {
+                write!(f, "the image at byte {offset} has no color table")
+            }
+            DecodeError::InvalidCodeSize { offset, size } => write!(
+                f,
+                "the image data at byte {offset} has a minimum code size of {size}, \
+                 expected 2 to 8"
+            ),
+            DecodeError::InvalidCode { offset, code } => write!(
+                f,
+                "the image data at byte {offset} uses code {code}, missing from the code table"
+            ),
+            DecodeError::MissingPixels {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the image data at byte {offset} holds {found} pixels out of {expected}"
+            ),
+            DecodeError::ColorIndexOutOfTable { offset, index } => write!(
+                f,
+                "the image data at byte {offset} uses color {index}, past the end of its \
+                 color table"
+            ),
+        }
+    }
+}
+
+impl DecodeError {
+    /// Offset of the block at fault, the version following the signature
+    pub fn offset(&self) -> usize {
+        match self {
+            DecodeError::NotAGif => 0,
+            DecodeError::UnsupportedVersion(_) => SIGNATURE.len(),
+            DecodeError::Truncated { offset }
+            | DecodeError::UnknownBlock { offset, .. }
+            | DecodeError::InvalidBlockSize { offset, .. }
+            | DecodeError::MissingColorTable { offset }
+            | DecodeError::InvalidCodeSize { offset, .. }
+            | DecodeError::InvalidCode { offset, .. }
+            | DecodeError::MissingPixels { offset, .. }
+            | DecodeError::ColorIndexOutOfTable { offset, .. } => *offset,
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decode a GIF into its images and metadata, the way `Gif` would encode them. Extensions
+/// that are not known are skipped, as by other decoders.
+pub fn decode(bytes: &[u8]) -> Result<Gif, DecodeError> {
+    Decoder::new(bytes, true).decode()
+}
+
+/// Same as `decode` without decoding the image data, the images being left out, which
+/// reads the metadata of a large GIF quickly
+pub fn decode_without_images(bytes: &[u8]) -> Result<Gif, DecodeError> {
+    Decoder::new(bytes, false).decode()
+}
+
+/// Kind of a block of a GIF, color tables and image data being blocks of their own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Block {
+    Header,
+    ScreenDescriptor,
+    GlobalColorTable,
+    GraphicControl,
+    Application,
+    Comment,
+    PlainText,
+    /// Extension with a label the spec does not define
+    Extension,
+    ImageDescriptor,
+    LocalColorTable,
+    ImageData,
+    Trailer,
+}
+
+/// Blocks of a GIF in the order of its bytes, along with the bytes each one spans
+pub(crate) fn blocks(bytes: &[u8]) -> Result<Vec<(Block, Range<usize>)>, DecodeError> {
+    let mut decoder = Decoder::new(bytes, false);
+    decoder.decode()?;
+    Ok(decoder.blocks)
+}
+
+/// What a Graphic Control Extension sets for the image after it
+struct GraphicControl {
+    delay: u16,
+    disposal: Disposal,
+    transparent_index: Option<usize>,
+}
+
+struct Decoder<'a> {
+    cursor: Cursor<'a>,
+    has_global_color_table: bool,
+    decode_images: bool,
+    /// Blocks read so far
+    blocks: Vec<(Block, Range<usize>)>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8], decode_images: bool) -> Self {
+        Decoder {
+            cursor: Cursor::new(bytes),
+            has_global_color_table: false,
+            decode_images,
+            blocks: Vec::new(),
+        }
+    }
+
+    fn decode(&mut self) -> Result<Gif, DecodeError> {
+        let version = self.cursor.version()?;
+        if !VERSIONS.contains(&version) {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        self.end_block(Block::Header, 0);
+
+        // Logical screen descriptor, followed by the global color table when its flag is set
+        let screen = self.cursor.offset;
+        self.cursor.block = screen;
+        let width = self.cursor.u16()?;
+        let height = self.cursor.u16()?;
+        let packed = self.cursor.byte()?;
+        let background_index = self.cursor.byte()?;
+        let pixel_aspect_ratio = self.cursor.byte()?;
+
+        self.end_block(Block::ScreenDescriptor, screen);
+
+        let mut gif = Gif::new(height, width, None);
+        gif.set_background_index(background_index)
+            .set_pixel_aspect_ratio(pixel_aspect_ratio);
+        if packed & 0x80 != 0 {
+            let table = self.cursor.offset;
+            *gif.global_color_table_ref().borrow_mut() = self.cursor.color_table(packed)?;
+            self.end_block(Block::GlobalColorTable, table);
+            self.has_global_color_table = true;
+        }
+
+        let mut control: Option<GraphicControl> = None;
+        loop {
+            self.cursor.block = self.cursor.offset;
+            match self.cursor.byte()? {
+                TRAILER_MARKER => {
+                    self.end_block(Block::Trailer, self.cursor.block);
+                    return Ok(gif);
+                }
+                EXTENSION_INTRODUCER => match self.cursor.byte()? {
+                    GRAPHIC_CONTROL_LABEL => {
+                        control = Some(self.graphic_control()?);
+                        self.end_block(Block::GraphicControl, self.cursor.block);
+                    }
+                    APPLICATION_LABEL => {
+                        self.block_size(APPLICATION_LABEL, APPLICATION_BLOCK_SIZE)?;
+                        let application = ApplicationData {
+                            identifier: self.cursor.take(8)?.try_into().unwrap(),
+                            authentication: self.cursor.take(3)?.try_into().unwrap(),
+                            data: self.cursor.sub_blocks()?,
+                        };
+                        match loop_count(&application) {
+                            Some(num_loop) => gif.set_loop(Some(num_loop)),
+                            None => gif.add_application_data(application),
+                        };
+                        self.end_block(Block::Application, self.cursor.block);
+                    }
+                    COMMENT_LABEL => {
+                        let text = self.cursor.sub_blocks()?;
+                        gif.add_comment(&String::from_utf8_lossy(&text));
+                        self.end_block(Block::Comment, self.cursor.block);
+                    }
+                    PLAIN_TEXT_LABEL => {
+                        gif.add_plain_text(self.plain_text()?);
+                        self.end_block(Block::PlainText, self.cursor.block);
+                        // The Graphic Control Extension before the text applies to it
+                        control = None;
+                    }
+                    // Other extensions are sub-blocks as well
+                    _ => {
+                        self.cursor.sub_blocks()?;
+                        self.end_block(Block::Extension, self.cursor.block);
+                    }
+                },
+                IMAGE_SEPARATOR => {
+                    if let Some(image) = self.image(&gif, control.take())? {
+                        gif.push_image(image);
+                    }
+                }
+                byte => {
+                    return Err(DecodeError::UnknownBlock {
+                        offset: self.cursor.block,
+                        byte,
+                    });
+                }
+            }
+        }
+    }
+
+    fn graphic_control(&mut self) -> Result<GraphicControl, DecodeError> {
+        self.block_size(GRAPHIC_CONTROL_LABEL, GCE_BYTE_SIZE)?;
+        let packed = self.cursor.byte()?;
+        let delay = self.cursor.u16()?;
+        let transparent_index = self.cursor.byte()?;
+        self.cursor.sub_blocks()?;
+
+        Ok(GraphicControl {
+            delay,
+            disposal: Disposal::from_method((packed >> 2) & 0x07),
+            transparent_index: (packed & 0x01 != 0).then_some(usize::from(transparent_index)),
+        })
+    }
+
+    fn plain_text(&mut self) -> Result<PlainText, DecodeError> {
+        self.block_size(PLAIN_TEXT_LABEL, PLAIN_TEXT_BLOCK_SIZE)?;
+        Ok(PlainText {
+            left: self.cursor.u16()?,
+            top: self.cursor.u16()?,
+            width: self.cursor.u16()?,
+            height: self.cursor.u16()?,
+            cell_width: self.cursor.byte()?,
+            cell_height: self.cursor.byte()?,
+            foreground_index: self.cursor.byte()?,
+            background_index: self.cursor.byte()?,
+            text: String::from_utf8_lossy(&self.cursor.sub_blocks()?).into_owned(),
+        })
+    }
+
+    /// Image descriptor, local color table and image data, `None` when the images are not
+    /// decoded
+    fn image(
+        &mut self,
+        gif: &Gif,
+        control: Option<GraphicControl>,
+    ) -> Result<Option<GifImage>, DecodeError> {
+        let left = self.cursor.u16()?;
+        let top = self.cursor.u16()?;
+        let width = self.cursor.u16()?;
+        let height = self.cursor.u16()?;
+        let packed = self.cursor.byte()?;
+        self.end_block(Block::ImageDescriptor, self.cursor.block);
+        let local_color_table = if packed & 0x80 != 0 {
+            let table = self.cursor.offset;
+            let color_table = self.cursor.color_table(packed)?;
+            self.end_block(Block::LocalColorTable, table);
+            Some(color_table)
+        } else if self.has_global_color_table {
+            None
+        } else {
+            return Err(DecodeError::MissingColorTable {
+                offset: self.cursor.block,
+            });
+        };
+
+        let offset = self.cursor.offset;
+        let min_code_size = self.cursor.byte()?;
+        if !(2..=8).contains(&min_code_size) {
+            return Err(DecodeError::InvalidCodeSize {
+                offset,
+                size: min_code_size,
+            });
+        }
+        let data = self.cursor.sub_blocks()?;
+        self.end_block(Block::ImageData, offset);
+        if !self.decode_images {
+            return Ok(None);
+        }
+
+        let mut image = match local_color_table {
+            Some(color_table) => {
+                let image = GifImage::new(height, width, None);
+                *image.color_table().borrow_mut() = color_table;
+                image
+            }
+            None => GifImage::new(height, width, Some(Rc::clone(gif.global_color_table_ref()))),
+        };
+
+        let color_count = image.color_table().borrow().len();
+
+        // The indexes only take the memory of the pixels the data holds, whatever the size
+        // the image descriptor claims
+        let pixel_count = usize::from(width) * usize::from(height);
+        let mut indexes = Vec::new();
+        decode_lzw(min_code_size, &data, pixel_count, |index| {
+            indexes.push(index)
+        })
+        .map_err(|code| DecodeError::InvalidCode { offset, code })?;
+        if indexes.len() < pixel_count {
+            return Err(DecodeError::MissingPixels {
+                offset,
+                expected: pixel_count,
+                found: indexes.len(),
+            });
+        }
+        if let Some(&index) = indexes
+            .iter()
+            .find(|&&index| usize::from(index) >= color_count)
+        {
+            return Err(DecodeError::ColorIndexOutOfTable {
+                offset,
+                index: usize::from(index),
+            });
+        }
+
+        // Interlaced rows come in the order of the passes
+        let interlaced = packed & 0x40 != 0;
+        if interlaced {
+            let width = usize::from(width);
+            let mut rows = vec![0; indexes.len()];
+            for (pass_row, row) in interlaced_rows(usize::from(height)).enumerate() {
+                rows[row * width..(row + 1) * width]
+                    .copy_from_slice(&indexes[pass_row * width..(pass_row + 1) * width]);
+            }
+            indexes = rows;
+        }
+
+        image.set_color_indexes(indexes.into_iter().map(usize::from));
+        image.add_left(left).add_top(top).set_interlaced(interlaced);
+        if let Some(control) = control {
+            image
+                .add_delay(control.delay)
+                .set_disposal(control.disposal)
+                .set_transparent_index(control.transparent_index);
+        }
+
+        Ok(Some(image))
+    }
+
+    /// Record the block from `start` to the current offset
+    fn end_block(&mut self, block: Block, start: usize) {
+        self.blocks.push((block, start..self.cursor.offset));
+    }
+
+    /// Check the size starting the fixed-size block of an extension
+    fn block_size(&mut self, label: u8, expected: u8) -> Result<(), DecodeError> {
+        match self.cursor.byte()? {
+            size if size == expected => Ok(()),
+            size => Err(DecodeError::InvalidBlockSize {
+                offset: self.cursor.block,
+                label,
+                size,
+            }),
+        }
+    }
+}
+
+/// Reads the bytes of a GIF in order, failing when they end before the block being read
+pub(crate) struct Cursor<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) offset: usize,
+    /// Start of the block being read, reported when the bytes end in it
+    pub(crate) block: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Cursor {
+            bytes,
+            offset: 0,
+            block: 0,
+        }
+    }
+
+    /// Version following the signature, which the bytes must start with
+    pub(crate) fn version(&mut self) -> Result<[u8; 3], DecodeError> {
+        if !self.bytes.starts_with(&SIGNATURE) {
+            return Err(DecodeError::NotAGif);
+        }
+        self.offset = SIGNATURE.len();
+        Ok(self.take(3)?.try_into().unwrap())
+    }
+
+    /// Color table whose size is given by the packed field of its descriptor
+    pub(crate) fn color_table(&mut self, packed: u8) -> Result<Vec<Color>, DecodeError> {
+        let bytes = self.take(3 << ((packed & 0x07) + 1))?;
+        Ok(bytes
+            .chunks(3)
+            .map(|color| Color::new(color[0], color[1], color[2]))
+            .collect())
+    }
+
+    /// Data of the sub-blocks up to their terminator
+    pub(crate) fn sub_blocks(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let mut data = Vec::new();
+        loop {
+            let size = usize::from(self.byte()?);
+            if size == 0 {
+                return Ok(data);
+            }
+            data.extend_from_slice(self.take(size)?);
+        }
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + len)
+            .ok_or(DecodeError::Truncated { offset: self.block })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+}
+
+/// Number of loops set by an Application Extension making the animation loop, `None` for
+/// other applications
+fn loop_count(application: &ApplicationData) -> Option<Loop> {
+    let name: Vec<u8> = [&application.identifier[..], &application.authentication].concat();
+    match application.data[..] {
+        [0x01, low, high] if LOOP_APPLICATIONS.iter().any(|known| known[..] == name[..]) => {
+            Some(match u16::from_le_bytes([low, high]) {
+                0 => Loop::Forever,
+                count => Loop::Repeat(count),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Pass the color indexes of LZW data to `index`, at most `pixel_count` of them and fewer
+/// when the data ends early. Returns their number along with whether the end of
+/// information code follows the last of them. Fails with the first code missing from the
+/// code table.
+pub(crate) fn decode_lzw(
+    min_code_size: u8,
+    data: &[u8],
+    pixel_count: usize,
+    mut index: impl FnMut(u8),
+) -> Result<(usize, bool), usize> {
+    let (cc, eoi) = (1 << min_code_size, (1 << min_code_size) + 1);
+
+    // Each code after EOI stands for the sequence of a prefix code followed by an index,
+    // the first index of the sequence being kept for the code added after it
+    let mut prefixes = [0u16; CODE_TABLE_MAX_SIZE];
+    let mut suffixes = [0u8; CODE_TABLE_MAX_SIZE];
+    let mut firsts = [0u8; CODE_TABLE_MAX_SIZE];
+    for code in 0..cc {
+        suffixes[code] = code as u8;
+        firsts[code] = code as u8;
+    }
+    let mut table_len = eoi + 1;
+    let mut code_size = min_code_size + 1;
+    let mut previous: Option<usize> = None;
+
+    let mut count = 0;
+    // Whether a sequence holds pixels past the image
+    let mut past_image = false;
+    let mut sequence = Vec::new();
+    let mut bytes = data.iter();
+    let (mut bits, mut bit_count) = (0u32, 0u8);
+
+    let end_code = loop {
+        while bit_count < code_size {
+            let Some(&byte) = bytes.next() else {
+                return Ok((count, false));
+            };
+            bits |= u32::from(byte) << bit_count;
+            bit_count += 8;
+        }
+        let code = (bits & ((1 << code_size) - 1)) as usize;
+        bits >>= code_size;
+        bit_count -= code_size;
+
+        if code == cc {
+            table_len = eoi + 1;
+            code_size = min_code_size + 1;
+            previous = None;
+            continue;
+        }
+        if code == eoi {
+            break true;
+        }
+        // Pixels past the image, which decoders leave out
+        if count >= pixel_count {
+            break false;
+        }
+
+        let Some(prefix) = previous else {
+            if code > cc {
+                return Err(code);
+            }
+            index(code as u8);
+            count += 1;
+            previous = Some(code);
+            continue;
+        };
+        // A code that is not in the table yet is the previous sequence followed by its own
+        // first index
+        let first = match code {
+            code if code < table_len => firsts[code],
+            code if code == table_len => firsts[prefix],
+            code => return Err(code),
+        };
+        if table_len < CODE_TABLE_MAX_SIZE {
+            prefixes[table_len] = prefix as u16;
+            suffixes[table_len] = first;
+            firsts[table_len] = firsts[prefix];
+            table_len += 1;
+            if table_len == 1 << code_size && code_size < MAX_CODE_SIZE {
+                code_size += 1;
+            }
+        }
+
+        sequence.clear();
+        let mut current = code;
+        while current > eoi {
+            sequence.push(suffixes[current]);
+            current = usize::from(prefixes[current]);
+        }
+        sequence.push(current as u8);
+        for &value in sequence.iter().rev() {
+            if count == pixel_count {
+                past_image = true;
+                break;
+            }
+            index(value);
+            count += 1;
+        }
+        previous = Some(code);
+    };
+
+    // The last sequence may hold pixels past the image as well
+    Ok((count, end_code && !past_image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::types::Pixel,
+        format::gif::metadata::{application_extension, comment_extension},
+    };
+
+    #[test]
+    fn decode_test() {
+        let (red, green, blue) = (
+            Pixel::new(255, 0, 0),
+            Pixel::new(0, 255, 0),
+            Pixel::new(0, 0, 255),
+        );
+        let mut gif = Gif::new(9, 3, Some(Loop::Repeat(3)));
+        gif.set_transparency()
+            .set_pixel_aspect_ratio(17)
+            .add_comment("made by GAS")
+            .add_application_data(ApplicationData::gas("script: a.gas"));
+        gif.add_image()
+            .add_delay(10)
+            .set_disposal(Disposal::RestoreToBackground)
+            .set_pixels_with_transparency(
+                [Some(&red), None, Some(&green)]
+                    .into_iter()
+                    .cycle()
+                    .take(27),
+            );
+        gif.add_local_image()
+            .set_interlaced(true)
+            .set_pixels([&blue, &red, &green].into_iter().cycle().take(27));
+        let bytes = gif.as_bytes().unwrap();
+
+        let mut decoded = decode(&bytes).unwrap();
+        assert_eq!((decoded.height(), decoded.width()), (9, 3));
+        assert!(matches!(decoded.num_loop(), Some(Loop::Repeat(3))));
+        assert_eq!(decoded.background_index(), 0);
+        assert_eq!(decoded.pixel_aspect_ratio(), 17);
+        assert_eq!(decoded.metadata(), gif.metadata());
+
+        let images = decoded.images();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].delay(), 10);
+        assert_eq!(images[0].disposal(), Disposal::RestoreToBackground);
+        assert_eq!(images[0].transparent_index(), Some(0));
+        assert_eq!(
+            images[0].pixels()[..3],
+            [Some(red.clone()), None, Some(green)]
+        );
+        assert!(images[1].has_local_color_table() && images[1].interlaced());
+        assert_eq!(images[1].pixels()[3..5], [Some(blue), Some(red)]);
+
+        // Encoding the decoded GIF gives back the same bytes
+        assert_eq!(decoded.as_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn decode_without_images_test() {
+        let application = ApplicationData::gas("script: a.gas");
+        let mut bytes = b"GIF89a".to_vec();
+        // 1x1 screen with a global color table of 2 colors
+        bytes.extend([1, 0, 1, 0, 0x80, 0, 0, 0, 0, 0, 255, 255, 255]);
+        bytes.extend(comment_extension("made by hand"));
+        bytes.extend(application_extension(&application));
+        // Image with a local color table, then its data
+        bytes.extend([0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0x80, 1, 2, 3, 4, 5, 6]);
+        bytes.extend([2, 2, 0x4C, 0x01, 0]);
+        bytes.push(TRAILER_MARKER);
+
+        let gif = decode_without_images(&bytes).unwrap();
+        assert!(gif.images().is_empty());
+        assert_eq!(gif.metadata().comments, vec!["made by hand".to_string()]);
+        assert_eq!(gif.metadata().applications, vec![application]);
+
+        let gif = decode(&bytes).unwrap();
+        assert_eq!(gif.images()[0].pixels(), vec![Some(Pixel::new(4, 5, 6))]);
+    }
+
+    #[test]
+    fn blocks_test() {
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend([1, 0, 1, 0, 0x80, 0, 0, 0, 0, 0, 255, 255, 255]);
+        bytes.extend(comment_extension("hi"));
+        bytes.extend([0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0x80, 1, 2, 3, 4, 5, 6]);
+        bytes.extend([2, 2, 0x4C, 0x01, 0]);
+        bytes.push(TRAILER_MARKER);
+
+        assert_eq!(
+            blocks(&bytes),
+            Ok(vec![
+                (Block::Header, 0..6),
+                (Block::ScreenDescriptor, 6..13),
+                (Block::GlobalColorTable, 13..19),
+                (Block::Comment, 19..25),
+                (Block::ImageDescriptor, 25..35),
+                (Block::LocalColorTable, 35..41),
+                (Block::ImageData, 41..46),
+                (Block::Trailer, 46..47),
+            ])
+        );
+    }
+
+    #[test]
+    fn decode_error_test() {
+        let mut gif = Gif::new(1, 2, None);
+        gif.add_image()
+            .set_pixels(&[Pixel::new(1, 2, 3), Pixel::new(4, 5, 6)]);
+        let bytes = gif.as_bytes().unwrap();
+        // Header, screen descriptor and 4 colors, then the image descriptor
+        let image = 6 + 7 + 12;
+        let data = image + 10;
+        let with = |offset: usize, byte: u8| {
+            let mut bytes = bytes.clone();
+            bytes[offset] = byte;
+            decode(&bytes).err()
+        };
+
+        assert_eq!(decode(b"PNG").err(), Some(DecodeError::NotAGif));
+        assert_eq!(
+            decode(b"GIF90a").err(),
+            Some(DecodeError::UnsupportedVersion(*b"90a"))
+        );
+        assert_eq!(
+            decode(&bytes[..bytes.len() - 3]).err(),
+            Some(DecodeError::Truncated { offset: image })
+        );
+        assert_eq!(
+            with(image, 0x00),
+            Some(DecodeError::UnknownBlock {
+                offset: image,
+                byte: 0
+            })
+        );
+        let mut no_color_table = b"GIF89a".to_vec();
+        no_color_table.extend([1, 0, 1, 0, 0, 0, 0]);
+        no_color_table.extend([0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0, 2, 2, 0x4C, 0x01, 0, 0x3B]);
+        assert_eq!(
+            decode(&no_color_table).err(),
+            Some(DecodeError::MissingColorTable { offset: 13 })
+        );
+        assert_eq!(
+            with(data, 12),
+            Some(DecodeError::InvalidCodeSize {
+                offset: data,
+                size: 12
+            })
+        );
+        // A width of 3 pixels, the data holding 2
+        assert_eq!(
+            with(image + 5, 3),
+            Some(DecodeError::MissingPixels {
+                offset: data,
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn decode_huge_image_test() {
+        // A single pixel of data for an image of 65535x65535 pixels
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend([0xFF, 0xFF, 0xFF, 0xFF, 0x80, 0, 0, 0, 0, 0, 255, 255, 255]);
+        bytes.extend([0x2C, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0]);
+        bytes.extend([2, 2, 0x4C, 0x01, 0, TRAILER_MARKER]);
+
+        assert_eq!(
+            decode(&bytes).err(),
+            Some(DecodeError::MissingPixels {
+                offset: 29,
+                expected: 65535 * 65535,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn decode_lzw_test() {
+        let decode = |data: &[u8], pixel_count| {
+            let mut indexes = Vec::new();
+            decode_lzw(2, data, pixel_count, |index| indexes.push(index))
+                .map(|(count, end_code)| (indexes, count, end_code))
+        };
+
+        // Clear code, 1, then end of information
+        assert_eq!(decode(&[0x4C, 0x01], 1), Ok((vec![1], 1, true)));
+        // Clear code, then a code past the table
+        assert_eq!(decode(&[0x3C], 1), Err(7));
+        // Data ending before the pixels
+        assert_eq!(decode(&[0x4C], 4), Ok((vec![1], 1, false)));
+        // A pixel past the image
+        assert_eq!(decode(&[0x4C, 0x01], 0), Ok((vec![], 0, false)));
+    }
+}
@@ -0,0 +1,861 @@
+use std::{cell::RefCell, collections::HashSet, io::Write, rc::Rc};
+
+use crate::{
+    common::types::Color,
+    format::{
+        dither::{Dither, Ditherer},
+        gif::{
+            gif::{
+                ColorPolicy, GifError, Loop, MAX_COLORS, SIGNATURE, ScreenDescriptor,
+                TRAILER_MARKER, VERSION, application_extension_block, color_table_size_bits,
+            },
+            gif_image::{Disposal, GifImage},
+            metadata::{self, ApplicationData, PlainText},
+        },
+        quantize::{self, Quantizer},
+    },
+};
+
+/// How much of the canvas each image covers
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Delta {
+    /// Every image covers the whole canvas
+    #[default]
+    Full,
+    /// Images only cover the smallest rectangle holding the pixels changed since the frame
+    /// shown before them. With `transparent_unchanged`, the pixels of that rectangle that
+    /// did not change are transparent when the global color table has a transparent
+    /// index, which makes longer runs for LZW.
+    Changes { transparent_unchanged: bool },
+}
+
+/// Rectangle of the canvas, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Area {
+    /// Indexes of the pixels of the area in a canvas `width` pixels wide, row by row
+    fn indexes(self, width: usize) -> impl Iterator<Item = usize> {
+        (self.top..self.top + self.height)
+            .flat_map(move |y| (self.left..self.left + self.width).map(move |x| y * width + x))
+    }
+}
+
+/// Frame shown once the last image is drawn, in the colors given to the encoder
+struct Canvas {
+    pixels: Vec<Option<Color>>,
+    /// Area of the last image, along with what it covered and how it is disposed of before
+    /// the next image
+    area: Area,
+    covered: Vec<Option<Color>>,
+    /// Pixels of the last image, `None` being transparent
+    image: Vec<Option<Color>>,
+    disposal: Disposal,
+}
+
+/// Writes a GIF as it goes: the header and the color table when created, then each image
+/// when it is added and the trailer on `finish`. Only the image being written is held in
+/// memory, whatever the number of images.
+pub struct GifEncoder<W: Write> {
+    writer: W,
+    height: u16,
+    width: u16,
+    color_table: Rc<RefCell<Vec<Color>>>,
+    /// Number of colors written in the global color table, padding included
+    written_colors: usize,
+    /// Colors of the global color table, but the transparent one
+    global_colors: HashSet<[u8; 3]>,
+    /// Index of the global color table standing for transparent pixels
+    transparent_index: Option<usize>,
+    color_policy: ColorPolicy,
+    quantizer: Quantizer,
+    /// Number of images whose colors were replaced by the closest ones of a palette
+    quantized_images: usize,
+    ditherer: Ditherer,
+    /// Disposal of the images created by the encoder
+    disposal: Disposal,
+    /// Whether the images created by the encoder are interlaced
+    interlaced: bool,
+    delta: Delta,
+    /// Frame shown before the next image, kept when writing only the changes
+    canvas: Option<Canvas>,
+    /// Last image along with its encoded image block, written once an image differs from
+    /// it so that identical images are merged into a longer delay
+    last: Option<(GifImage, Vec<u8>)>,
+    /// Number of images merged into the one before them
+    merged_images: usize,
+}
+
+impl<W: Write> GifEncoder<W> {
+    /// Write the header, the logical screen descriptor and a global color table holding
+    /// `colors`, followed by the looping block when `num_loop` is set
+    pub fn new(
+        writer: W,
+        height: u16,
+        width: u16,
+        colors: Vec<Color>,
+        num_loop: Option<Loop>,
+    ) -> Result<Self, GifError> {
+        Self::with_screen(
+            writer,
+            ScreenDescriptor::new(height, width),
+            colors,
+            num_loop,
+        )
+    }
+
+    /// Same as `new`, the logical screen descriptor setting the background color index
+    /// and the pixel aspect ratio as well
+    pub fn with_screen(
+        writer: W,
+        screen: ScreenDescriptor,
+        colors: Vec<Color>,
+        num_loop: Option<Loop>,
+    ) -> Result<Self, GifError> {
+        Self::with_color_table(
+            writer,
+            &screen,
+            Rc::new(RefCell::new(colors)),
+            num_loop.as_ref(),
+        )
+    }
+
+    /// Same as `with_screen` with a color table the images may already share
+    pub(crate) fn with_color_table(
+        mut writer: W,
+        screen: &ScreenDescriptor,
+        color_table: Rc<RefCell<Vec<Color>>>,
+        num_loop: Option<&Loop>,
+    ) -> Result<Self, GifError> {
+        let color_count = color_table.borrow().len();
+        if color_count > MAX_COLORS {
+            return Err(GifError::TooManyColors(color_count));
+        }
+        let global_color_bits = color_table_size_bits(color_count);
+
+        let mut bytes = Vec::new();
+
+        // add header block
+        bytes.extend_from_slice(&SIGNATURE);
+        bytes.extend_from_slice(&VERSION);
+
+        // add logistical screen descriptor
+        bytes.extend(screen.as_bytes(global_color_bits));
+
+        // add padding to the color table, the images encode their pixels with the
+        // padded size
+        let written_colors = {
+            let mut color_table = color_table.borrow_mut();
+            color_table.resize(1 << (global_color_bits + 1), Color::new(0, 0, 0));
+
+            for color in color_table.iter() {
+                bytes.extend(color.as_bytes());
+            }
+            color_table.len()
+        };
+        let global_colors = color_table.borrow().iter().map(Color::as_bytes).collect();
+
+        // Adding application extension if present
+        bytes.extend_from_slice(&application_extension_block(num_loop));
+
+        writer.write_all(&bytes)?;
+
+        Ok(GifEncoder {
+            writer,
+            height: screen.height,
+            width: screen.width,
+            color_table,
+            written_colors,
+            global_colors,
+            transparent_index: None,
+            color_policy: ColorPolicy::default(),
+            quantizer: Quantizer::default(),
+            quantized_images: 0,
+            ditherer: Ditherer::new(Dither::default()),
+            disposal: Disposal::default(),
+            interlaced: false,
+            delta: Delta::default(),
+            canvas: None,
+            last: None,
+            merged_images: 0,
+        })
+    }
+
+    /// Make a color of the global color table stand for transparent pixels, images using
+    /// the table never showing it otherwise
+    pub fn set_transparent_index(&mut self, index: Option<usize>) -> &mut Self {
+        self.transparent_index = index;
+        self.global_colors = self
+            .color_table
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != self.transparent_index)
+            .map(|(_, color)| color.as_bytes())
+            .collect();
+        self
+    }
+
+    /// Choose what happens to images using colors missing from the global color table
+    pub fn set_color_policy(&mut self, color_policy: ColorPolicy) -> &mut Self {
+        self.color_policy = color_policy;
+        self
+    }
+
+    /// Choose how the local color tables of images are reduced with
+    /// `ColorPolicy::QuantizeFrames`
+    pub fn set_quantizer(&mut self, quantizer: Quantizer) -> &mut Self {
+        self.quantizer = quantizer;
+        self
+    }
+
+    /// Choose how the pixels of quantized images are spread over the colors of the palette
+    pub fn set_dither(&mut self, dither: Dither) -> &mut Self {
+        self.ditherer = Ditherer::new(dither);
+        self
+    }
+
+    /// Choose the disposal of the images the encoder creates, which tells what the next
+    /// image is drawn over
+    pub fn set_disposal(&mut self, disposal: Disposal) -> &mut Self {
+        self.disposal = disposal;
+        self
+    }
+
+    /// Interlace the images the encoder creates
+    pub fn set_interlaced(&mut self, interlaced: bool) -> &mut Self {
+        self.interlaced = interlaced;
+        self
+    }
+
+    /// Choose how much of the canvas the images created by `image_with_pixels` cover. The
+    /// next image covers the whole canvas, the ones after it being compared to the frame
+    /// shown before them.
+    pub fn set_delta(&mut self, delta: Delta) -> &mut Self {
+        self.delta = delta;
+        self.canvas = None;
+        self
+    }
+
+    /// Number of images created by `image_with_pixels` whose colors were replaced by the
+    /// closest ones of a palette
+    pub fn quantized_images(&self) -> usize {
+        self.quantized_images
+    }
+
+    /// Number of images given to `write_image` that were merged into the image before them
+    pub fn merged_images(&self) -> usize {
+        self.merged_images
+    }
+
+    /// New image using the global color table, to be passed to `write_image` once drawn
+    pub fn new_image(&self) -> GifImage {
+        self.area_image(self.full_area(), false)
+    }
+
+    fn full_area(&self) -> Area {
+        Area {
+            left: 0,
+            top: 0,
+            width: usize::from(self.width),
+            height: usize::from(self.height),
+        }
+    }
+
+    /// New image covering `area`, using the global color table or its own
+    fn area_image(&self, area: Area, local: bool) -> GifImage {
+        let color_table = (!local).then(|| Rc::clone(&self.color_table));
+        let mut image = GifImage::new(area.height as u16, area.width as u16, color_table);
+        image
+            .add_left(area.left as u16)
+            .add_top(area.top as u16)
+            .set_disposal(self.disposal)
+            .set_interlaced(self.interlaced);
+        if !local {
+            image.set_transparent_index(self.transparent_index);
+        }
+        image
+    }
+
+    /// New image holding `pixels`, given row by row. It uses the global color table when
+    /// the table holds all of their colors, otherwise the color policy decides between an
+    /// error, a local color table, or the closest colors of a palette.
+    pub fn image_with_pixels(&mut self, pixels: &[Color]) -> Result<GifImage, GifError> {
+        let pixels: Vec<Option<Color>> = pixels.iter().cloned().map(Some).collect();
+        self.image_with_transparency(&pixels)
+    }
+
+    /// Same as `image_with_pixels`, `None` being a transparent pixel. Images using the
+    /// global color table need it to have a transparent index.
+    ///
+    /// Unless the delta is `Delta::Full`, the image only covers what changed since the
+    /// image created before it, the images being expected to be written in order.
+    pub fn image_with_transparency(
+        &mut self,
+        pixels: &[Option<Color>],
+    ) -> Result<GifImage, GifError> {
+        let (area, pixels) = match self.delta {
+            Delta::Full => (self.full_area(), pixels.to_vec()),
+            Delta::Changes {
+                transparent_unchanged,
+            } => self.changes(pixels, transparent_unchanged),
+        };
+
+        self.pixels_image(area, &pixels)
+    }
+
+    /// Area of the pixels changed since the frame shown before them along with the pixels
+    /// of that area, the frame being updated to show them
+    fn changes(
+        &mut self,
+        pixels: &[Option<Color>],
+        transparent_unchanged: bool,
+    ) -> (Area, Vec<Option<Color>>) {
+        let width = usize::from(self.width);
+        let full_area = self.full_area();
+
+        // Frame the image is drawn over once the last image is disposed of, along with the
+        // last image when it stays
+        let (shown, last) = match self.canvas.take() {
+            None => (None, None),
+            Some(canvas) => {
+                let mut shown = canvas.pixels;
+                let mut last = None;
+                match canvas.disposal {
+                    Disposal::Unspecified | Disposal::DoNotDispose => {
+                        last = Some((canvas.area, canvas.image));
+                    }
+                    Disposal::RestoreToBackground => {
+                        for index in canvas.area.indexes(width) {
+                            shown[index] = None;
+                        }
+                    }
+                    Disposal::RestoreToPrevious => {
+                        for (index, pixel) in canvas.area.indexes(width).zip(canvas.covered) {
+                            shown[index] = pixel;
+                        }
+                    }
+                }
+                (Some(shown), last)
+            }
+        };
+        // Transparent pixels let the frame shown through, so only opaque ones change it
+        let changed = |index: usize| {
+            pixels[index].is_some()
+                && shown
+                    .as_ref()
+                    .is_none_or(|shown| shown[index] != pixels[index])
+        };
+        let transparent_unchanged = transparent_unchanged && self.transparent_index.is_some();
+        let area_pixels = |area: Area| -> Vec<Option<Color>> {
+            area.indexes(width)
+                .map(|index| {
+                    if transparent_unchanged && !changed(index) {
+                        None
+                    } else {
+                        pixels[index].clone()
+                    }
+                })
+                .collect()
+        };
+
+        let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+        for index in (0..pixels.len()).filter(|index| changed(*index)) {
+            let (x, y) = (index % width, index / width);
+            (left, top) = (left.min(x), top.min(y));
+            (right, bottom) = (right.max(x), bottom.max(y));
+        }
+        let (area, area_pixels) = match (&shown, last) {
+            (None, _) => (full_area, area_pixels(full_area)),
+            // Nothing changed: drawing the last image again keeps the frame as it is, and
+            // lets the image be merged with it
+            (Some(_), Some(last)) if left == usize::MAX => last,
+            (Some(_), _) => {
+                if left == usize::MAX {
+                    // An image still needs a pixel
+                    (left, top, right, bottom) = (0, 0, 0, 0);
+                }
+                let area = Area {
+                    left,
+                    top,
+                    width: right - left + 1,
+                    height: bottom - top + 1,
+                };
+                (area, area_pixels(area))
+            }
+        };
+
+        let mut shown = shown.unwrap_or_else(|| vec![None; pixels.len()]);
+        let covered = area
+            .indexes(width)
+            .map(|index| shown[index].clone())
+            .collect();
+        for (index, pixel) in area.indexes(width).zip(&area_pixels) {
+            if pixel.is_some() {
+                shown[index] = pixel.clone();
+            }
+        }
+        self.canvas = Some(Canvas {
+            pixels: shown,
+            area,
+            covered,
+            image: area_pixels.clone(),
+            disposal: self.disposal,
+        });
+
+        (area, area_pixels)
+    }
+
+    /// Image covering `area` holding its pixels, given row by row
+    fn pixels_image(&mut self, area: Area, pixels: &[Option<Color>]) -> Result<GifImage, GifError> {
+        let transparent = pixels.iter().any(Option::is_none);
+        let mut previous = None;
+        let fits = (!transparent || self.transparent_index.is_some())
+            && pixels.iter().flatten().all(|color| {
+                // Consecutive pixels usually share the same color
+                let fits =
+                    previous == Some(color) || self.global_colors.contains(&color.as_bytes());
+                previous = Some(color);
+                fits
+            });
+
+        let mut image = match (fits, self.color_policy) {
+            (true, _) => self.area_image(area, false),
+            (false, ColorPolicy::Error) => return Err(GifError::ColorTableMismatch),
+            (false, ColorPolicy::LocalTables) => self.area_image(area, true),
+            (false, ColorPolicy::Quantize) => {
+                if transparent && self.transparent_index.is_none() {
+                    return Err(GifError::ColorTableMismatch);
+                }
+                let palette: Vec<Color> = self
+                    .color_table
+                    .borrow()
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| Some(*index) != self.transparent_index)
+                    .map(|(_, color)| color.clone())
+                    .collect();
+                let pixels = self.dither(pixels, area, &palette);
+                let mut image = self.area_image(area, false);
+                image.set_pixels_with_transparency(pixels.iter().map(Option::as_ref));
+                self.quantized_images += 1;
+                return Ok(image);
+            }
+            (false, ColorPolicy::QuantizeFrames) => {
+                let mut image = self.area_image(area, true);
+                // The transparent index takes a color of the table
+                let max_colors = MAX_COLORS - usize::from(transparent);
+                let histogram = quantize::histogram(pixels.iter().flatten());
+                if histogram.len() <= max_colors {
+                    image.set_pixels_with_transparency(pixels.iter().map(Option::as_ref));
+                } else {
+                    let palette = self.quantizer.palette(&histogram, max_colors);
+                    let pixels = self.dither(pixels, area, &palette);
+                    image.set_pixels_with_transparency(pixels.iter().map(Option::as_ref));
+                    self.quantized_images += 1;
+                }
+                return Ok(image);
+            }
+        };
+        image.set_pixels_with_transparency(pixels.iter().map(Option::as_ref));
+
+        Ok(image)
+    }
+
+    /// Colors of the palette the pixels of `area` are spread over, transparent pixels
+    /// staying so
+    fn dither(
+        &mut self,
+        pixels: &[Option<Color>],
+        area: Area,
+        palette: &[Color],
+    ) -> Vec<Option<Color>> {
+        let opaque: Vec<Color> = pixels
+            .iter()
+            .map(|pixel| pixel.clone().unwrap_or(Color::new(0, 0, 0)))
+            .collect();
+
+        self.ditherer
+            .dither(
+                &opaque,
+                area.width,
+                (area.left, area.top),
+                usize::from(self.width),
+                palette,
+            )
+            .into_iter()
+            .zip(pixels)
+            .map(|(index, pixel)| pixel.as_ref().map(|_| palette[index].clone()))
+            .collect()
+    }
+
+    /// Write an image created by `new_image` or `image_with_pixels`, returns its size in
+    /// bytes. An image showing the same as the one before it only lengthens its delay, its
+    /// size being the one of the Graphic Control Extension it may then need. The last image
+    /// is held back for that until the next one or `finish`.
+    pub fn write_image(&mut self, image: &GifImage) -> Result<usize, GifError> {
+        image.check_pixel_count()?;
+        if image.has_local_color_table() {
+            let color_count = image.color_table().borrow().len();
+            if color_count > MAX_COLORS {
+                return Err(GifError::TooManyColors(color_count));
+            }
+        } else if !Rc::ptr_eq(image.color_table(), &self.color_table)
+            || self.color_table.borrow().len() > self.written_colors
+        {
+            // Colors added to the table after it was written cannot be encoded anymore
+            return Err(GifError::ColorTableMismatch);
+        }
+
+        if let Some((last, _)) = &mut self.last
+            && last.same_frame(image)
+            && let Some(delay) = last.delay().checked_add(image.delay())
+        {
+            let had_gce = last.has_gce();
+            last.add_delay(delay);
+            self.merged_images += 1;
+            return Ok(if had_gce || !last.has_gce() {
+                0
+            } else {
+                last.get_gce().len()
+            });
+        }
+
+        self.write_last()?;
+        let block = image.get_image_block();
+        let size = if image.has_gce() {
+            image.get_gce().len() + block.len()
+        } else {
+            block.len()
+        };
+        self.last = Some((image.clone(), block));
+
+        Ok(size)
+    }
+
+    /// Write a Comment Extension holding `text`, after the images written before
+    pub fn write_comment(&mut self, text: &str) -> Result<(), GifError> {
+        self.write_last()?;
+        self.writer.write_all(&metadata::comment_extension(text))?;
+        Ok(())
+    }
+
+    /// Write an Application Extension, after the images written before
+    pub fn write_application_data(
+        &mut self,
+        application: &ApplicationData,
+    ) -> Result<(), GifError> {
+        self.write_last()?;
+        self.writer
+            .write_all(&metadata::application_extension(application))?;
+        Ok(())
+    }
+
+    /// Write a Plain Text Extension, after the images written before
+    pub fn write_plain_text(&mut self, plain_text: &PlainText) -> Result<(), GifError> {
+        self.write_last()?;
+        self.writer
+            .write_all(&metadata::plain_text_extension(plain_text))?;
+        Ok(())
+    }
+
+    /// Write the image held back by `write_image`
+    fn write_last(&mut self) -> Result<(), GifError> {
+        if let Some((image, block)) = self.last.take() {
+            if image.has_gce() {
+                self.writer.write_all(&image.get_gce())?;
+            }
+            self.writer.write_all(&block)?;
+        }
+        Ok(())
+    }
+
+    /// Write the trailer, returns the writer
+    pub fn finish(mut self) -> Result<W, GifError> {
+        self.write_last()?;
+        self.writer.write_all(&[TRAILER_MARKER])?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use crate::{
+        common::types::Pixel,
+        format::{
+            gif::{gif::Gif, validator::assert_valid},
+            image::ImageFormat,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn encoder_matches_gif_test() {
+        let (red, blue) = (Pixel::new(255, 0, 0), Pixel::new(0, 0, 255));
+
+        let mut gif = Gif::new(2, 2, Some(Loop::Forever));
+        gif.add_image().add_delay(4).fill(&red);
+        gif.add_image().add_delay(4).fill(&blue);
+
+        let mut encoder = GifEncoder::new(
+            Vec::new(),
+            2,
+            2,
+            vec![red.clone(), blue.clone()],
+            Some(Loop::Forever),
+        )
+        .unwrap();
+        for color in [&red, &blue] {
+            let mut image = encoder.new_image();
+            image.add_delay(4).fill(color);
+            encoder.write_image(&image).unwrap();
+        }
+
+        let bytes = encoder.finish().unwrap();
+        assert_valid(&bytes);
+        assert_eq!(bytes, gif.as_bytes().unwrap());
+    }
+
+    #[test]
+    fn encoder_local_color_table_test() {
+        let (red, blue) = (Pixel::new(255, 0, 0), Pixel::new(0, 0, 255));
+        let mut encoder = GifEncoder::new(Vec::new(), 1, 2, vec![red.clone()], None).unwrap();
+
+        let image = encoder
+            .image_with_pixels(&[red.clone(), red.clone()])
+            .unwrap();
+        assert!(!image.has_local_color_table());
+        let image = encoder
+            .image_with_pixels(&[red.clone(), blue.clone()])
+            .unwrap();
+        assert!(image.has_local_color_table());
+        encoder.write_image(&image).unwrap();
+        assert_valid(&encoder.finish().unwrap());
+    }
+
+    #[test]
+    fn encoder_color_policy_test() {
+        let (red, blue) = (Pixel::new(255, 0, 0), Pixel::new(0, 0, 255));
+        let mut encoder = GifEncoder::new(Vec::new(), 1, 2, vec![red.clone()], None).unwrap();
+        let pixels = [Pixel::new(200, 0, 30), blue];
+
+        encoder.set_color_policy(ColorPolicy::Error);
+        assert!(matches!(
+            encoder.image_with_pixels(&pixels),
+            Err(GifError::ColorTableMismatch)
+        ));
+
+        // Blue is closer to the black padding than to red
+        encoder.set_color_policy(ColorPolicy::Quantize);
+        let image = encoder.image_with_pixels(&pixels).unwrap();
+        assert!(!image.has_local_color_table());
+        assert_eq!(image.color_index_bound(), 2);
+        encoder.write_image(&image).unwrap();
+        assert_valid(&encoder.finish().unwrap());
+
+        // A frame of 300 colors gets a local color table reduced to 256
+        let mut encoder = GifEncoder::new(Vec::new(), 1, 300, vec![red], None).unwrap();
+        encoder.set_color_policy(ColorPolicy::QuantizeFrames);
+        let pixels: Vec<Pixel> = (0..300)
+            .map(|i| Pixel::new((i / 256) as u8, (i % 256) as u8, 0))
+            .collect();
+        let image = encoder.image_with_pixels(&pixels).unwrap();
+        assert!(image.has_local_color_table());
+        assert_eq!(image.color_index_bound(), MAX_COLORS);
+        assert_eq!(encoder.quantized_images(), 1);
+        encoder.write_image(&image).unwrap();
+        assert_valid(&encoder.finish().unwrap());
+    }
+
+    #[test]
+    fn encoder_color_table_mismatch_test() {
+        let mut encoder =
+            GifEncoder::new(Vec::new(), 1, 1, vec![Pixel::new(255, 0, 0)], None).unwrap();
+
+        // The table was padded to 4 colors, a fifth one cannot be encoded
+        let mut image = encoder.new_image();
+        for i in 0..4 {
+            image.fill(&Pixel::new(0, i, 255));
+        }
+        assert!(matches!(
+            encoder.write_image(&image),
+            Err(GifError::ColorTableMismatch)
+        ));
+
+        let foreign = GifImage::new(1, 1, Some(Rc::new(RefCell::new(Vec::new()))));
+        assert!(matches!(
+            encoder.write_image(&foreign),
+            Err(GifError::ColorTableMismatch)
+        ));
+    }
+
+    #[test]
+    fn encoder_pixel_count_mismatch_test() {
+        let red = Pixel::new(255, 0, 0);
+        let mut encoder = GifEncoder::new(Vec::new(), 1, 2, vec![red.clone()], None).unwrap();
+
+        for count in [1, 3] {
+            let mut image = encoder.new_image();
+            image.set_pixels(iter::repeat_n(&red, count));
+            assert!(matches!(
+                encoder.write_image(&image),
+                Err(GifError::PixelCountMismatch { expected: 2, found }) if found == count
+            ));
+        }
+
+        // Nor through a `Gif`
+        let mut gif = Gif::new(1, 2, None);
+        gif.add_image().set_pixels([&red]);
+        assert!(matches!(
+            gif.as_bytes(),
+            Err(GifError::PixelCountMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn encoder_transparency_test() {
+        let (black, red) = (Pixel::new(0, 0, 0), Pixel::new(255, 0, 0));
+        let pixels = [None, Some(red.clone())];
+        let (green, blue) = (Pixel::new(0, 255, 0), Pixel::new(0, 0, 255));
+        let color_table = vec![black.clone(), red.clone(), green, blue];
+        let mut encoder = GifEncoder::new(Vec::new(), 1, 2, color_table, None).unwrap();
+
+        // Without a transparent index, the frame gets its own table holding one
+        let image = encoder.image_with_transparency(&pixels).unwrap();
+        assert!(image.has_local_color_table());
+        assert_eq!(image.transparent_index(), Some(0));
+
+        // The placeholder of the transparent index is not a usable color
+        encoder.set_transparent_index(Some(0));
+        let image = encoder.image_with_transparency(&pixels).unwrap();
+        assert!(!image.has_local_color_table());
+        assert_eq!(image.color_indexes().collect::<Vec<_>>(), vec![0, 1]);
+        let image = encoder.image_with_pixels(&[black, red]).unwrap();
+        assert!(image.has_local_color_table());
+        encoder.write_image(&image).unwrap();
+        assert_valid(&encoder.finish().unwrap());
+    }
+
+    #[test]
+    fn encoder_disposal_test() {
+        let (red, blue) = (Pixel::new(255, 0, 0), Pixel::new(0, 0, 255));
+        let mut encoder = GifEncoder::new(Vec::new(), 1, 1, vec![red.clone()], None).unwrap();
+        assert_eq!(encoder.new_image().disposal(), Disposal::Unspecified);
+
+        encoder.set_disposal(Disposal::DoNotDispose);
+        assert_eq!(encoder.new_image().disposal(), Disposal::DoNotDispose);
+        for pixels in [[red], [blue]] {
+            let image = encoder.image_with_pixels(&pixels).unwrap();
+            assert_eq!(image.disposal(), Disposal::DoNotDispose);
+            encoder.write_image(&image).unwrap();
+        }
+        assert_valid(&encoder.finish().unwrap());
+    }
+
+    #[test]
+    fn encoder_delta_test() {
+        let (red, blue) = (Pixel::new(255, 0, 0), Pixel::new(0, 0, 255));
+        let mut encoder = GifEncoder::new(Vec::new(), 3, 3, vec![red.clone()], None).unwrap();
+        encoder.set_delta(Delta::Changes {
+            transparent_unchanged: false,
+        });
+        // Left, top, width and height of the image descriptor
+        let area = |image: &GifImage| {
+            let descriptor = image.get_image_descriptor();
+            [1, 3, 5, 7].map(|at| u16::from_le_bytes([descriptor[at], descriptor[at + 1]]))
+        };
+
+        let mut pixels = vec![red.clone(); 9];
+        let image = encoder.image_with_pixels(&pixels).unwrap();
+        assert_eq!(area(&image), [0, 0, 3, 3]);
+        encoder.write_image(&image).unwrap();
+
+        pixels[5] = blue.clone();
+        pixels[7] = blue.clone();
+        let changed = encoder.image_with_pixels(&pixels).unwrap();
+        assert_eq!(area(&changed), [1, 1, 2, 2]);
+        assert!(changed.has_local_color_table());
+
+        // Without changes, the last image is drawn again
+        let image = encoder.image_with_pixels(&pixels).unwrap();
+        assert!(image.same_frame(&changed));
+        encoder.write_image(&changed).unwrap();
+        encoder.write_image(&image).unwrap();
+        assert_valid(&encoder.finish().unwrap());
+
+        // An image restoring the previous frame leaves the next one to cover it again
+        let (black, green) = (Pixel::new(0, 0, 0), Pixel::new(0, 255, 0));
+        let color_table = vec![black, red.clone(), green.clone(), blue];
+        let mut encoder = GifEncoder::new(Vec::new(), 3, 3, color_table, None).unwrap();
+        encoder
+            .set_transparent_index(Some(0))
+            .set_delta(Delta::Changes {
+                transparent_unchanged: true,
+            })
+            .set_disposal(Disposal::RestoreToPrevious);
+        let pixels = vec![red; 9];
+        encoder.image_with_pixels(&pixels).unwrap();
+        let image = encoder.image_with_pixels(&pixels).unwrap();
+        assert_eq!(area(&image), [0, 0, 3, 3]);
+
+        // Only the changed pixels of the rectangle are opaque
+        encoder.set_disposal(Disposal::DoNotDispose);
+        encoder.image_with_pixels(&pixels).unwrap();
+        let mut changed = pixels.clone();
+        changed[4] = green.clone();
+        changed[8] = green;
+        let image = encoder.image_with_pixels(&changed).unwrap();
+        assert_eq!(area(&image), [1, 1, 2, 2]);
+        assert!(!image.has_local_color_table());
+        assert_eq!(image.color_indexes().filter(|index| *index == 0).count(), 2);
+        encoder.write_image(&image).unwrap();
+        assert_valid(&encoder.finish().unwrap());
+    }
+
+    #[test]
+    fn encoder_merge_test() {
+        let (red, blue) = (Pixel::new(255, 0, 0), Pixel::new(0, 0, 255));
+        let mut encoder =
+            GifEncoder::new(Vec::new(), 1, 1, vec![red.clone(), blue.clone()], None).unwrap();
+
+        let mut sizes = Vec::new();
+        for (color, delay) in [
+            (&red, 0),
+            (&red, 4),
+            (&red, u16::MAX),
+            (&blue, 4),
+            (&blue, 4),
+        ] {
+            let mut image = encoder.new_image();
+            image.add_delay(delay).fill(color);
+            sizes.push(encoder.write_image(&image).unwrap());
+        }
+        let bytes = encoder.finish().unwrap();
+        assert_valid(&bytes);
+
+        // The first merge adds a Graphic Control Extension for the delay, while a delay
+        // past the longest one keeps the image apart
+        assert_eq!(sizes[1], 8);
+        assert_eq!(sizes[4], 0);
+        assert_eq!(bytes.len(), 6 + 7 + 4 * 3 + sizes.iter().sum::<usize>() + 1);
+        let delays: Vec<u16> = bytes
+            .windows(6)
+            .filter(|window| window[..3] == [0x21, 0xF9, 0x04])
+            .map(|window| u16::from_le_bytes([window[4], window[5]]))
+            .collect();
+        assert_eq!(delays, vec![4, u16::MAX, 8]);
+    }
+}
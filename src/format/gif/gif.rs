@@ -1,7 +1,15 @@
-use crate::common::types::{Color, Pixel};
-use crate::format::gif::gif_image::GifImage;
-use std::cmp::max;
-use std::{cell::RefCell, cmp::min, fs::File, io::Write, rc::Rc};
+use crate::common::types::Color;
+use crate::format::{
+    dither::{Dither, Ditherer},
+    gif::{
+        decoder::{Block, DecodeError, blocks},
+        encoder::GifEncoder,
+        gif_image::GifImage,
+        metadata::{ApplicationData, Metadata, PlainText},
+    },
+    quantize::{ColorMap, Quantizer, distance},
+};
+use std::{cell::RefCell, fmt::Display, fs::File, io, io::Write, rc::Rc};
 
 pub const SIGNATURE: [u8; 3] = *b"GIF";
 pub const VERSION: [u8; 3] = *b"89a";
@@ -19,6 +27,133 @@ pub const APPLICATION_EXTENSION_BLOCK: [u8; 19] = [
 ];
 pub const LOOP_COUNT_STARTING_INDEX: usize = 16;
 
+/// Number of colors a color table can hold
+pub const MAX_COLORS: usize = 256;
+
+/// Logical Screen Descriptor, written after the header and before the global color table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenDescriptor {
+    pub height: u16,
+    pub width: u16,
+    /// Index of the global color table shown where no image is drawn
+    pub background_index: u8,
+    /// Width of the pixels over their height as written by `pixel_aspect_ratio`, 0 when
+    /// they are square
+    pub pixel_aspect_ratio: u8,
+}
+
+impl ScreenDescriptor {
+    /// Screen with square pixels and the first color of the global color table as
+    /// background
+    pub fn new(height: u16, width: u16) -> Self {
+        ScreenDescriptor {
+            height,
+            width,
+            background_index: 0,
+            pixel_aspect_ratio: 0,
+        }
+    }
+
+    /// Bytes of the descriptor, announcing a global color table of 2^(bits + 1) colors
+    pub(crate) fn as_bytes(&self, global_color_bits: u8) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(7);
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+
+        // Global color table, 8 bits per primary color, and its size
+        bytes.push(0x90 | global_color_bits);
+        bytes.push(self.background_index);
+        bytes.push(self.pixel_aspect_ratio);
+        bytes
+    }
+}
+
+/// Pixel aspect ratio of pixels `ratio` times as wide as they are tall, written as
+/// `ratio * 64 - 15`. `None` when the ratio is outside the 1/4 to 4 range GIF can hold.
+pub fn pixel_aspect_ratio(ratio: f64) -> Option<u8> {
+    let byte = (ratio * 64.0 - 15.0).round();
+    (1.0..=255.0).contains(&byte).then_some(byte as u8)
+}
+
+/// Index of the color of `color_table` closest to `background`, the transparent index
+/// being left out. Only the colors a color table holds are considered.
+pub fn background_index(
+    color_table: &[Color],
+    background: &Color,
+    transparent_index: Option<usize>,
+) -> u8 {
+    (0..color_table.len().min(MAX_COLORS))
+        .filter(|&index| Some(index) != transparent_index)
+        .min_by_key(|&index| distance(&color_table[index], background))
+        .unwrap_or(0) as u8
+}
+
+/// Reason a `Gif` cannot be encoded
+#[derive(Debug)]
+pub enum GifError {
+    /// The images use more colors than a color table can hold
+    TooManyColors(usize),
+    /// An image uses colors missing from the color table written before it
+    ColorTableMismatch,
+    /// An image has more or fewer pixels than its width times its height
+    PixelCountMismatch { expected: usize, found: usize },
+    /// Writing the encoded bytes failed
+    Io(io::Error),
+}
+
+impl Display for GifError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GifError::TooManyColors(count) => write!(
+                f,
+                "the images use {count} colors but a GIF can only hold {MAX_COLORS}"
+            ),
+            GifError::ColorTableMismatch => write!(
+                f,
+                "an image uses colors missing from the color table written before it"
+            ),
+            GifError::PixelCountMismatch { expected, found } => write!(
+                f,
+                "an image has {found} pixels where its size holds {expected}"
+            ),
+            GifError::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for GifError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GifError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GifError {
+    fn from(error: io::Error) -> Self {
+        GifError::Io(error)
+    }
+}
+
+/// What to do with colors that do not fit in a color table
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorPolicy {
+    /// Fail with `GifError::TooManyColors` or `GifError::ColorTableMismatch`
+    Error,
+    /// Give the images using colors missing from the global color table a local color
+    /// table, an image using more than 256 colors on its own being an error
+    #[default]
+    LocalTables,
+    /// Replace the colors by the closest ones of a palette reduced from the colors of all
+    /// the images
+    Quantize,
+    /// Same as `LocalTables`, the local color table of an image using more than 256 colors
+    /// being reduced from its own colors
+    QuantizeFrames,
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum Loop {
     Forever,
     Repeat(u16),
@@ -29,6 +164,23 @@ pub struct Gif {
     width: u16,
     global_color_table: Rc<RefCell<Vec<Color>>>,
     num_loop: Option<Loop>,
+    color_policy: ColorPolicy,
+    quantizer: Quantizer,
+    dither: Dither,
+    /// Index of the global color table standing for transparent pixels
+    transparent_index: Option<usize>,
+    /// Whether the images are interlaced
+    interlaced: bool,
+    /// Index of the global color table shown where no image is drawn, unless it is
+    /// derived from `background_color`
+    background_index: u8,
+    /// Color shown where no image is drawn, its index being the one of the closest color
+    /// of the global color table once the colors fit in it
+    background_color: Option<Color>,
+    pixel_aspect_ratio: u8,
+    /// Comments and application data written before the images, the NETSCAPE2.0 block
+    /// coming from `num_loop`
+    metadata: Metadata,
     images: Vec<GifImage>, // TODO add image type
 }
 
@@ -40,235 +192,499 @@ impl Gif {
             global_color_table: Rc::new(RefCell::new(Vec::new())),
             images: Vec::new(),
             num_loop,
+            color_policy: ColorPolicy::default(),
+            quantizer: Quantizer::default(),
+            dither: Dither::default(),
+            transparent_index: None,
+            interlaced: false,
+            background_index: 0,
+            background_color: None,
+            pixel_aspect_ratio: 0,
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Choose what happens when the images use more colors than a color table can hold
+    pub fn set_color_policy(&mut self, color_policy: ColorPolicy) -> &mut Self {
+        self.color_policy = color_policy;
+        self
+    }
+
+    /// Choose how palettes are reduced when the color policy quantizes them
+    pub fn set_quantizer(&mut self, quantizer: Quantizer) -> &mut Self {
+        self.quantizer = quantizer;
+        self
+    }
+
+    /// Choose how the pixels of quantized images are spread over the colors of the palette
+    pub fn set_dither(&mut self, dither: Dither) -> &mut Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Add a Comment Extension holding `text`
+    pub fn add_comment(&mut self, text: &str) -> &mut Self {
+        self.metadata.comments.push(text.to_string());
+        self
+    }
+
+    /// Add an Application Extension
+    pub fn add_application_data(&mut self, application: ApplicationData) -> &mut Self {
+        self.metadata.applications.push(application);
+        self
+    }
+
+    /// Add a Plain Text Extension, written before the images
+    pub fn add_plain_text(&mut self, plain_text: PlainText) -> &mut Self {
+        self.metadata.plain_texts.push(plain_text);
+        self
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Interlace the images, the ones already added as well as the next ones
+    pub fn set_interlaced(&mut self, interlaced: bool) -> &mut Self {
+        self.interlaced = interlaced;
+        for image in &mut self.images {
+            image.set_interlaced(interlaced);
+        }
+        self
+    }
+
+    /// Show the color of the global color table at `index` where no image is drawn
+    pub fn set_background_index(&mut self, index: u8) -> &mut Self {
+        self.background_index = index;
+        self.background_color = None;
+        self
+    }
+
+    /// Show the color of the global color table closest to `color` where no image is
+    /// drawn, as found when the GIF is encoded
+    pub fn set_background_color(&mut self, color: Color) -> &mut Self {
+        self.background_color = Some(color);
+        self
+    }
+
+    /// Index of the global color table shown where no image is drawn
+    pub fn background_index(&self) -> u8 {
+        match &self.background_color {
+            Some(color) => background_index(
+                &self.global_color_table.borrow(),
+                color,
+                self.transparent_index,
+            ),
+            None => self.background_index,
+        }
+    }
+
+    /// Declare pixels that are not square, `ratio` being written by `pixel_aspect_ratio`,
+    /// or 0 for square pixels
+    pub fn set_pixel_aspect_ratio(&mut self, ratio: u8) -> &mut Self {
+        self.pixel_aspect_ratio = ratio;
+        self
+    }
+
+    pub fn pixel_aspect_ratio(&self) -> u8 {
+        self.pixel_aspect_ratio
+    }
+
+    /// Logical screen descriptor of the GIF
+    pub fn screen_descriptor(&self) -> ScreenDescriptor {
+        ScreenDescriptor {
+            height: self.height,
+            width: self.width,
+            background_index: self.background_index(),
+            pixel_aspect_ratio: self.pixel_aspect_ratio,
         }
     }
 
-    fn get_global_color_table_size_bits(&self) -> u8 {
-        let color_count = self.global_color_table.borrow().len();
-        let bit_size = ((color_count as f64).log2().ceil() - 1.0) as u8;
+    /// Reserve the first color of the global color table for transparent pixels, which the
+    /// images then hold with `set_pixels_with_transparency`. It becomes the background,
+    /// unless a background color is set.
+    pub fn set_transparency(&mut self) -> &mut Self {
+        if self.transparent_index.is_none() {
+            self.global_color_table
+                .borrow_mut()
+                .insert(0, Color::new(0, 0, 0));
+            for image in &mut self.images {
+                if !image.has_local_color_table() {
+                    image.map_color_indexes(|index| index + 1);
+                    image.set_transparent_index(Some(0));
+                }
+            }
+            self.transparent_index = Some(0);
+            self.background_index = 0;
+        }
+        self
+    }
 
-        // Minimum of one because some modern viewer does't support global color table of length 2
-        max(1, min(bit_size, 0x07))
+    /// Size of the global color table as written in the logical screen descriptor, the
+    /// table being padded to 2^(bits + 1) colors
+    pub fn get_global_color_table_size_bits(&self) -> u8 {
+        color_table_size_bits(self.global_color_table.borrow().len())
     }
 
-    // NOTE for now only global table
+    /// Add an image using the global color table
     pub fn add_image(&mut self) -> &mut GifImage {
-        let new_image = GifImage::new(
+        let mut new_image = GifImage::new(
             self.height,
             self.width,
             Some(Rc::clone(&self.global_color_table)),
         );
+        new_image
+            .set_transparent_index(self.transparent_index)
+            .set_interlaced(self.interlaced);
 
         self.images.push(new_image);
 
         self.images.last_mut().unwrap()
     }
 
+    /// Add an image with its own color table, which does not take from the colors of the
+    /// global one
+    pub fn add_local_image(&mut self) -> &mut GifImage {
+        let mut new_image = GifImage::new(self.height, self.width, None);
+        new_image.set_interlaced(self.interlaced);
+        self.images.push(new_image);
 
-    fn get_application_extension_block(&self) -> Vec<u8> {
-        if let Some(num_loop) = &self.num_loop {
-            let mut aeb: Vec<u8> = APPLICATION_EXTENSION_BLOCK.to_vec();
+        self.images.last_mut().unwrap()
+    }
 
-            // Adjust num of loop if specified
-            if let Loop::Repeat(n) = num_loop {
-                let num_loop = n.to_le_bytes();
+    /// Add an image built apart, such as a decoded one
+    pub(crate) fn push_image(&mut self, image: GifImage) -> &mut GifImage {
+        self.images.push(image);
+        self.images.last_mut().unwrap()
+    }
 
-                let mut i = LOOP_COUNT_STARTING_INDEX;
-                for byte in num_loop {
-                    aeb[i] = byte;
-                    i += 1;
-                }
-            }
+    pub fn images(&self) -> &[GifImage] {
+        &self.images
+    }
 
-            aeb
-        } else {
-            Vec::with_capacity(0)
-        }
+    pub fn height(&self) -> u16 {
+        self.height
     }
 
-    pub fn write_to_file(&mut self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let mut file_contents = Vec::new();
-        let global_color_bits = self.get_global_color_table_size_bits();
+    pub fn width(&self) -> u16 {
+        self.width
+    }
 
-        // add header block
-        file_contents.extend_from_slice(&SIGNATURE);
-        file_contents.extend_from_slice(&VERSION);
+    pub fn set_loop(&mut self, num_loop: Option<Loop>) -> &mut Self {
+        self.num_loop = num_loop;
+        self
+    }
 
-        // add logistical screen descriptor
-        file_contents.extend_from_slice(&self.width.to_le_bytes());
-        file_contents.extend_from_slice(&self.height.to_le_bytes());
+    pub fn num_loop(&self) -> Option<Loop> {
+        self.num_loop
+    }
+
+    /// Colors of the global color table
+    pub fn global_color_table(&self) -> Vec<Color> {
+        self.global_color_table.borrow().clone()
+    }
+
+    pub(crate) fn global_color_table_ref(&self) -> &Rc<RefCell<Vec<Color>>> {
+        &self.global_color_table
+    }
 
-        // add packed field
-        let packed_field = 0x90 | global_color_bits;
-        file_contents.push(packed_field);
+    pub fn color_count(&self) -> usize {
+        self.global_color_table.borrow().len()
+    }
 
-        // add background color index
-        file_contents.push(0);
+    /// Encode the GIF, returns its bytes along with the size in bytes of each image. Images
+    /// showing the same as the one before them are merged into its delay, as by
+    /// `GifEncoder::write_image`.
+    pub fn encode(&mut self) -> Result<(Vec<u8>, Vec<usize>), GifError> {
+        // Before their colors are fitted, which goes through their rows
+        for image in &self.images {
+            image.check_pixel_count()?;
+        }
+        self.fit_colors()?;
+
+        let mut encoder = GifEncoder::with_color_table(
+            Vec::new(),
+            &self.screen_descriptor(),
+            Rc::clone(&self.global_color_table),
+            self.num_loop.as_ref(),
+        )?;
+        for comment in &self.metadata.comments {
+            encoder.write_comment(comment)?;
+        }
+        for application in &self.metadata.applications {
+            encoder.write_application_data(application)?;
+        }
+        for plain_text in &self.metadata.plain_texts {
+            encoder.write_plain_text(plain_text)?;
+        }
 
-        // add pixel aspect ratio
-        file_contents.push(0);
+        let image_sizes = self
+            .images
+            .iter()
+            .map(|image| encoder.write_image(image))
+            .collect::<Result<_, _>>()?;
 
-        // add padding to the color table
-        {
-            let mut global_color_table = self.global_color_table.borrow_mut();
+        Ok((encoder.finish()?, image_sizes))
+    }
 
-            // Filling global color table with padding
-            let padding_size = (1 << (global_color_bits + 1)) - global_color_table.len();
-            for _ in 0..padding_size {
-                global_color_table.push(Color::new(0, 0, 0));
+    /// Bring the color tables down to 256 colors according to the color policy
+    fn fit_colors(&mut self) -> Result<(), GifError> {
+        let color_count = self.color_count();
+        if color_count > MAX_COLORS {
+            match self.color_policy {
+                ColorPolicy::Error => return Err(GifError::TooManyColors(color_count)),
+                ColorPolicy::LocalTables | ColorPolicy::QuantizeFrames => {
+                    for image in &mut self.images {
+                        if !image.has_local_color_table() && image.color_index_bound() > MAX_COLORS
+                        {
+                            *image = image.with_local_color_table();
+                        }
+                    }
+                    self.global_color_table.borrow_mut().truncate(MAX_COLORS);
+                }
+                ColorPolicy::Quantize => {
+                    let mut images: Vec<&mut GifImage> = self
+                        .images
+                        .iter_mut()
+                        .filter(|image| !image.has_local_color_table())
+                        .collect();
+                    quantize_color_table(
+                        &self.global_color_table,
+                        self.transparent_index,
+                        &mut images,
+                        usize::from(self.width),
+                        &self.quantizer,
+                        self.dither,
+                    );
+                }
             }
+        }
 
-            // add colors
-            for color in global_color_table.iter() {
-                file_contents.extend(color.as_bytes());
+        if matches!(
+            self.color_policy,
+            ColorPolicy::Quantize | ColorPolicy::QuantizeFrames
+        ) {
+            for image in &mut self.images {
+                let color_table = Rc::clone(image.color_table());
+                if image.has_local_color_table() && color_table.borrow().len() > MAX_COLORS {
+                    let transparent_index = image.transparent_index();
+                    quantize_color_table(
+                        &color_table,
+                        transparent_index,
+                        &mut [image],
+                        usize::from(self.width),
+                        &self.quantizer,
+                        self.dither,
+                    );
+                }
             }
         }
 
-        // Adding application extension if present
-        file_contents.extend_from_slice(&self.get_application_extension_block());
+        Ok(())
+    }
 
-        // add images
-        for image in &self.images {
-            file_contents.extend_from_slice(&image.as_bytes());
-        }
+    pub fn as_bytes(&mut self) -> Result<Vec<u8>, GifError> {
+        self.encode().map(|(bytes, _)| bytes)
+    }
 
-        // add gif end
-        file_contents.push(TRAILER_MARKER);
+    /// Encode the GIF and write it to `writer`, nothing being written when encoding fails
+    pub fn write_to(&mut self, mut writer: impl Write) -> Result<(), Box<dyn std::error::Error>> {
+        let file_contents = self.as_bytes()?;
+        writer.write_all(&file_contents)?;
 
+        Ok(())
+    }
+
+    pub fn write_to_file(&mut self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // Encode before creating the file so that a broken GIF is never written
+        let file_contents = self.as_bytes()?;
 
-        // write to file
         let mut file = File::create(filename)?;
         file.write_all(&file_contents)?;
 
         Ok(())
     }
+}
 
-    
-    #[rustfmt::skip]
-    pub fn debug(&self, num_byte_per_row: Option<usize>) {
-        let mut to_print = Vec::new();
-        const RED: &'static str= "\x1B[31m";
-        const GREEN: &'static str= "\x1B[32m";
-        const YELLOW: &'static str= "\x1B[33m";
-        const BLUE: &'static str= "\x1B[34m";
-        const DARK_MAGENTA: &'static str= "\x1B[95m";
-        const DARK_CYAN: &'static str= "\x1B[96m";
-        const RESET: &'static str= "\x1B[0m";
-
-        // Printing legend
-        println!("Legend: ");
-        println!("  {YELLOW}Header Block{RESET}");
-        println!("  {BLUE}Logistical Screen Descriptor{RESET}");
-        println!("  Global Color Table: colors identified by their background colors");
-        println!("  {GREEN}Application Extension Block{RESET}");
-        println!(
-            "  Images:\
-            \n    - {RED}Graphical Control extension{RESET}\
-            \n    - {BLUE}Image Descriptor{RESET}\
-            \n    - {DARK_MAGENTA}Image Data{RESET}"
-        );
-        println!("  {DARK_CYAN}Trailer Marker{RESET}");
-        println!();
-
-        // add header block
-        let header_block = vec![SIGNATURE, VERSION].concat();
-        for byte in header_block {
-            to_print.push(format!("{YELLOW}{:02X?}{RESET}", byte));
+/// Print the bytes of an encoded GIF in hexadecimal, colored by the block they belong to
+#[rustfmt::skip]
+pub fn debug(bytes: &[u8], num_byte_per_row: Option<usize>) -> Result<(), DecodeError> {
+    const RED: &str = "\x1B[31m";
+    const GREEN: &str = "\x1B[32m";
+    const YELLOW: &str = "\x1B[33m";
+    const BLUE: &str = "\x1B[34m";
+    const DARK_MAGENTA: &str = "\x1B[95m";
+    const DARK_CYAN: &str = "\x1B[96m";
+    const RESET: &str = "\x1B[0m";
+
+    let blocks = blocks(bytes)?;
+
+    // Printing legend
+    println!("Legend: ");
+    println!("  {YELLOW}Header Block{RESET}");
+    println!("  {BLUE}Logistical Screen Descriptor{RESET}");
+    println!("  Color Tables: colors identified by their background colors");
+    println!("  {GREEN}Application, Comment and Plain Text Extensions{RESET}");
+    println!(
+        "  Images:\
+        \n    - {RED}Graphical Control extension{RESET}\
+        \n    - {BLUE}Image Descriptor{RESET}\
+        \n    - {DARK_MAGENTA}Image Data{RESET}"
+    );
+    println!("  {DARK_CYAN}Trailer Marker{RESET}");
+    println!();
+
+    let mut to_print = Vec::with_capacity(bytes.len());
+    for (block, range) in blocks {
+        let bytes = &bytes[range];
+        let style = match block {
+            Block::Header => YELLOW,
+            Block::ScreenDescriptor | Block::ImageDescriptor => BLUE,
+            Block::Application | Block::Comment | Block::PlainText | Block::Extension => GREEN,
+            Block::GraphicControl => RED,
+            Block::ImageData => DARK_MAGENTA,
+            Block::Trailer => DARK_CYAN,
+            Block::GlobalColorTable | Block::LocalColorTable => {
+                for color in bytes.chunks(3) {
+                    let custom_bg = format!("\x1B[48;2;{};{};{}m", color[0], color[1], color[2]);
+                    for byte in color {
+                        to_print.push(format!("{custom_bg}{byte:02X}{RESET}"));
+                    }
+                }
+                continue;
+            }
+        };
+        for byte in bytes {
+            to_print.push(format!("{style}{byte:02X}{RESET}"));
         }
+    }
 
-        // add logistical screen descriptor
-        let mut logistical_screen_descriptor =
-            vec![self.width.to_le_bytes(), self.height.to_le_bytes()].concat();
-
-        // add packed field
-        let global_color_bits = self.get_global_color_table_size_bits();
-        let packed_field = 0x90 | global_color_bits;
-        logistical_screen_descriptor.push(packed_field);
-
-        // add background color index
-        logistical_screen_descriptor.push(0);
+    let num_byte_per_row = num_byte_per_row.unwrap_or(16);
+    for (i, byte) in to_print.iter().enumerate() {
+        print!("{}", byte);
+        if (i + 1) % 2  == 0 { print!(" ") }
+        if (i + 1) % num_byte_per_row == 0 { println!() }
+    }
+    println!();
 
-        // add pixel aspect ratio
-        logistical_screen_descriptor.push(0);
+    Ok(())
+}
 
-        for byte in logistical_screen_descriptor {
-            to_print.push(format!("{BLUE}{:02X}{RESET}", byte));
+/// Replace a color table by a palette of 256 colors reduced from the colors the images
+/// using it show, the images being mapped to the colors of the palette. The transparent
+/// index of the table becomes the first one of the palette. Images are dithered by their
+/// position in the screen, `canvas_width` pixels wide.
+fn quantize_color_table(
+    color_table: &Rc<RefCell<Vec<Color>>>,
+    transparent_index: Option<usize>,
+    images: &mut [&mut GifImage],
+    canvas_width: usize,
+    quantizer: &Quantizer,
+    dither: Dither,
+) {
+    let colors = color_table.replace(Vec::new());
+
+    let mut counts = vec![0; colors.len()];
+    for image in images.iter() {
+        for (index, count) in image.color_index_counts() {
+            counts[index] += count;
         }
-
-        // add padding to the color table
-        {
-            let mut global_color_table = self.global_color_table.borrow_mut();
-
-            // Filling global color table with padding
-            let padding_size = (1 << (global_color_bits + 1)) - global_color_table.len();
-            for _ in 0..padding_size {
-                global_color_table.push(Color::new(0, 0, 0));
-            }
-
-            // add colors
-            for color in global_color_table.iter() {
-                let color_bytes = color.as_bytes();
-                let Pixel { r, g, b } = *color;
-
-                let custom_bg = format!("\x1B[48;2;{r};{g};{b}m");
-                for byte in color_bytes {
-                    to_print.push(format!("{custom_bg}{:02X}{RESET}", byte));
-                }
-            }
+    }
+    let histogram: Vec<(Color, usize)> = colors
+        .iter()
+        .cloned()
+        .zip(counts)
+        .enumerate()
+        .filter(|(index, (_, count))| *count > 0 && Some(*index) != transparent_index)
+        .map(|(_, entry)| entry)
+        .collect();
+
+    // Colors of the palette come after the transparent one
+    let reserved = usize::from(transparent_index.is_some());
+    let palette = quantizer.palette(&histogram, MAX_COLORS - reserved);
+    let palette_index = |index: Option<usize>| index.map_or(0, |index| index + reserved);
+
+    if dither == Dither::None {
+        // Every pixel of a color takes the same color of the palette
+        let mut color_map = ColorMap::new(&palette);
+        let mapping: Vec<usize> = colors
+            .iter()
+            .enumerate()
+            .map(|(index, color)| {
+                palette_index((Some(index) != transparent_index).then(|| color_map.index(color)))
+            })
+            .collect();
+        for image in images.iter_mut() {
+            image.map_color_indexes(|index| mapping[index]);
         }
-
-        // Adding application extension if present
-        let application_extension_block = self.get_application_extension_block();
-        for byte in application_extension_block {
-            to_print.push(format!("{GREEN}{:02X?}{RESET}", byte));
+    } else {
+        // Images are dithered in order, so that they can keep the colors of the previous one
+        let mut ditherer = Ditherer::new(dither);
+        for image in images.iter_mut() {
+            let indexes: Vec<usize> = image.color_indexes().collect();
+            let pixels: Vec<Color> = indexes.iter().map(|&index| colors[index].clone()).collect();
+            let dithered = ditherer.dither(
+                &pixels,
+                usize::from(image.width()),
+                (usize::from(image.left()), usize::from(image.top())),
+                canvas_width,
+                &palette,
+            );
+
+            let indexes: Vec<usize> = indexes
+                .into_iter()
+                .zip(dithered)
+                .map(|(index, color)| {
+                    palette_index((Some(index) != transparent_index).then_some(color))
+                })
+                .collect();
+            image.set_color_indexes(indexes);
         }
+    }
 
-        // // add images
-        for image in &self.images {
-            // Printing Graphical Control Extension
-            let gce_bytes = image.get_gce();
-            if gce_bytes.len() > 0 {
-                for byte in gce_bytes {
-                    to_print.push(format!("{RED}{:02X?}{RESET}", byte));
-                }
-            }
-
-            // Printing Image Descriptor
-            let img_descriptor_bytes = image.get_image_descriptor();
-            for byte in img_descriptor_bytes {
-                to_print.push(format!("{BLUE}{:02X?}{RESET}", byte));
-            }
+    for image in images.iter_mut() {
+        image.set_transparent_index(transparent_index.map(|_| 0));
+    }
+    *color_table.borrow_mut() = transparent_index
+        .map(|_| Color::new(0, 0, 0))
+        .into_iter()
+        .chain(palette)
+        .collect();
+}
 
-            // Printing Image
-            let img_data = image.get_image_data();
-            for byte in img_data {
-                to_print.push(format!("{DARK_MAGENTA}{:02X?}{RESET}", byte));
-            }
+/// Size of a color table as written in packed fields: the table holds 2^(bits + 1) colors
+pub(crate) fn color_table_size_bits(color_count: usize) -> u8 {
+    let bit_size = ((color_count as f64).log2().ceil() - 1.0) as u8;
 
-            print!("{RESET}");
-        }
+    // Minimum of one because some modern viewer does't support global color table of length 2
+    bit_size.clamp(1, 0x07)
+}
 
-        // Printing Trailer Mark
-        to_print.push(format!("{DARK_CYAN}{TRAILER_MARKER}{RESET}"));
+/// NETSCAPE2.0 block making the animation loop, empty when it plays once
+pub(crate) fn application_extension_block(num_loop: Option<&Loop>) -> Vec<u8> {
+    if let Some(num_loop) = num_loop {
+        let mut aeb: Vec<u8> = APPLICATION_EXTENSION_BLOCK.to_vec();
 
-        print!("{}", to_print[0]);
+        // Adjust num of loop if specified
+        if let Loop::Repeat(n) = num_loop {
+            let num_loop = n.to_le_bytes();
 
-        let num_byte_per_row= match num_byte_per_row {
-            Some(num) => num,
-            None => 16,
-        };
-        for i in 1..to_print.len() {
-            print!("{}", to_print[i]);
-            if (i + 1) % 2  == 0 { print!(" ") }
-            if (i + 1) % num_byte_per_row == 0 { println!() }
+            aeb[LOOP_COUNT_STARTING_INDEX..LOOP_COUNT_STARTING_INDEX + num_loop.len()]
+                .copy_from_slice(&num_loop);
         }
-        println!();
+
+        aeb
+    } else {
+        Vec::with_capacity(0)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{common::types::Pixel, format::image::ImageFormat};
+    use crate::{
+        common::types::Pixel,
+        format::{gif::validator::assert_valid, image::ImageFormat},
+    };
 
     use super::*;
     #[test]
@@ -292,4 +708,192 @@ mod tests {
         gif.add_image().fill(&black);
         assert_eq!(gif.get_global_color_table_size_bits(), 1);
     }
+
+    #[test]
+    fn gif_encode_test() {
+        let mut gif = Gif::new(2, 2, None);
+        gif.add_image().fill(&Pixel::new(255, 0, 0));
+        gif.add_image().fill(&Pixel::new(0, 0, 255));
+
+        let (bytes, image_sizes) = gif.encode().unwrap();
+
+        assert_eq!(image_sizes.len(), 2);
+        // Header, screen descriptor, 4 colors, images and trailer
+        assert_eq!(
+            bytes.len(),
+            6 + 7 + 4 * 3 + image_sizes.iter().sum::<usize>() + 1
+        );
+        assert_eq!(bytes.last(), Some(&TRAILER_MARKER));
+        assert_valid(&bytes);
+    }
+
+    #[test]
+    fn gif_local_color_table_test() {
+        let mut gif = Gif::new(1, 1, None);
+        gif.add_image().fill(&Pixel::new(255, 0, 0));
+        gif.add_local_image().fill(&Pixel::new(0, 0, 255));
+
+        let (bytes, image_sizes) = gif.encode().unwrap();
+
+        // The local image only brings its table: 4 padded colors after its descriptor
+        assert_eq!(gif.color_count(), 4);
+        assert_eq!(image_sizes[1], image_sizes[0] + 4 * 3);
+        let descriptor = bytes.len() - 1 - image_sizes[1];
+        assert_eq!(bytes[descriptor + 9], 0x81);
+        assert_eq!(&bytes[descriptor + 10..descriptor + 13], &[0, 0, 255]);
+        assert_valid(&bytes);
+    }
+
+    #[test]
+    fn gif_write_to_test() {
+        let mut gif = Gif::new(2, 2, None);
+        gif.add_image().fill(&Pixel::new(255, 0, 0));
+
+        let mut written = Vec::new();
+        gif.write_to(&mut written).unwrap();
+
+        assert_eq!(written, gif.as_bytes().unwrap());
+        assert_valid(&written);
+    }
+
+    #[test]
+    fn gif_too_many_colors_test() {
+        let mut gif = Gif::new(1, 257, None);
+        let pixels: Vec<Pixel> = (0..257)
+            .map(|i| Pixel::new((i / 2) as u8, (i % 2) as u8, 0))
+            .collect();
+        gif.add_image().set_pixels(&pixels);
+
+        let filename = std::env::temp_dir().join("gas_too_many_colors.gif");
+        let error = gif.write_to_file(&filename.to_string_lossy()).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<GifError>(),
+            Some(GifError::TooManyColors(257))
+        ));
+        assert!(!filename.exists());
+    }
+
+    #[test]
+    fn gif_color_policy_test() {
+        let colors = |offset: usize| -> Vec<Pixel> {
+            (offset..offset + 200)
+                .map(|i| Pixel::new((i / 256) as u8, (i % 256) as u8, 0))
+                .collect()
+        };
+        let gif = |policy| {
+            let mut gif = Gif::new(1, 200, None);
+            gif.set_color_policy(policy);
+            gif.add_image().set_pixels(&colors(0));
+            gif.add_image().set_pixels(&colors(200));
+            gif
+        };
+
+        assert!(matches!(
+            gif(ColorPolicy::Error).encode(),
+            Err(GifError::TooManyColors(400))
+        ));
+
+        let mut local = gif(ColorPolicy::LocalTables);
+        assert_valid(&local.as_bytes().unwrap());
+        assert!(!local.images[0].has_local_color_table());
+        assert!(local.images[1].has_local_color_table());
+        assert_eq!(local.color_count(), MAX_COLORS);
+
+        let mut quantized = gif(ColorPolicy::Quantize);
+        assert_valid(&quantized.as_bytes().unwrap());
+        assert_eq!(quantized.color_count(), MAX_COLORS);
+        assert!(
+            quantized
+                .images
+                .iter()
+                .all(|image| image.color_index_bound() <= MAX_COLORS)
+        );
+
+        let mut dithered = gif(ColorPolicy::Quantize);
+        dithered.set_dither(Dither::FloydSteinberg {
+            lock_unchanged: true,
+        });
+        assert_valid(&dithered.as_bytes().unwrap());
+        assert!(
+            dithered
+                .images
+                .iter()
+                .all(|image| image.color_index_bound() <= MAX_COLORS)
+        );
+    }
+
+    #[test]
+    fn gif_transparency_test() {
+        let red = Pixel::new(255, 0, 0);
+        let mut gif = Gif::new(1, 2, None);
+        gif.add_image().set_pixels(&[red.clone(), red.clone()]);
+        gif.set_transparency();
+        gif.add_image()
+            .set_pixels_with_transparency([None, Some(&red)]);
+        assert_valid(&gif.as_bytes().unwrap());
+
+        // Images added before share the shifted table
+        assert_eq!(
+            gif.images[0].color_indexes().collect::<Vec<_>>(),
+            vec![1, 1]
+        );
+        assert_eq!(
+            gif.images[1].color_indexes().collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert!(
+            gif.images
+                .iter()
+                .all(|image| image.transparent_index() == Some(0))
+        );
+
+        // Quantizing keeps the transparent index out of the palette
+        let mut quantized = Gif::new(1, 300, None);
+        quantized
+            .set_color_policy(ColorPolicy::Quantize)
+            .set_transparency();
+        let colors: Vec<Pixel> = (0..299)
+            .map(|i| Pixel::new((i / 256) as u8, (i % 256) as u8, 0))
+            .collect();
+        quantized
+            .add_image()
+            .set_pixels_with_transparency(colors.iter().map(Some).chain([None]));
+        assert_valid(&quantized.as_bytes().unwrap());
+        let indexes: Vec<usize> = quantized.images[0].color_indexes().collect();
+        assert_eq!(indexes[299], 0);
+        assert!(
+            indexes[..299]
+                .iter()
+                .all(|index| (1..MAX_COLORS).contains(index))
+        );
+    }
+
+    #[test]
+    fn gif_screen_descriptor_test() {
+        let red = Pixel::new(255, 0, 0);
+        let blue = Pixel::new(0, 0, 255);
+        let mut gif = Gif::new(1, 2, None);
+        gif.add_image().set_pixels(&[red.clone(), blue.clone()]);
+
+        let bytes = gif.as_bytes().unwrap();
+        assert_eq!(bytes[10..13], [0x91, 0, 0]);
+
+        gif.set_background_color(Pixel::new(10, 0, 200))
+            .set_pixel_aspect_ratio(pixel_aspect_ratio(8.0 / 7.0).unwrap());
+        assert_eq!(gif.background_index(), 1);
+        let bytes = gif.as_bytes().unwrap();
+        assert_eq!(bytes[10..13], [0x91, 1, 58]);
+        assert_valid(&bytes);
+
+        // The transparent index is never the closest color
+        gif.set_transparency();
+        assert_eq!(gif.background_index(), 2);
+        gif.set_background_index(0);
+        assert_eq!(gif.screen_descriptor().background_index, 0);
+
+        assert_eq!(pixel_aspect_ratio(1.0), Some(49));
+        assert_eq!(pixel_aspect_ratio(0.2), None);
+        assert_eq!(pixel_aspect_ratio(5.0), None);
+    }
 }
@@ -1,38 +1,91 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, iter, rc::Rc};
 
 use crate::{
     common::types::{Color, Pixel, Position},
-    format::image::{Image, ImageFormat},
+    format::{
+        gif::{
+            gif::{GifError, color_table_size_bits},
+            metadata::sub_blocks,
+        },
+        image::{Image, ImageFormat, RLE},
+    },
 };
 
 /** Graphical Control Extension **/
 const GRAPHICAL_CONTROL_EXTENSION_SIZE: usize = 8;
 const EXTENSIONS_INTRODUCER: u8 = 0x21;
-const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
-const GCE_BYTE_SIZE: u8 = 0x04;
+pub(crate) const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+pub(crate) const GCE_BYTE_SIZE: u8 = 0x04;
 
 /** Image Descriptor **/
 const IMAGE_DESCRIPTOR_SIZE: usize = 10;
-const IMAGE_SEPARATOR: u8 = b'\x2C';
+pub(crate) const IMAGE_SEPARATOR: u8 = b'\x2C';
 
 /** Image Data **/
-const SUB_BLOCK_MAX_SIZE: usize = 255;
 const CODE_TABLE_MAX_SIZE: usize = 4096;
 
+/// What a decoder does with the area of an image before drawing the next one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Disposal {
+    /// Left to the decoder, which usually keeps the image
+    #[default]
+    Unspecified,
+    /// The image stays, the next one being drawn over it
+    DoNotDispose,
+    /// The area of the image is cleared to the background, transparent for most decoders
+    RestoreToBackground,
+    /// The area of the image goes back to what it showed before the image was drawn
+    RestoreToPrevious,
+}
+
+impl Disposal {
+    /// Disposal method as written in the packed field of the Graphic Control Extension
+    pub fn method(self) -> u8 {
+        match self {
+            Disposal::Unspecified => 0,
+            Disposal::DoNotDispose => 1,
+            Disposal::RestoreToBackground => 2,
+            Disposal::RestoreToPrevious => 3,
+        }
+    }
+
+    /// Disposal of a method read from a Graphic Control Extension, the methods the spec
+    /// reserves being left to the decoder
+    pub fn from_method(method: u8) -> Self {
+        match method {
+            1 => Disposal::DoNotDispose,
+            2 => Disposal::RestoreToBackground,
+            3 => Disposal::RestoreToPrevious,
+            _ => Disposal::Unspecified,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct GifImage {
     color_table: Rc<RefCell<Vec<Pixel>>>,
-    /// Image of u8 which represents the indexes of the colors
-    image: Image<u8>,
+    /// Whether `color_table` belongs to this image and is written along with it, instead
+    /// of being the global color table of the GIF
+    local_color_table: bool,
+    /// Indexes of the colors in the color table, which may hold more colors than can be
+    /// encoded until the encoder fits them
+    image: Image<usize>,
     left: u16,
     top: u16,
     height: u16,
     width: u16,
     delay: u16,
+    /// Index of the color table standing for transparent pixels, its color being never
+    /// used by other pixels
+    transparent_index: Option<usize>,
+    disposal: Disposal,
+    /// Whether the rows are written in the 4 passes of interlacing
+    interlaced: bool,
 }
 
 impl ImageFormat<Color> for GifImage {
     fn fill(&mut self, color: &Color) -> &mut Self {
-        let color_index = self.get_color_index(color) as u8;
+        let color_index = self.get_color_index(color);
 
         self.image.fill(color_index);
 
@@ -45,7 +98,7 @@ impl ImageFormat<Color> for GifImage {
         bottom_right: Position,
         color: &Color,
     ) -> &mut Self {
-        let color_index = self.get_color_index(color) as u8;
+        let color_index = self.get_color_index(color);
 
         self.image
             .draw_rectangle(top_left, bottom_right, color_index);
@@ -55,15 +108,17 @@ impl ImageFormat<Color> for GifImage {
 }
 
 impl GifImage {
+    /// Image using the global `color_table`, or its own local color table when `None`
     pub fn new(height: u16, width: u16, color_table: Option<Rc<RefCell<Vec<Pixel>>>>) -> Self {
-        // TODO if no colorTable create local one
+        let local_color_table = color_table.is_none();
         let color_table = match color_table {
             Some(ct) => ct,
             None => Rc::new(RefCell::new(Vec::new())),
         };
 
         GifImage {
-            color_table: color_table,
+            color_table,
+            local_color_table,
             // pixel_indexes: vec![0u8; height as usize * width as usize],
             image: Image::new(height as usize, width as usize, 0),
             left: 0,
@@ -71,6 +126,9 @@ impl GifImage {
             height,
             width,
             delay: 0,
+            transparent_index: None,
+            disposal: Disposal::default(),
+            interlaced: false,
         }
     }
 
@@ -92,34 +150,264 @@ impl GifImage {
         self
     }
 
-    fn get_color_index(&mut self, color: &Color) -> usize {
-        let mut color_table = self.color_table.borrow_mut();
+    pub fn delay(&self) -> u16 {
+        self.delay
+    }
+
+    pub fn left(&self) -> u16 {
+        self.left
+    }
+
+    pub fn top(&self) -> u16 {
+        self.top
+    }
+
+    /// Make a color of the table stand for transparent pixels, the GIF or the encoder
+    /// creating the image usually reserving it for all the images of the global color table
+    pub fn set_transparent_index(&mut self, index: Option<usize>) -> &mut GifImage {
+        self.transparent_index = index;
+        self
+    }
+
+    pub fn transparent_index(&self) -> Option<usize> {
+        self.transparent_index
+    }
+
+    /// Choose what happens to the area of the image once its delay is over
+    pub fn set_disposal(&mut self, disposal: Disposal) -> &mut GifImage {
+        self.disposal = disposal;
+        self
+    }
+
+    pub fn disposal(&self) -> Disposal {
+        self.disposal
+    }
+
+    /// Write the rows interlaced: every 8th row from the first, every 8th from the 5th,
+    /// every 4th from the 3rd and then the odd rows, so that decoders can show a coarse
+    /// image before all of it is received
+    pub fn set_interlaced(&mut self, interlaced: bool) -> &mut GifImage {
+        self.interlaced = interlaced;
+        self
+    }
+
+    pub fn interlaced(&self) -> bool {
+        self.interlaced
+    }
+
+    /// Set every pixel of the image from colors given row by row
+    pub fn set_pixels<'a>(&mut self, pixels: impl IntoIterator<Item = &'a Color>) -> &mut GifImage {
+        self.set_pixels_with_transparency(pixels.into_iter().map(Some))
+    }
+
+    /// Set every pixel of the image from colors given row by row, `None` being a transparent
+    /// pixel. A color of the table is reserved for them if none is yet.
+    pub fn set_pixels_with_transparency<'a>(
+        &mut self,
+        pixels: impl IntoIterator<Item = Option<&'a Color>>,
+    ) -> &mut GifImage {
+        // Consecutive pixels usually share the same color
+        let mut previous: Option<(Option<&Color>, usize)> = None;
+        let mut indexes = Vec::with_capacity(self.width as usize * self.height as usize);
+
+        for color in pixels {
+            let index = match (previous, color) {
+                (Some((previous_color, index)), _) if previous_color == color => index,
+                (_, Some(color)) => self.get_color_index(color),
+                (_, None) => self.reserve_transparent_index(),
+            };
+            previous = Some((color, index));
+            indexes.push(index);
+        }
+
+        self.image.set_pixels(indexes);
+        self
+    }
+
+    /// Index standing for transparent pixels, added to the color table when missing
+    fn reserve_transparent_index(&mut self) -> usize {
+        *self.transparent_index.get_or_insert_with(|| {
+            let mut color_table = self.color_table.borrow_mut();
+            color_table.push(Color::new(0, 0, 0));
+            color_table.len() - 1
+        })
+    }
+
+    /// Color of each pixel, row by row, `None` being a transparent pixel
+    pub fn pixels(&self) -> Vec<Option<Color>> {
+        let color_table = self.color_table.borrow();
+        self.color_indexes()
+            .map(|index| {
+                (Some(index) != self.transparent_index)
+                    .then(|| color_table.get(index).cloned())
+                    .flatten()
+            })
+            .collect()
+    }
 
-        let index = match &color_table.iter().position(|c| c == color) {
-            Some(index) => *index,
-            None => {
-                color_table.push(color.clone());
-                color_table.len() - 1
+    pub(crate) fn color_table(&self) -> &Rc<RefCell<Vec<Pixel>>> {
+        &self.color_table
+    }
+
+    pub fn has_local_color_table(&self) -> bool {
+        self.local_color_table
+    }
+
+    /// Number of colors of its table the image can use, ie. its highest color index plus one
+    pub fn color_index_bound(&self) -> usize {
+        self.image
+            .pixels_indexes
+            .iter()
+            .map(|rle| rle.value + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Number of pixels of each color index, an index appearing several times
+    pub(crate) fn color_index_counts(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.image
+            .pixels_indexes
+            .iter()
+            .map(|rle| (rle.value, rle.freq))
+    }
+
+    /// Index in the color table of each pixel, row by row
+    pub(crate) fn color_indexes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.image
+            .pixels_indexes
+            .iter()
+            .flat_map(|rle| iter::repeat_n(rle.value, rle.freq))
+    }
+
+    /// Fail unless the image has as many pixels as its size holds, which its LZW data has
+    /// to match
+    pub(crate) fn check_pixel_count(&self) -> Result<(), GifError> {
+        let expected = usize::from(self.width) * usize::from(self.height);
+        let found = self.image.pixels_indexes.iter().map(|rle| rle.freq).sum();
+        if found == expected {
+            Ok(())
+        } else {
+            Err(GifError::PixelCountMismatch { expected, found })
+        }
+    }
+
+    /// Set the index in the color table of each pixel, given row by row
+    pub(crate) fn set_color_indexes(&mut self, indexes: impl IntoIterator<Item = usize>) {
+        self.image.set_pixels(indexes);
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Replace each color index by `map(index)`
+    pub(crate) fn map_color_indexes(&mut self, mut map: impl FnMut(usize) -> usize) {
+        let mut pixels_indexes: Vec<RLE<usize>> = Vec::new();
+
+        for rle in &self.image.pixels_indexes {
+            let value = map(rle.value);
+            match pixels_indexes.last_mut() {
+                // Runs of colors mapped to the same index are merged
+                Some(last) if last.value == value => last.freq += rle.freq,
+                _ => pixels_indexes.push(RLE::new(rle.freq, value)),
             }
-        };
+        }
 
-        index
+        self.image.pixels_indexes = pixels_indexes;
     }
 
-    /** Converting the image to bytes **/
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let img_descriptor = self.get_image_descriptor();
-        let img_data = self.get_image_data();
+    /// Copy of the image with a local color table holding only the colors it uses
+    pub fn with_local_color_table(&self) -> GifImage {
+        let color_table = self.color_table.borrow();
+        let mut local = GifImage::new(self.height, self.width, None);
+        local.left = self.left;
+        local.top = self.top;
+        local.delay = self.delay;
+        local.disposal = self.disposal;
+        local.interlaced = self.interlaced;
+
+        let mut local_table = Vec::new();
+        let mut transparent_index = None;
+        local.image.pixels_indexes = self.image.pixels_indexes.clone();
+        local.map_color_indexes(|index| {
+            if Some(index) == self.transparent_index {
+                *transparent_index.get_or_insert_with(|| {
+                    local_table.push(Color::new(0, 0, 0));
+                    local_table.len() - 1
+                })
+            } else {
+                color_index(&mut local_table, &color_table[index], transparent_index)
+            }
+        });
+        *local.color_table.borrow_mut() = local_table;
+        local.transparent_index = transparent_index;
 
-        let mut bytes = Vec::with_capacity(
-            GRAPHICAL_CONTROL_EXTENSION_SIZE + IMAGE_DESCRIPTOR_SIZE + img_data.len(),
-        );
+        local
+    }
+
+    /// Number of colors the pixels are encoded with, tables being padded to a power of two
+    fn color_table_size(&self) -> usize {
+        1 << (color_table_size_bits(self.color_table.borrow().len()) + 1)
+    }
+
+    fn get_color_index(&mut self, color: &Color) -> usize {
+        color_index(
+            &mut self.color_table.borrow_mut(),
+            color,
+            self.transparent_index,
+        )
+    }
+
+    /// Whether both images draw the same pixels at the same place with the same colors and
+    /// disposal, so that showing them one after the other is showing the first one for
+    /// both delays. The runs of pixels are compared as they are, which is cheap but misses
+    /// the same pixels split in other runs.
+    pub(crate) fn same_frame(&self, other: &GifImage) -> bool {
+        (self.left, self.top, self.width, self.height)
+            == (other.left, other.top, other.width, other.height)
+            && self.local_color_table == other.local_color_table
+            && (Rc::ptr_eq(&self.color_table, &other.color_table)
+                || *self.color_table.borrow() == *other.color_table.borrow())
+            && self.transparent_index == other.transparent_index
+            && self.disposal == other.disposal
+            && self.interlaced == other.interlaced
+            && self.image.pixels_indexes == other.image.pixels_indexes
+    }
 
-        if self.delay != 0 {
+    /** Converting the image to bytes **/
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if self.has_gce() {
             bytes.extend_from_slice(&self.get_gce());
         }
+        bytes.extend(self.get_image_block());
 
+        bytes
+    }
+
+    /// Whether the image needs a Graphic Control Extension, which is left out when it
+    /// holds nothing but defaults
+    pub(crate) fn has_gce(&self) -> bool {
+        self.delay != 0
+            || self.transparent_index.is_some()
+            || self.disposal != Disposal::Unspecified
+    }
+
+    /// Image descriptor, local color table and image data, what follows the Graphic
+    /// Control Extension
+    pub(crate) fn get_image_block(&self) -> Vec<u8> {
+        let img_descriptor = self.get_image_descriptor();
+        let local_color_table = self.get_local_color_table();
+        let img_data = self.get_image_data();
+
+        let mut bytes =
+            Vec::with_capacity(IMAGE_DESCRIPTOR_SIZE + local_color_table.len() + img_data.len());
         bytes.extend_from_slice(&img_descriptor);
+        bytes.extend_from_slice(&local_color_table);
         bytes.extend_from_slice(&img_data);
 
         bytes
@@ -133,15 +421,17 @@ impl GifImage {
         bytes[1] = GRAPHIC_CONTROL_LABEL;
         bytes[2] = GCE_BYTE_SIZE;
 
-        // TODO implement
-        bytes[3] = 0; // Packed field
+        // Packed field: 3 reserved bits, disposal method on 3 bits, no user input flag and
+        // transparent color flag
+        bytes[3] = self.disposal.method() << 2 | u8::from(self.transparent_index.is_some());
 
         // adding delay bytes
         let delay_bytes = self.delay.to_le_bytes();
         bytes[4] = delay_bytes[0];
         bytes[5] = delay_bytes[1];
 
-        // NOTE remaining bytes not important for now
+        // Transparent color index, the encoder making sure it fits in the table
+        bytes[6] = self.transparent_index.unwrap_or(0) as u8;
 
         // println!("GCE: {:02X?}", bytes);
 
@@ -157,69 +447,58 @@ impl GifImage {
         img_desc.extend(self.width.to_le_bytes());
         img_desc.extend(self.height.to_le_bytes());
 
-        // FIXME NOTE for now packed field will be 0, but will probably change in the future
-        img_desc.push(0); // packed field
+        // packed field: local color table flag, interlace flag, then the size of the table
+        let mut packed_field = if self.local_color_table {
+            0x80 | color_table_size_bits(self.color_table.borrow().len())
+        } else {
+            0
+        };
+        if self.interlaced {
+            packed_field |= 0x40;
+        }
+        img_desc.push(packed_field);
 
         img_desc
     }
 
-    pub fn get_image_data(&self) -> Vec<u8> {
-        let (lzw_min_code_size, encoded) = self.encode_to_lzw();
-
-        let size_of_encoded = encoded.len();
-        let num_sub_block = size_of_encoded / SUB_BLOCK_MAX_SIZE;
-        let num_byte_last_block = size_of_encoded % SUB_BLOCK_MAX_SIZE;
+    /// Colors of the local color table padded with black, empty for the global one
+    pub fn get_local_color_table(&self) -> Vec<u8> {
+        if !self.local_color_table {
+            return Vec::new();
+        }
 
-        let total_num_sub_block = if num_byte_last_block > 0 {
-            num_sub_block + 1
-        } else {
-            num_sub_block
-        };
+        let color_table = self.color_table.borrow();
+        let padding = self.color_table_size() - color_table.len();
 
-        // lzw_min_code_size (1 byte) +
-        // size_of_encoded bytes +
-        // total_num_sub_block bytes (1 byte for each block) +
-        // block terminator (1 byte)
-        let mut img_data = Vec::with_capacity(2 + total_num_sub_block + size_of_encoded);
-        img_data.push(lzw_min_code_size);
-
-        // Adding full blocks
-        for i in 0..num_sub_block {
-            img_data.push(SUB_BLOCK_MAX_SIZE as u8); // Adding block size
-            img_data.extend_from_slice(
-                &encoded[(SUB_BLOCK_MAX_SIZE * i)..(i + 1) * SUB_BLOCK_MAX_SIZE],
-            );
-        }
+        color_table
+            .iter()
+            .chain(iter::repeat_n(&Color::new(0, 0, 0), padding))
+            .flat_map(Color::as_bytes)
+            .collect()
+    }
 
-        // Adding the last block
-        if num_byte_last_block > 0 {
-            img_data.push(num_byte_last_block as u8);
-            img_data.extend_from_slice(&encoded[size_of_encoded - num_byte_last_block..]);
-        }
+    pub fn get_image_data(&self) -> Vec<u8> {
+        let (lzw_min_code_size, encoded) = self.encode_to_lzw();
 
-        // Pushing the block terminator
-        img_data.push(0);
+        // lzw_min_code_size followed by the sub-blocks of the encoded data
+        let mut img_data = vec![lzw_min_code_size];
+        img_data.extend(sub_blocks(&encoded));
 
-        return img_data;
+        img_data
     }
 
     /// Encode input to lzw and variable length code
-    // fn encode_to_lzw(input: &[u8], num_unique_code: usize) -> (u8, Vec<u8>) {
     fn encode_to_lzw(&self) -> (u8, Vec<u8>) {
-        let num_unique_code = self.color_table.borrow().len();
-        // Initializing code table
-        let mut table: Vec<Vec<usize>> = Vec::with_capacity(CODE_TABLE_MAX_SIZE);
-        for i in 0..num_unique_code {
-            table.push(vec![i]);
-        }
+        let num_unique_code = self.color_table_size();
 
-        // Adding CC (clear code) and EOI (End of Information) to the table
+        // Codes below CC stand for a single color index, the codes after EOI for the
+        // sequences added to the table: a known sequence, its prefix code, followed by k
         let (cc, eoi) = (num_unique_code, num_unique_code + 1);
-        table.push(vec![cc]); // Adding Clear Code
-        table.push(vec![eoi]); // Adding End Of Information
+        let mut table: HashMap<(usize, usize), usize> = HashMap::with_capacity(CODE_TABLE_MAX_SIZE);
+        let mut table_len = eoi + 1;
 
         let mut lzw_min_code_size = 0u8;
-        while 1 << (lzw_min_code_size + 1) < table.len() {
+        while 1 << (lzw_min_code_size + 1) < table_len {
             lzw_min_code_size += 1;
         }
 
@@ -228,55 +507,59 @@ impl GifImage {
         let mut encoded = BitCoder::new();
         encoded.write_code(cc, curr_code_size);
 
-        let mut pixel_indexes = self.image.pixels_indexes.clone();
-
-        // Taking first value for index_buffer
-        let first = &mut pixel_indexes[0];
-        let mut index_buffer = vec![first.value as usize];
-        first.freq -= 1;
-
-        // for rle in &self.image.pixels_indexes {
-        for rle in pixel_indexes {
-            for _ in 0..rle.freq {
-                let k = rle.value as usize;
-
-                let mut index_buffer_plus_k = index_buffer.clone();
-                index_buffer_plus_k.push(k);
-
-                let index = table.iter().position(|code| *code == index_buffer_plus_k);
-
-                match index {
-                    Some(_index) => {
-                        index_buffer.push(k);
-                    }
-                    None => {
-                        table.push(index_buffer_plus_k.clone());
-
-                        // Writing current code to buffer
-                        let code = table.iter().position(|code| *code == index_buffer).unwrap();
-
-                        encoded.write_code(code, curr_code_size);
-
-                        // Update the code size when the number of element in array
-                        // surpasses the number of bits needed to write their index
-                        if (1 << curr_code_size) < table.len() {
-                            curr_code_size += 1;
-                        } else if table.len() == CODE_TABLE_MAX_SIZE - 1 {
-                            // Resetting the table when reaching max size
-                            encoded.write_code(cc, curr_code_size);
-                            table.truncate(eoi + 1); // Since eoi is the last index of the table
-                            curr_code_size = lzw_min_code_size + 1;
-                        }
-
-                        // Set index buffer to k
-                        index_buffer = vec![k];
-                    }
-                }
+        let mut pixels: Box<dyn Iterator<Item = usize>> = if self.interlaced {
+            let width = usize::from(self.width);
+            let indexes: Vec<usize> = self.color_indexes().collect();
+            Box::new(
+                interlaced_rows(usize::from(self.height))
+                    .flat_map(move |row| indexes[row * width..(row + 1) * width].to_vec()),
+            )
+        } else {
+            Box::new(
+                self.image
+                    .pixels_indexes
+                    .iter()
+                    .flat_map(|rle| iter::repeat_n(rle.value, rle.freq)),
+            )
+        };
+
+        // Code of the index buffer, which starts with the first pixel
+        let Some(first) = pixels.next() else {
+            encoded.write_code(eoi, curr_code_size);
+            encoded.flush();
+            return (lzw_min_code_size, encoded.buffer);
+        };
+        let mut index_buffer = first;
+
+        for k in pixels {
+            if let Some(code) = table.get(&(index_buffer, k)) {
+                index_buffer = *code;
+                continue;
             }
+
+            table.insert((index_buffer, k), table_len);
+            table_len += 1;
+
+            // Writing current code to buffer
+            encoded.write_code(index_buffer, curr_code_size);
+
+            // Update the code size when the number of element in array
+            // surpasses the number of bits needed to write their index
+            if (1 << curr_code_size) < table_len {
+                curr_code_size += 1;
+            } else if table_len == CODE_TABLE_MAX_SIZE - 1 {
+                // Resetting the table when reaching max size
+                encoded.write_code(cc, curr_code_size);
+                table.clear();
+                table_len = eoi + 1;
+                curr_code_size = lzw_min_code_size + 1;
+            }
+
+            // Set index buffer to k
+            index_buffer = k;
         }
         // Adding last index value
-        let last_index_value = table.iter().position(|code| *code == index_buffer).unwrap();
-        encoded.write_code(last_index_value, curr_code_size);
+        encoded.write_code(index_buffer, curr_code_size);
 
         // Adding End of information code
         encoded.write_code(eoi, curr_code_size);
@@ -323,6 +606,29 @@ impl BitCoder {
     }
 }
 
+/// Rows of an image `height` pixels high in the order they are written when interlaced
+pub(crate) fn interlaced_rows(height: usize) -> impl Iterator<Item = usize> {
+    [(0, 8), (4, 8), (2, 4), (1, 2)]
+        .into_iter()
+        .flat_map(move |(start, step)| (start..height).step_by(step))
+}
+
+/// Index of a color in a color table, the color being added when missing. The transparent
+/// index is never the one of a color.
+fn color_index(color_table: &mut Vec<Pixel>, color: &Color, transparent: Option<usize>) -> usize {
+    match color_table
+        .iter()
+        .enumerate()
+        .position(|(index, c)| c == color && Some(index) != transparent)
+    {
+        Some(index) => index,
+        None => {
+            color_table.push(color.clone());
+            color_table.len() - 1
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -366,4 +672,110 @@ mod tests {
 
         assert_eq!(expected, result);
     }
+
+    /// FNV-1a hash, to compare large outputs with the ones of the original encoder
+    fn fnv1a(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01B3)
+        })
+    }
+
+    #[test]
+    fn image_data_table_reset_test() {
+        // Noisy images fill the code table several times. The 7 colors are encoded as a
+        // table of 8, its clear code being 8 and not 7.
+        for (color_count, min_code_size, expected_len, expected_hash) in [
+            (7, 3, 14734, 0xFDE9_075E_2584_7E36),
+            (8, 3, 12064, 0xC87B_F150_E454_5A35),
+            (256, 8, 51146, 0x11EB_2C00_BF56_F430),
+        ] {
+            let colors: Vec<Pixel> = (0..color_count)
+                .map(|i| Pixel::new(i as u8, 0, 0))
+                .collect();
+            let pixels: Vec<&Pixel> = (0..200 * 200)
+                .map(|i| {
+                    let (x, y) = (i % 200, i / 200);
+                    &colors[((x * x + y * 3) / 7 + (x ^ y)) % color_count]
+                })
+                .collect();
+
+            let color_table = Rc::new(RefCell::new(colors.clone()));
+            let mut img = GifImage::new(200, 200, Some(color_table));
+            img.set_pixels(pixels);
+
+            let result = img.get_image_data();
+            assert_eq!(result[0], min_code_size);
+            assert_eq!(
+                (result.len(), fnv1a(&result)),
+                (expected_len, expected_hash)
+            );
+        }
+    }
+
+    #[test]
+    fn transparency_test() {
+        let red = Pixel::new(255, 0, 0);
+        let color_table = Rc::new(RefCell::new(vec![red.clone()]));
+        let mut img = GifImage::new(1, 3, Some(Rc::clone(&color_table)));
+        img.set_pixels_with_transparency([Some(&red), None, None]);
+
+        // The transparent color is added to the table and never reused for an opaque pixel
+        assert_eq!(img.transparent_index(), Some(1));
+        assert_eq!(img.color_indexes().collect::<Vec<_>>(), vec![0, 1, 1]);
+        let black = Pixel::new(0, 0, 0);
+        assert_eq!(
+            color_index(&mut color_table.borrow_mut(), &black, Some(1)),
+            2
+        );
+
+        let gce = img.get_gce();
+        assert_eq!((gce[3], gce[6]), (0b0000_0001, 1));
+        assert!(img.as_bytes().starts_with(&gce));
+    }
+
+    #[test]
+    fn disposal_test() {
+        let mut img = GifImage::new(1, 1, None);
+        img.fill(&Pixel::new(255, 0, 0));
+        // Without delay, transparency nor disposal there is no GCE
+        assert_eq!(img.as_bytes()[0], IMAGE_SEPARATOR);
+
+        for (disposal, packed) in [
+            (Disposal::DoNotDispose, 0b0000_0100),
+            (Disposal::RestoreToBackground, 0b0000_1000),
+            (Disposal::RestoreToPrevious, 0b0000_1100),
+        ] {
+            img.set_disposal(disposal);
+            assert_eq!(img.get_gce()[3], packed);
+            assert!(img.as_bytes().starts_with(&img.get_gce()));
+        }
+
+        img.set_transparent_index(Some(0));
+        assert_eq!(img.get_gce()[3], 0b0000_1101);
+        assert_eq!(
+            img.with_local_color_table().disposal(),
+            Disposal::RestoreToPrevious
+        );
+    }
+
+    #[test]
+    fn interlaced_test() {
+        assert_eq!(
+            interlaced_rows(10).collect::<Vec<_>>(),
+            vec![0, 8, 4, 2, 6, 1, 3, 5, 7, 9]
+        );
+
+        let colors: Vec<Pixel> = (0..10).map(|i| Pixel::new(i * 20, 0, 0)).collect();
+        let color_table = Rc::new(RefCell::new(colors.clone()));
+        let mut img = GifImage::new(10, 2, Some(Rc::clone(&color_table)));
+        img.set_pixels(colors.iter().flat_map(|color| [color, color]));
+        img.set_interlaced(true);
+
+        // The same data as the rows written in the interlaced order
+        let mut reordered = GifImage::new(10, 2, Some(color_table));
+        reordered.set_pixels(interlaced_rows(10).flat_map(|row| [&colors[row], &colors[row]]));
+        assert_eq!(img.get_image_data(), reordered.get_image_data());
+        assert_eq!(img.get_image_descriptor()[9], 0x40);
+        assert_eq!(reordered.get_image_descriptor()[9], 0);
+    }
 }
@@ -0,0 +1,165 @@
+pub(crate) const EXTENSION_INTRODUCER: u8 = 0x21;
+pub(crate) const PLAIN_TEXT_LABEL: u8 = 0x01;
+pub(crate) const COMMENT_LABEL: u8 = 0xFE;
+pub(crate) const APPLICATION_LABEL: u8 = 0xFF;
+/// Size of the identifier and authentication code of an Application Extension
+pub(crate) const APPLICATION_BLOCK_SIZE: u8 = 11;
+/// Size of the text grid, cells and colors of a Plain Text Extension
+pub(crate) const PLAIN_TEXT_BLOCK_SIZE: u8 = 12;
+const SUB_BLOCK_MAX_SIZE: usize = 255;
+
+/// Identifier and authentication code of the Application Extension holding the provenance
+/// of a GIF rendered by GAS
+pub const GAS_IDENTIFIER: [u8; 8] = *b"GASPROVN";
+pub const GAS_AUTHENTICATION: [u8; 3] = *b"1.0";
+
+/// Text and data a GIF holds besides its images, which decoders do not show
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// Text of the Comment Extensions
+    pub comments: Vec<String>,
+    /// Application Extensions, but the NETSCAPE2.0 block making animations loop
+    pub applications: Vec<ApplicationData>,
+    /// Plain Text Extensions, which few decoders draw
+    pub plain_texts: Vec<PlainText>,
+}
+
+/// Data of an Application Extension, read by the applications knowing its identifier
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApplicationData {
+    pub identifier: [u8; 8],
+    pub authentication: [u8; 3],
+    pub data: Vec<u8>,
+}
+
+impl ApplicationData {
+    /// Data of GAS, holding the provenance of the GIF
+    pub fn gas(data: &str) -> Self {
+        ApplicationData {
+            identifier: GAS_IDENTIFIER,
+            authentication: GAS_AUTHENTICATION,
+            data: data.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn is_gas(&self) -> bool {
+        self.identifier == GAS_IDENTIFIER && self.authentication == GAS_AUTHENTICATION
+    }
+
+    /// Identifier followed by the authentication code, eg. `NETSCAPE2.0`
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.identifier).into_owned()
+            + &String::from_utf8_lossy(&self.authentication)
+    }
+}
+
+/// Text drawn over the animation by a Plain Text Extension, in a grid of cells of one
+/// character each
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlainText {
+    pub left: u16,
+    pub top: u16,
+    /// Size of the grid in pixels
+    pub width: u16,
+    pub height: u16,
+    /// Size of a character in pixels
+    pub cell_width: u8,
+    pub cell_height: u8,
+    /// Indexes of the global color table of the text and of the cells
+    pub foreground_index: u8,
+    pub background_index: u8,
+    pub text: String,
+}
+
+/// Comment Extension holding `text`
+pub fn comment_extension(text: &str) -> Vec<u8> {
+    let mut bytes = vec![EXTENSION_INTRODUCER, COMMENT_LABEL];
+    bytes.extend(sub_blocks(text.as_bytes()));
+    bytes
+}
+
+/// Application Extension holding `application`
+pub fn application_extension(application: &ApplicationData) -> Vec<u8> {
+    let mut bytes = vec![
+        EXTENSION_INTRODUCER,
+        APPLICATION_LABEL,
+        APPLICATION_BLOCK_SIZE,
+    ];
+    bytes.extend(application.identifier);
+    bytes.extend(application.authentication);
+    bytes.extend(sub_blocks(&application.data));
+    bytes
+}
+
+/// Plain Text Extension drawing `plain_text`
+pub fn plain_text_extension(plain_text: &PlainText) -> Vec<u8> {
+    let mut bytes = vec![
+        EXTENSION_INTRODUCER,
+        PLAIN_TEXT_LABEL,
+        PLAIN_TEXT_BLOCK_SIZE,
+    ];
+    for value in [
+        plain_text.left,
+        plain_text.top,
+        plain_text.width,
+        plain_text.height,
+    ] {
+        bytes.extend(value.to_le_bytes());
+    }
+    bytes.extend([
+        plain_text.cell_width,
+        plain_text.cell_height,
+        plain_text.foreground_index,
+        plain_text.background_index,
+    ]);
+    bytes.extend(sub_blocks(plain_text.text.as_bytes()));
+    bytes
+}
+
+/// Data split in sub-blocks of at most 255 bytes, each preceded by its size, followed by
+/// the block terminator
+pub(crate) fn sub_blocks(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len() + data.len() / SUB_BLOCK_MAX_SIZE + 2);
+    for chunk in data.chunks(SUB_BLOCK_MAX_SIZE) {
+        bytes.push(chunk.len() as u8);
+        bytes.extend_from_slice(chunk);
+    }
+    bytes.push(0);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_blocks_test() {
+        assert_eq!(sub_blocks(b""), vec![0]);
+        let bytes = sub_blocks(&[7; 300]);
+        assert_eq!(bytes.len(), 1 + 255 + 1 + 45 + 1);
+        assert_eq!((bytes[0], bytes[256]), (255, 45));
+    }
+
+    #[test]
+    fn extensions_test() {
+        let application = application_extension(&ApplicationData::gas("a"));
+        assert_eq!(application[..3], [0x21, 0xFF, 11]);
+        assert_eq!(application[3..14], *b"GASPROVN1.0");
+        assert_eq!(application[14..], [1, b'a', 0]);
+
+        let plain_text = plain_text_extension(&PlainText {
+            left: 1,
+            top: 2,
+            width: 16,
+            height: 8,
+            cell_width: 8,
+            cell_height: 8,
+            foreground_index: 1,
+            background_index: 0,
+            text: "hi".to_string(),
+        });
+        assert_eq!(plain_text.len(), 3 + 12 + 4);
+        assert_eq!(plain_text[3..7], [1, 0, 2, 0]);
+        assert_eq!(plain_text[15..], [2, b'h', b'i', 0]);
+    }
+}
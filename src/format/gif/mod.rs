@@ -1,2 +1,7 @@
+pub mod decoder;
+pub mod encoder;
+#[allow(clippy::module_inception)]
 pub mod gif;
 pub mod gif_image;
+pub mod metadata;
+pub mod validator;
@@ -0,0 +1,578 @@
+use std::fmt::Display;
+
+use crate::format::gif::{
+    decoder::{Cursor, DecodeError, VERSIONS, decode_lzw},
+    gif::TRAILER_MARKER,
+    gif_image::{Disposal, GCE_BYTE_SIZE, GRAPHIC_CONTROL_LABEL, IMAGE_SEPARATOR},
+    metadata::{
+        APPLICATION_BLOCK_SIZE, APPLICATION_LABEL, EXTENSION_INTRODUCER, PLAIN_TEXT_BLOCK_SIZE,
+        PLAIN_TEXT_LABEL,
+    },
+};
+
+/// Part of a GIF breaking the GIF89a spec, along with the offset of the block at fault
+#[derive(Debug, PartialEq)]
+pub enum Violation {
+    /// A block that decoders reject. The validation goes on past it when its end can be
+    /// found, and ends with it when the bytes end early or start no known block.
+    Malformed(DecodeError),
+    /// A background color index past the end of the global color table
+    BackgroundIndexOutOfTable { offset: usize, index: u8 },
+    /// A Graphic Control Extension followed by no image nor plain text
+    UnusedGraphicControl { offset: usize },
+    /// A disposal method the spec reserves
+    ReservedDisposal { offset: usize, method: u8 },
+    /// A transparent color index past the end of the color table of the image
+    TransparentIndexOutOfTable { offset: usize, index: usize },
+    /// An image reaching past the logical screen
+    ImageOutOfScreen {
+        offset: usize,
+        left: u16,
+        top: u16,
+        width: u16,
+        height: u16,
+    },
+    /// An LZW minimum code size too small for the color indexes of the color table
+    CodeSizeTooSmall {
+        offset: usize,
+        size: u8,
+        colors: usize,
+    },
+    /// A pixel using a color past the end of the color table of its image, the first one
+    /// of the image being reported
+    ColorIndexOutOfTable { offset: usize, index: u8 },
+    /// LZW data ending without the end of information code right after the last pixel
+    MissingEndCode { offset: usize },
+    /// Bytes after the trailer
+    TrailingBytes { offset: usize },
+}
+
+impl Violation {
+    /// Offset of the block at fault
+    pub fn offset(&self) -> usize {
+        match self {
+            Violation::Malformed(error) => error.offset(),
+            Violation::BackgroundIndexOutOfTable { offset, .. }
+            | Violation::UnusedGraphicControl { offset }
+            | Violation::ReservedDisposal { offset, .. }
+            | Violation::TransparentIndexOutOfTable { offset, .. }
+            | Violation::ImageOutOfScreen { offset, .. }
+            | Violation::CodeSizeTooSmall { offset, .. }
+            | Violation::ColorIndexOutOfTable { offset, .. }
+            | Violation::MissingEndCode { offset }
+            | Violation::TrailingBytes { offset } => *offset,
+        }
+    }
+
+    /// Stable name of the violation in machine-readable messages
+    pub fn code(&self) -> &'static str {
+        match self {
+            Violation::Malformed(_) => "malformed",
+            Violation::BackgroundIndexOutOfTable { .. } => "background-index",
+            Violation::UnusedGraphicControl { .. } => "unused-graphic-control",
+            Violation::ReservedDisposal { .. } => "reserved-disposal",
+            Violation::TransparentIndexOutOfTable { .. } => "transparent-index",
+            Violation::ImageOutOfScreen { .. } => "image-bounds",
+            Violation::CodeSizeTooSmall { .. } => "code-size",
+            Violation::ColorIndexOutOfTable { .. } => "color-index",
+            Violation::MissingEndCode { .. } => "end-code",
+            Violation::TrailingBytes { .. } => "trailing-bytes",
+        }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Malformed(error) => write!(f, "{error}"),
+            Violation::BackgroundIndexOutOfTable { offset, index } => write!(
+                f,
+                "the background color index {index} at byte {offset} is past the end of the \
+                 global color table"
+            ),
+            Violation::UnusedGraphicControl { offset } => write!(
+                f,
+                "the Graphic Control Extension at byte {offset} is followed by no image"
+            ),
+            Violation::ReservedDisposal { offset, method } => write!(
+                f,
+                "the Graphic Control Extension at byte {offset} has the reserved disposal \
+                 method {method}"
+            ),
+            Violation::TransparentIndexOutOfTable { offset, index } => write!(
+                f,
+                "the transparent color index {index} at byte {offset} is past the end of the \
+                 color table of its image"
+            ),
+            Violation::ImageOutOfScreen {
+                offset,
+                left,
+                top,
+                width,
+                height,
+            } => write!(
+                f,
+                "the {width}x{height} image at ({left}, {top}) at byte {offset} reaches past \
+                 the logical screen"
+            ),
+            Violation::CodeSizeTooSmall {
+                offset,
+                size,
+                colors,
+            } => write!(
+                f,
+                "the image data at byte {offset} has a minimum code size of {size}, too \
+                 small for {colors} colors"
+            ),
+            Violation::ColorIndexOutOfTable { offset, index } => write!(
+                f,
+                "the image data at byte {offset} uses color {index}, past the end of its \
+                 color table"
+            ),
+            Violation::MissingEndCode { offset } => write!(
+                f,
+                "the image data at byte {offset} does not end with the end of information \
+                 code after its last pixel"
+            ),
+            Violation::TrailingBytes { offset } => {
+                write!(f, "bytes follow the trailer at byte {offset}")
+            }
+        }
+    }
+}
+
+/// Check a GIF against the GIF89a spec: its block structure, sub-blocks, color table
+/// sizes, LZW code sizes and data, and the bounds of its images. Violations are listed in
+/// the order of the bytes. Blocks are walked on their own rather than decoded, so that a
+/// block decoders reject does not hide the violations after it.
+pub fn validate(bytes: &[u8]) -> Vec<Violation> {
+    let mut validator = Validator {
+        cursor: Cursor::new(bytes),
+        width: 0,
+        height: 0,
+        violations: Vec::new(),
+    };
+    if let Err(error) = validator.walk() {
+        validator.violations.push(Violation::Malformed(error));
+    }
+    validator.violations
+}
+
+/// What a Graphic Control Extension sets for the image after it that is checked against it
+struct GraphicControl {
+    /// Offset of the extension
+    offset: usize,
+    transparent_index: Option<usize>,
+}
+
+struct Validator<'a> {
+    cursor: Cursor<'a>,
+    /// Size of the logical screen
+    width: u16,
+    height: u16,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Validator<'a> {
+    /// Walk the blocks up to the trailer, failing with the error that keeps the next block
+    /// from being found
+    fn walk(&mut self) -> Result<(), DecodeError> {
+        let version = self.cursor.version()?;
+        if !VERSIONS.contains(&version) {
+            self.malformed(DecodeError::UnsupportedVersion(version));
+        }
+
+        // Logical screen descriptor, followed by the global color table when its flag is set
+        let screen = self.cursor.offset;
+        self.cursor.block = screen;
+        self.width = self.cursor.u16()?;
+        self.height = self.cursor.u16()?;
+        let packed = self.cursor.byte()?;
+        let background_index = self.cursor.byte()?;
+        self.cursor.byte()?;
+        let global_color_count = if packed & 0x80 != 0 {
+            let color_count = self.cursor.color_table(packed)?.len();
+            if usize::from(background_index) >= color_count {
+                self.violations.push(Violation::BackgroundIndexOutOfTable {
+                    offset: screen,
+                    index: background_index,
+                });
+            }
+            Some(color_count)
+        } else {
+            None
+        };
+
+        let mut control: Option<GraphicControl> = None;
+        loop {
+            self.cursor.block = self.cursor.offset;
+            match self.cursor.byte()? {
+                TRAILER_MARKER => {
+                    self.unused_graphic_control(control);
+                    if self.cursor.offset < self.cursor.bytes.len() {
+                        self.violations.push(Violation::TrailingBytes {
+                            offset: self.cursor.offset,
+                        });
+                    }
+                    return Ok(());
+                }
+                EXTENSION_INTRODUCER => match self.cursor.byte()? {
+                    GRAPHIC_CONTROL_LABEL => {
+                        self.unused_graphic_control(control);
+                        control = Some(self.graphic_control()?);
+                    }
+                    APPLICATION_LABEL => {
+                        self.fixed_block(APPLICATION_LABEL, APPLICATION_BLOCK_SIZE)?;
+                        self.cursor.sub_blocks()?;
+                    }
+                    PLAIN_TEXT_LABEL => {
+                        self.fixed_block(PLAIN_TEXT_LABEL, PLAIN_TEXT_BLOCK_SIZE)?;
+                        self.cursor.sub_blocks()?;
+                        // The Graphic Control Extension before the text applies to it
+                        control = None;
+                    }
+                    // Comments and other extensions are sub-blocks only
+                    _ => {
+                        self.cursor.sub_blocks()?;
+                    }
+                },
+                IMAGE_SEPARATOR => self.image(global_color_count, control.take())?,
+                byte => {
+                    return Err(DecodeError::UnknownBlock {
+                        offset: self.cursor.block,
+                        byte,
+                    });
+                }
+            }
+        }
+    }
+
+    fn graphic_control(&mut self) -> Result<GraphicControl, DecodeError> {
+        let fields = self.fixed_block(GRAPHIC_CONTROL_LABEL, GCE_BYTE_SIZE)?;
+        self.cursor.sub_blocks()?;
+
+        // The fields of a block of another size are not known
+        let mut transparent_index = None;
+        if let &[packed, _, _, index] = fields {
+            let method = (packed >> 2) & 0x07;
+            if method > Disposal::RestoreToPrevious.method() {
+                self.violations.push(Violation::ReservedDisposal {
+                    offset: self.cursor.block,
+                    method,
+                });
+            }
+            transparent_index = (packed & 0x01 != 0).then_some(usize::from(index));
+        }
+
+        Ok(GraphicControl {
+            offset: self.cursor.block,
+            transparent_index,
+        })
+    }
+
+    /// Image descriptor, local color table and image data, the data being decoded without
+    /// keeping its pixels
+    fn image(
+        &mut self,
+        global_color_count: Option<usize>,
+        control: Option<GraphicControl>,
+    ) -> Result<(), DecodeError> {
+        let left = self.cursor.u16()?;
+        let top = self.cursor.u16()?;
+        let width = self.cursor.u16()?;
+        let height = self.cursor.u16()?;
+        let packed = self.cursor.byte()?;
+        if u32::from(left) + u32::from(width) > u32::from(self.width)
+            || u32::from(top) + u32::from(height) > u32::from(self.height)
+        {
+            self.violations.push(Violation::ImageOutOfScreen {
+                offset: self.cursor.block,
+                left,
+                top,
+                width,
+                height,
+            });
+        }
+        let color_count = if packed & 0x80 != 0 {
+            Some(self.cursor.color_table(packed)?.len())
+        } else {
+            global_color_count
+        };
+        if color_count.is_none() {
+            self.malformed(DecodeError::MissingColorTable {
+                offset: self.cursor.block,
+            });
+        }
+
+        let offset = self.cursor.offset;
+        let min_code_size = self.cursor.byte()?;
+        let data = self.cursor.sub_blocks()?;
+        if !(2..=8).contains(&min_code_size) {
+            self.malformed(DecodeError::InvalidCodeSize {
+                offset,
+                size: min_code_size,
+            });
+            return Ok(());
+        }
+
+        if let Some(color_count) = color_count {
+            if 1 << min_code_size < color_count {
+                self.violations.push(Violation::CodeSizeTooSmall {
+                    offset,
+                    size: min_code_size,
+                    colors: color_count,
+                });
+            }
+            if let Some(control) = &control
+                && let Some(index) = control.transparent_index
+                && index >= color_count
+            {
+                self.violations.push(Violation::TransparentIndexOutOfTable {
+                    offset: control.offset,
+                    index,
+                });
+            }
+        }
+
+        let pixel_count = usize::from(width) * usize::from(height);
+        let mut out_of_table = None;
+        let lzw = decode_lzw(min_code_size, &data, pixel_count, |index| {
+            if out_of_table.is_none()
+                && color_count.is_some_and(|count| usize::from(index) >= count)
+            {
+                out_of_table = Some(index);
+            }
+        });
+        if let Some(index) = out_of_table {
+            self.violations
+                .push(Violation::ColorIndexOutOfTable { offset, index });
+        }
+        match lzw {
+            Err(code) => self.malformed(DecodeError::InvalidCode { offset, code }),
+            Ok((found, _)) if found < pixel_count => self.malformed(DecodeError::MissingPixels {
+                offset,
+                expected: pixel_count,
+                found,
+            }),
+            Ok((_, false)) => self.violations.push(Violation::MissingEndCode { offset }),
+            Ok(_) => {}
+        }
+        Ok(())
+    }
+
+    /// A block that decoders reject, past which the walk goes on
+    fn malformed(&mut self, error: DecodeError) {
+        self.violations.push(Violation::Malformed(error));
+    }
+
+    /// A Graphic Control Extension that is not followed by the image it applies to
+    fn unused_graphic_control(&mut self, control: Option<GraphicControl>) {
+        if let Some(control) = control {
+            self.violations.push(Violation::UnusedGraphicControl {
+                offset: control.offset,
+            });
+        }
+    }
+
+    /// Fixed-size block of an extension, read with the size it gives itself when it is not
+    /// the one of the spec
+    fn fixed_block(&mut self, label: u8, expected: u8) -> Result<&'a [u8], DecodeError> {
+        let size = self.cursor.byte()?;
+        if size != expected {
+            self.malformed(DecodeError::InvalidBlockSize {
+                offset: self.cursor.block,
+                label,
+                size,
+            });
+        }
+        self.cursor.take(usize::from(size))
+    }
+}
+
+/// Fail with the violations of a GIF written by the tests of the encoder
+/// Panic with the violations of a GIF breaking the spec, for the tests of the GIFs written
+#[track_caller]
+pub fn assert_valid(bytes: &[u8]) {
+    let violations = validate(bytes);
+    assert!(violations.is_empty(), "invalid GIF: {violations:?}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::types::Pixel,
+        format::gif::gif::{Gif, Loop},
+    };
+
+    fn gif_bytes() -> Vec<u8> {
+        let mut gif = Gif::new(2, 2, Some(Loop::Forever));
+        gif.add_image().add_delay(10).set_pixels(&[
+            Pixel::new(255, 0, 0),
+            Pixel::new(0, 255, 0),
+            Pixel::new(0, 0, 255),
+            Pixel::new(0, 0, 0),
+        ]);
+        gif.as_bytes().unwrap()
+    }
+
+    #[test]
+    fn validate_test() {
+        let bytes = gif_bytes();
+        assert_valid(&bytes);
+
+        // Header, screen descriptor, 4 colors and the NETSCAPE2.0 block, then the Graphic
+        // Control Extension and the image descriptor
+        let gce = 6 + 7 + 12 + 19;
+        let image = gce + 8;
+        let with = |changes: &[(usize, u8)]| {
+            let mut bytes = bytes.clone();
+            for &(offset, byte) in changes {
+                bytes[offset] = byte;
+            }
+            validate(&bytes)
+        };
+
+        assert_eq!(
+            with(&[(11, 9)]),
+            vec![Violation::BackgroundIndexOutOfTable {
+                offset: 6,
+                index: 9
+            }]
+        );
+        // Disposal method 5 and transparent index 7
+        assert_eq!(
+            with(&[(gce + 3, 5 << 2 | 1), (gce + 6, 7)]),
+            vec![
+                Violation::ReservedDisposal {
+                    offset: gce,
+                    method: 5
+                },
+                Violation::TransparentIndexOutOfTable {
+                    offset: gce,
+                    index: 7
+                }
+            ]
+        );
+        assert_eq!(
+            with(&[(image + 1, 1)]),
+            vec![Violation::ImageOutOfScreen {
+                offset: image,
+                left: 1,
+                top: 0,
+                width: 2,
+                height: 2
+            }]
+        );
+
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert_eq!(
+            validate(&extra),
+            vec![Violation::TrailingBytes {
+                offset: bytes.len()
+            }]
+        );
+
+        // A Graphic Control Extension before the trailer
+        let mut unused = bytes[..bytes.len() - 1].to_vec();
+        unused.extend(&bytes[gce..gce + 8]);
+        unused.push(0x3B);
+        assert_eq!(
+            validate(&unused),
+            vec![Violation::UnusedGraphicControl {
+                offset: bytes.len() - 1
+            }]
+        );
+
+        // Cutting the bytes ends the validation
+        let violations = validate(&bytes[..image + 4]);
+        assert_eq!(
+            violations,
+            vec![Violation::Malformed(DecodeError::Truncated {
+                offset: image
+            })]
+        );
+        assert_eq!(violations[0].offset(), image);
+        assert_eq!(violations[0].code(), "malformed");
+    }
+
+    #[test]
+    fn validate_image_data_test() {
+        let bytes = gif_bytes();
+        let data = 6 + 7 + 12 + 19 + 8 + 10;
+
+        // A 2-bit code size for the colors of a 256 colors table
+        let mut large_table = bytes[..13].to_vec();
+        large_table[10] = 0x97;
+        large_table.extend([0; 3 * 256]);
+        large_table.extend(&bytes[13 + 12..]);
+        assert_eq!(
+            validate(&large_table),
+            vec![Violation::CodeSizeTooSmall {
+                offset: data + 3 * 256 - 12,
+                size: 2,
+                colors: 256
+            }]
+        );
+
+        // Clear code and the 4 pixels, the last one on 4 bits, without the end of
+        // information code
+        let mut no_end = bytes[..data].to_vec();
+        no_end.extend([2, 2, 0x44, 0x34, 0, 0x3B]);
+        assert_eq!(
+            validate(&no_end),
+            vec![Violation::MissingEndCode { offset: data }]
+        );
+    }
+
+    #[test]
+    fn validate_past_malformed_blocks_test() {
+        // A future version, a 1x1 screen with 2 colors, a Graphic Control Extension of 5
+        // bytes, then an image out of the screen using color 3
+        let mut bytes = b"GIF90a".to_vec();
+        bytes.extend([1, 0, 1, 0, 0x80, 0, 0, 0, 0, 0, 255, 255, 255]);
+        bytes.extend([0x21, 0xF9, 5, 0, 0, 0, 0, 0, 0]);
+        bytes.extend([0x2C, 1, 0, 0, 0, 1, 0, 1, 0, 0]);
+        bytes.extend([2, 2, 0x5C, 0x01, 0, TRAILER_MARKER]);
+
+        let violations = validate(&bytes);
+        assert_eq!(
+            violations,
+            vec![
+                Violation::Malformed(DecodeError::UnsupportedVersion(*b"90a")),
+                Violation::Malformed(DecodeError::InvalidBlockSize {
+                    offset: 19,
+                    label: 0xF9,
+                    size: 5
+                }),
+                Violation::ImageOutOfScreen {
+                    offset: 28,
+                    left: 1,
+                    top: 0,
+                    width: 1,
+                    height: 1
+                },
+                Violation::ColorIndexOutOfTable {
+                    offset: 38,
+                    index: 3
+                },
+            ]
+        );
+        assert_eq!(violations[3].code(), "color-index");
+
+        // A single pixel of data for an image of 65535x65535 pixels
+        let mut huge = b"GIF89a".to_vec();
+        huge.extend([0xFF, 0xFF, 0xFF, 0xFF, 0x80, 0, 0, 0, 0, 0, 255, 255, 255]);
+        huge.extend([0x2C, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0]);
+        huge.extend([2, 2, 0x4C, 0x01, 0, TRAILER_MARKER]);
+        assert_eq!(
+            validate(&huge),
+            vec![Violation::Malformed(DecodeError::MissingPixels {
+                offset: 29,
+                expected: 65535 * 65535,
+                found: 1
+            })]
+        );
+    }
+}
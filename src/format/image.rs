@@ -27,6 +27,7 @@ pub trait ImageFormat<T> {
     ) -> &mut Self;
 }
 
+#[derive(Clone)]
 pub struct Image<T> {
     height: usize,
     width: usize,
@@ -41,7 +42,7 @@ where
         Image {
             height,
             width,
-            pixels_indexes: vec![RLE::new((width * height) as usize, default_color.clone())],
+            pixels_indexes: vec![RLE::new(width * height, default_color)],
         }
     }
 
@@ -53,16 +54,15 @@ where
         // current interval bounds (eg. [1, 4]);
         let (mut start_count, mut end_count) = (0, 0);
 
-        let mut current_index = 0;
         let mut new_entries = vec![RLE::new(freq, color)];
-        for p in &mut self.pixels_indexes {
+        for (current_index, p) in self.pixels_indexes.iter_mut().enumerate() {
             end_count += p.freq;
 
             // If start of interval in current interval
             if start_count <= interval_start && interval_start < end_count {
                 let diff = interval_start - start_count;
                 if diff > 0 {
-                    new_entries.insert(0, RLE::new(diff as usize, p.value));
+                    new_entries.insert(0, RLE::new(diff, p.value));
                 }
                 index_start = current_index;
             }
@@ -77,7 +77,7 @@ where
                     new_entries.push(RLE::new(diff as usize, p.value));
                 } else if diff < 0 {
                     // Reducing the size of the next entry
-                    self.pixels_indexes[index_end + 1].freq -= diff.abs() as usize;
+                    self.pixels_indexes[index_end + 1].freq -= diff.unsigned_abs();
                 }
 
                 // Since the index_start must be set if we reach index_end
@@ -85,7 +85,6 @@ where
             }
 
             start_count = end_count;
-            current_index += 1;
         }
         // DEBUG
         // println!("new_entries: {new_entries:?}");
@@ -103,14 +102,31 @@ where
 
     pub fn draw_rectangle(&mut self, start: Position, end: Position, color: T) -> &mut Self {
         for y in start.y..end.y + 1 {
-            let interval_start = y * self.width as usize + start.x;
-            let interval_end = y * self.width as usize + end.x;
+            let interval_start = y * self.width + start.x;
+            let interval_end = y * self.width + end.x;
             self.add_interval(interval_start, interval_end, color);
         }
 
         self
     }
 
+    /// Replace the content of the image by values given row by row
+    pub fn set_pixels(&mut self, pixels: impl IntoIterator<Item = T>) -> &mut Self
+    where
+        T: PartialEq,
+    {
+        self.pixels_indexes.clear();
+
+        for value in pixels {
+            match self.pixels_indexes.last_mut() {
+                Some(last) if last.value == value => last.freq += 1,
+                _ => self.pixels_indexes.push(RLE::new(1, value)),
+            }
+        }
+
+        self
+    }
+
     pub fn print_content(&self) {
         let mut i = 1;
         for RLE { freq, value } in &self.pixels_indexes {
@@ -230,4 +246,15 @@ mod tests {
 
         assert_eq!(expected, img.pixels_indexes);
     }
+
+    #[test]
+    fn image_set_pixels_test() {
+        let mut img = Image::new(2, 3, 'A');
+        img.set_pixels("ABBBCC".chars());
+
+        assert_eq!(
+            img.pixels_indexes,
+            vec![RLE::new(1, 'A'), RLE::new(3, 'B'), RLE::new(2, 'C')]
+        );
+    }
 }
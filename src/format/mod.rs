@@ -1,2 +1,5 @@
+pub mod dither;
 pub mod gif;
 pub mod image;
+pub mod ppm;
+pub mod quantize;
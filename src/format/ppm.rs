@@ -0,0 +1,62 @@
+use crate::common::types::Pixel;
+use std::{fs::File, io::Write};
+
+pub const MAGIC_NUMBER: [u8; 2] = *b"P6";
+pub const MAX_COLOR_VALUE: u8 = 255;
+
+/// Binary PPM image: a short text header followed by the RGB bytes of every pixel
+pub struct Ppm {
+    height: usize,
+    width: usize,
+    pixels: Vec<Pixel>,
+}
+
+impl Ppm {
+    /// `pixels` are read row by row, missing ones are black
+    pub fn new(height: usize, width: usize, pixels: Vec<Pixel>) -> Self {
+        let mut pixels = pixels;
+        pixels.resize(height * width, Pixel::new(0, 0, 0));
+
+        Ppm {
+            height,
+            width,
+            pixels,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let header = format!("{} {}\n{MAX_COLOR_VALUE}\n", self.width, self.height);
+
+        let mut bytes = Vec::with_capacity(3 + header.len() + self.pixels.len() * 3);
+        bytes.extend_from_slice(&MAGIC_NUMBER);
+        bytes.push(b'\n');
+        bytes.extend_from_slice(header.as_bytes());
+        for pixel in &self.pixels {
+            bytes.extend(pixel.as_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), Box<dyn std::error::Error>> {
+        writer.write_all(&self.as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn write_to_file(&self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_to(File::create(filename)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ppm_as_bytes_test() {
+        let ppm = Ppm::new(1, 2, vec![Pixel::new(255, 0, 0)]);
+
+        assert_eq!(ppm.as_bytes(), b"P6\n2 1\n255\n\xFF\x00\x00\x00\x00\x00");
+    }
+}
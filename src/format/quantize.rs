@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use crate::common::types::Pixel;
+
+/// Builds a reduced palette out of the colors of images: median cut followed by
+/// `kmeans_iterations` rounds of k-means refinement
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Quantizer {
+    /// Rounds of k-means moving the colors of the palette to the mean of the colors they
+    /// replace, none by default since median cut alone is usually close
+    pub kmeans_iterations: u32,
+}
+
+impl Quantizer {
+    /// Palette of at most `max_colors` colors for a histogram of colors and their number of
+    /// pixels, its colors being kept as is when there are few enough of them
+    pub fn palette(&self, histogram: &[(Pixel, usize)], max_colors: usize) -> Vec<Pixel> {
+        if histogram.len() <= max_colors {
+            return histogram.iter().map(|(color, _)| color.clone()).collect();
+        }
+
+        let mut palette = median_cut(histogram, max_colors);
+        kmeans(histogram, &mut palette, self.kmeans_iterations);
+
+        palette
+    }
+}
+
+/// Colors of pixels along with their number of pixels, in order of appearance
+pub fn histogram<'a>(pixels: impl IntoIterator<Item = &'a Pixel>) -> Vec<(Pixel, usize)> {
+    let mut indexes = HashMap::new();
+    let mut histogram: Vec<(Pixel, usize)> = Vec::new();
+
+    for pixel in pixels {
+        let index = *indexes.entry(pixel.as_bytes()).or_insert_with(|| {
+            histogram.push((pixel.clone(), 0));
+            histogram.len() - 1
+        });
+        histogram[index].1 += 1;
+    }
+
+    histogram
+}
+
+/// Split the colors in boxes until there are `max_colors` of them, each time cutting the
+/// box spreading the most along a channel at the median pixel of that channel. The palette
+/// holds the mean color of each box.
+pub fn median_cut(histogram: &[(Pixel, usize)], max_colors: usize) -> Vec<Pixel> {
+    let mut colors: Vec<([u8; 3], usize)> = histogram
+        .iter()
+        .map(|(color, count)| (color.as_bytes(), *count))
+        .collect();
+    let mut boxes = Vec::with_capacity(max_colors);
+    boxes.push(0..colors.len());
+
+    while boxes.len() < max_colors {
+        // Widest channel of each box that can still be cut, along with its spread and
+        // number of pixels
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| range.len() > 1)
+            .map(|(index, range)| {
+                let colors = &colors[range.clone()];
+                let (channel, spread) = (0..3)
+                    .map(|channel| {
+                        let values = colors.iter().map(|(color, _)| color[channel]);
+                        let spread = values.clone().max().unwrap() - values.min().unwrap();
+                        (channel, spread)
+                    })
+                    .max_by_key(|(_, spread)| *spread)
+                    .unwrap();
+                let pixels: usize = colors.iter().map(|(_, count)| count).sum();
+                (index, channel, spread, pixels)
+            })
+            .max_by_key(|(_, _, spread, pixels)| (*spread, *pixels));
+        let Some((index, channel, _, pixels)) = widest else {
+            break;
+        };
+
+        let range = boxes[index].clone();
+        let cut = &mut colors[range.clone()];
+        cut.sort_by_key(|(color, _)| color[channel]);
+
+        // First color past half of the pixels, keeping a color on each side
+        let mut seen = 0;
+        let mut median = cut
+            .iter()
+            .position(|(_, count)| {
+                seen += count;
+                seen * 2 >= pixels
+            })
+            .unwrap_or(0)
+            .clamp(1, cut.len() - 1);
+        // Colors with the same value on the channel stay in the same box
+        let value = |index: usize| cut[index].0[channel];
+        while median < cut.len() - 1 && value(median - 1) == value(median) {
+            median += 1;
+        }
+        while median > 1 && value(median - 1) == value(median) {
+            median -= 1;
+        }
+
+        boxes[index] = range.start..range.start + median;
+        boxes.push(range.start + median..range.end);
+    }
+
+    boxes
+        .into_iter()
+        .map(|range| mean(colors[range].iter().map(|(color, count)| (*color, *count))))
+        .collect()
+}
+
+/// Move each color of the palette to the mean of the colors closest to it, `iterations`
+/// times or until the palette does not change anymore
+pub fn kmeans(histogram: &[(Pixel, usize)], palette: &mut [Pixel], iterations: u32) {
+    for _ in 0..iterations {
+        let mut clusters: Vec<Vec<([u8; 3], usize)>> = vec![Vec::new(); palette.len()];
+        for (color, count) in histogram {
+            clusters[nearest(palette, color)].push((color.as_bytes(), *count));
+        }
+
+        let mut moved = false;
+        for (color, cluster) in palette.iter_mut().zip(clusters) {
+            // A color no other is closest to stays where it is
+            if cluster.is_empty() {
+                continue;
+            }
+            let mean = mean(cluster.into_iter());
+            moved |= mean != *color;
+            *color = mean;
+        }
+
+        if !moved {
+            break;
+        }
+    }
+}
+
+/// Mean of colors weighted by their number of pixels
+fn mean(colors: impl Iterator<Item = ([u8; 3], usize)>) -> Pixel {
+    let (mut sums, mut total) = ([0u64; 3], 0u64);
+    for (color, count) in colors {
+        for (sum, value) in sums.iter_mut().zip(color) {
+            *sum += u64::from(value) * count as u64;
+        }
+        total += count as u64;
+    }
+
+    let [r, g, b] = sums.map(|sum| ((sum + total / 2) / total.max(1)) as u8);
+    Pixel::new(r, g, b)
+}
+
+/// Index of the color of the palette closest to `color`
+pub fn nearest(palette: &[Pixel], color: &Pixel) -> usize {
+    (0..palette.len())
+        .min_by_key(|&index| distance(&palette[index], color))
+        .unwrap_or(0)
+}
+
+/// Squared euclidean distance between two colors
+pub fn distance(a: &Pixel, b: &Pixel) -> u32 {
+    [(a.r, b.r), (a.g, b.g), (a.b, b.b)]
+        .into_iter()
+        .map(|(a, b)| u32::from(a.abs_diff(b)).pow(2))
+        .sum()
+}
+
+/// Maps colors to the closest color of a palette, remembering the colors already mapped
+pub struct ColorMap<'a> {
+    palette: &'a [Pixel],
+    nearest: HashMap<[u8; 3], usize>,
+}
+
+impl<'a> ColorMap<'a> {
+    pub fn new(palette: &'a [Pixel]) -> Self {
+        ColorMap {
+            palette,
+            nearest: HashMap::new(),
+        }
+    }
+
+    /// Index of the closest color of the palette
+    pub fn index(&mut self, color: &Pixel) -> usize {
+        *self
+            .nearest
+            .entry(color.as_bytes())
+            .or_insert_with(|| nearest(self.palette, color))
+    }
+
+    pub fn palette(&self) -> &'a [Pixel] {
+        self.palette
+    }
+
+    /// Closest color of the palette
+    pub fn color(&mut self, color: &Pixel) -> &'a Pixel {
+        let palette = self.palette;
+        &palette[self.index(color)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_cut_test() {
+        // Two groups of colors, one of them used much more
+        let mut histogram: Vec<(Pixel, usize)> =
+            (0..10).map(|i| (Pixel::new(i, 0, 0), 100)).collect();
+        histogram.extend((0..10).map(|i| (Pixel::new(0, 0, 240 + i), 1)));
+
+        let palette = median_cut(&histogram, 4);
+        assert_eq!(palette.len(), 4);
+        // The groups are cut apart first, however few pixels one of them has
+        assert_eq!(palette.iter().filter(|color| color.b > 200).count(), 2);
+        assert_eq!(
+            Quantizer::default().palette(&histogram[..3], 4),
+            vec![
+                Pixel::new(0, 0, 0),
+                Pixel::new(1, 0, 0),
+                Pixel::new(2, 0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn kmeans_test() {
+        let histogram = [
+            (Pixel::new(0, 0, 0), 1),
+            (Pixel::new(10, 0, 0), 3),
+            (Pixel::new(200, 0, 0), 1),
+        ];
+        let mut palette = vec![Pixel::new(0, 0, 0), Pixel::new(255, 0, 0)];
+
+        kmeans(&histogram, &mut palette, 5);
+        assert_eq!(palette, vec![Pixel::new(8, 0, 0), Pixel::new(200, 0, 0)]);
+    }
+
+    #[test]
+    fn color_map_test() {
+        let palette = [Pixel::new(0, 0, 255), Pixel::new(255, 0, 0)];
+        let mut map = ColorMap::new(&palette);
+
+        assert_eq!(map.index(&Pixel::new(200, 10, 30)), 1);
+        assert_eq!(map.color(&Pixel::new(20, 10, 200)), &palette[0]);
+        assert_eq!(
+            histogram(&[palette[1].clone(), palette[0].clone(), palette[1].clone()]),
+            vec![(palette[1].clone(), 2), (palette[0].clone(), 1)]
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod animator;
+pub mod common;
+pub mod format;
+pub mod render;
+pub mod script;
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+
+use crate::{
+    animator::{scene::Frame, types::Color},
+    common::types::Pixel,
+    format::{
+        gif::{
+            gif::{Gif, Loop},
+            gif_image::Disposal,
+        },
+        ppm::Ppm,
+    },
+    script::{compiler::Settings, source::SourceMap},
+};
+
+/// Delay between two frames in centiseconds, as used by GIF
+pub fn frame_delay(fps: u32) -> u16 {
+    (100.0 / f64::from(fps.max(1))).round().max(1.0) as u16
+}
+
+/// Flatten a color over the background, since GIF pixels are opaque
+pub fn blend(color: Color, background: Color) -> Pixel {
+    let [r, g, b, a] = color.to_rgba();
+    let [bg_r, bg_g, bg_b, _] = background.to_rgba();
+
+    let alpha = u16::from(a);
+    let mix = |fg: u8, bg: u8| {
+        ((u16::from(fg) * alpha + u16::from(bg) * (255 - alpha) + 127) / 255) as u8
+    };
+
+    Pixel::new(mix(r, bg_r), mix(g, bg_g), mix(b, bg_b))
+}
+
+/// Color of the pixels no shape covers, the background being opaque
+pub fn background_pixel(background: Color) -> Pixel {
+    blend(background, background)
+}
+
+/// Pixels of a frame, row by row
+pub fn frame_pixels(frame: &Frame, background: Color) -> Vec<Pixel> {
+    frame
+        .buffer
+        .iter()
+        .flatten()
+        .map(|color| blend(*color, background))
+        .collect()
+}
+
+/// Whether the pixels no shape covers are transparent, which takes a background with an
+/// alpha of 0
+pub fn is_transparent(background: Color) -> bool {
+    background.to_rgba()[3] == 0
+}
+
+/// Pixels of a frame, row by row, `None` being a pixel left fully transparent over a
+/// transparent background. Other pixels are flattened over the color of the background.
+pub fn frame_pixels_with_transparency(frame: &Frame, background: Color) -> Vec<Option<Pixel>> {
+    let transparent = is_transparent(background);
+
+    frame
+        .buffer
+        .iter()
+        .flatten()
+        .map(|color| (!transparent || color.to_rgba()[3] != 0).then(|| blend(*color, background)))
+        .collect()
+}
+
+/// Colors of the frames once flattened over the background, in order of appearance
+pub fn frames_palette(frames: impl IntoIterator<Item = Frame>, background: Color) -> Vec<Pixel> {
+    frames_histogram(frames, background)
+        .into_iter()
+        .map(|(color, _)| color)
+        .collect()
+}
+
+/// Colors of the frames once flattened over the background along with their number of
+/// pixels, in order of appearance. Transparent pixels are left out.
+pub fn frames_histogram(
+    frames: impl IntoIterator<Item = Frame>,
+    background: Color,
+) -> Vec<(Pixel, usize)> {
+    let mut indexes = HashMap::new();
+    let mut histogram: Vec<(Pixel, usize)> = Vec::new();
+
+    let transparent = is_transparent(background);
+
+    for frame in frames {
+        for color in frame.buffer.iter().flatten() {
+            if transparent && color.to_rgba()[3] == 0 {
+                continue;
+            }
+            let pixel = blend(*color, background);
+            let index = *indexes.entry(pixel.as_bytes()).or_insert_with(|| {
+                histogram.push((pixel, 0));
+                histogram.len() - 1
+            });
+            histogram[index].1 += 1;
+        }
+    }
+
+    histogram
+}
+
+/// Convert rendered frames into a looping `Gif`, one image per frame
+pub fn frames_to_gif(frames: &[Frame], settings: &Settings) -> Gif {
+    let mut gif = Gif::new(
+        settings.height as u16,
+        settings.width as u16,
+        Some(Loop::Forever),
+    );
+    let delay = frame_delay(settings.fps);
+    let transparent = is_transparent(settings.background);
+    // Transparent pixels would show the previous frame through unless it is cleared
+    let disposal = if transparent {
+        gif.set_transparency();
+        Disposal::RestoreToBackground
+    } else {
+        gif.set_background_color(background_pixel(settings.background));
+        Disposal::Unspecified
+    };
+
+    for frame in frames {
+        let pixels = frame_pixels_with_transparency(frame, settings.background);
+        gif.add_image()
+            .add_delay(delay)
+            .set_disposal(disposal)
+            .set_pixels_with_transparency(pixels.iter().map(Option::as_ref));
+    }
+
+    gif
+}
+
+/// Convert a single frame into a still `Gif`, without delay nor looping
+pub fn frame_to_gif(frame: &Frame, settings: &Settings) -> Gif {
+    let mut gif = Gif::new(settings.height as u16, settings.width as u16, None);
+    if is_transparent(settings.background) {
+        gif.set_transparency();
+    } else {
+        gif.set_background_color(background_pixel(settings.background));
+    }
+
+    let pixels = frame_pixels_with_transparency(frame, settings.background);
+    gif.add_image()
+        .set_pixels_with_transparency(pixels.iter().map(Option::as_ref));
+
+    gif
+}
+
+/// Where a GIF comes from, written in its metadata so that it can be traced back to the
+/// script that made it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    /// Path of the script, as given to GAS
+    pub script: String,
+    /// FNV-1a hash of the script and its imports
+    pub source_hash: u64,
+    /// Version of GAS
+    pub version: String,
+    /// Settings of the render, eg. `("fps", "25")`
+    pub settings: Vec<(String, String)>,
+}
+
+impl Provenance {
+    /// Provenance of a render of the script of `sources` by this version of GAS
+    pub fn new(sources: &SourceMap, settings: Vec<(String, String)>) -> Self {
+        let files = sources.files();
+        Provenance {
+            script: files
+                .first()
+                .map_or_else(String::new, |file| file.path.display().to_string()),
+            source_hash: files.iter().fold(FNV_OFFSET_BASIS, |hash, file| {
+                fnv1a(hash, file.source.as_bytes())
+            }),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            settings,
+        }
+    }
+
+    /// Provenance as lines of text, eg.
+    ///
+    /// ```text
+    /// GAS 0.1.0
+    /// script: intro.gas
+    /// source: fnv1a-64 cbf29ce484222325
+    /// settings: width=400 height=300 fps=25
+    /// ```
+    pub fn to_text(&self) -> String {
+        let settings: Vec<String> = self
+            .settings
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+
+        format!(
+            "GAS {}\nscript: {}\nsource: fnv1a-64 {:016x}\nsettings: {}",
+            self.version,
+            self.script,
+            self.source_hash,
+            settings.join(" ")
+        )
+    }
+
+    /// Provenance written by `to_text`, `None` for another text
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let version = lines.next()?.strip_prefix("GAS ")?;
+        let script = lines.next()?.strip_prefix("script: ")?;
+        let hash = lines.next()?.strip_prefix("source: fnv1a-64 ")?;
+        let settings = lines.next()?.strip_prefix("settings:")?;
+
+        Some(Provenance {
+            script: script.to_string(),
+            source_hash: u64::from_str_radix(hash, 16).ok()?,
+            version: version.to_string(),
+            settings: settings
+                .split_whitespace()
+                .map(|setting| {
+                    let (name, value) = setting.split_once('=')?;
+                    Some((name.to_string(), value.to_string()))
+                })
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+
+/// FNV-1a hash of `bytes`, starting from `hash`
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+pub fn frame_to_ppm(frame: &Frame, settings: &Settings) -> Ppm {
+    Ppm::new(
+        settings.height,
+        settings.width,
+        frame_pixels(frame, settings.background),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_delay_test() {
+        assert_eq!(frame_delay(25), 4);
+        assert_eq!(frame_delay(30), 3);
+        assert_eq!(frame_delay(100), 1);
+    }
+
+    #[test]
+    fn frames_palette_test() {
+        let mut frame = Frame::new(2, 1);
+        frame.put_pixel(1, 0, Color::RGB(255, 0, 0));
+        let background = Color::RGB(0, 0, 255);
+
+        assert_eq!(
+            frames_palette([frame.clone(), frame], background),
+            vec![Pixel::new(0, 0, 255), Pixel::new(255, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn frames_histogram_test() {
+        let mut frame = Frame::new(3, 1);
+        frame.put_pixel(1, 0, Color::RGB(255, 0, 0));
+        let background = Color::RGB(0, 0, 255);
+
+        assert_eq!(
+            frames_histogram([frame.clone(), frame], background),
+            vec![(Pixel::new(0, 0, 255), 4), (Pixel::new(255, 0, 0), 2)]
+        );
+    }
+
+    #[test]
+    fn frame_pixels_with_transparency_test() {
+        let mut frame = Frame::new(3, 1);
+        frame.put_pixel(1, 0, Color::RGB(255, 0, 0));
+        frame.put_pixel(2, 0, Color::RGBA(255, 0, 0, 128));
+
+        assert_eq!(
+            frame_pixels_with_transparency(&frame, Color::RGBA(0, 0, 0, 0)),
+            vec![
+                None,
+                Some(Pixel::new(255, 0, 0)),
+                Some(Pixel::new(128, 0, 0))
+            ]
+        );
+        assert_eq!(
+            frame_pixels_with_transparency(&frame, Color::RGB(0, 0, 255))[0],
+            Some(Pixel::new(0, 0, 255))
+        );
+        assert_eq!(frames_histogram([frame], Color::RGBA(0, 0, 0, 0)).len(), 2);
+    }
+
+    #[test]
+    fn provenance_test() {
+        let mut sources = SourceMap::new();
+        sources.add("intro.gas".into(), "fps: 25".to_string());
+        let provenance = Provenance::new(&sources, vec![("fps".to_string(), "25".to_string())]);
+
+        assert_eq!(provenance.script, "intro.gas");
+        assert_ne!(provenance.source_hash, FNV_OFFSET_BASIS);
+        assert!(provenance.to_text().ends_with("\nsettings: fps=25"));
+        assert_eq!(Provenance::parse(&provenance.to_text()), Some(provenance));
+        assert_eq!(Provenance::parse("made by hand"), None);
+    }
+
+    #[test]
+    fn blend_test() {
+        let background = Color::RGB(0, 0, 255);
+
+        assert_eq!(
+            blend(Color::RGBA(0, 0, 0, 0), background),
+            Pixel::new(0, 0, 255)
+        );
+        assert_eq!(
+            blend(Color::RGBA(255, 0, 0, 255), background),
+            Pixel::new(255, 0, 0)
+        );
+        assert_eq!(
+            blend(Color::RGBA(255, 0, 0, 128), background),
+            Pixel::new(128, 0, 127)
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use super::diagnostic::Span;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Script {
+    pub config: Vec<ConfigEntry>,
+    pub statements: Vec<Statement>,
+}
+
+/// `key: value` line of the config block delimited by `---`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub key_span: Span,
+    pub value: String,
+    pub value_span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Identifier(Identifier),
+    Number {
+        value: f64,
+        unit: Option<String>,
+        span: Span,
+    },
+    HexColor {
+        value: u32,
+        span: Span,
+    },
+    String {
+        value: String,
+        span: Span,
+    },
+    /// Function call, eg. `rgb(255, 0, 0)`
+    Call {
+        name: Identifier,
+        arguments: Vec<Expression>,
+        span: Span,
+    },
+    /// List of values, eg. `[0, 5]`
+    List {
+        items: Vec<Expression>,
+        span: Span,
+    },
+    /// Values in parentheses, eg. `(color 1s)` in a transition
+    Group {
+        items: Vec<Expression>,
+        span: Span,
+    },
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Identifier(identifier) => identifier.span,
+            Expression::Number { span, .. }
+            | Expression::HexColor { span, .. }
+            | Expression::String { span, .. }
+            | Expression::Call { span, .. }
+            | Expression::List { span, .. }
+            | Expression::Group { span, .. } => *span,
+        }
+    }
+}
+
+/// Field of a declaration, eg. `color red -> green` where `red` is an argument and `green`
+/// the target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: Identifier,
+    pub arguments: Vec<Expression>,
+    pub target: Option<Expression>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// `name = value`
+    Assignment { name: Identifier, value: Expression },
+    /// `name RECT` followed by indented fields
+    Shape {
+        name: Identifier,
+        kind: Identifier,
+        fields: Vec<Field>,
+    },
+    /// `name ANIM` followed by indented fields
+    Animation {
+        name: Identifier,
+        fields: Vec<Field>,
+    },
+    /// Animation sequence, each step starting once the previous one is done
+    Sequence(Vec<SequenceStep>),
+    /// `import "file.gas"`, replaced by the statements of the file when loading the script
+    Import { path: String, span: Span },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SequenceStep {
+    /// `animation(target)`, or `'animation(target)` when reversed
+    Apply {
+        animation: Identifier,
+        target: Identifier,
+        reversed: bool,
+        span: Span,
+    },
+    /// `[a(x), b(y)]`, every branch starting at the same time
+    Concurrent {
+        branches: Vec<Vec<SequenceStep>>,
+        span: Span,
+    },
+}
+
+impl SequenceStep {
+    pub fn span(&self) -> Span {
+        match self {
+            SequenceStep::Apply { span, .. } | SequenceStep::Concurrent { span, .. } => *span,
+        }
+    }
+}
@@ -0,0 +1,1306 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    animator::{
+        objects::shapes::rectangle::Rectangle,
+        scene::Scene,
+        transition::{Transition, TransitionDescriptor, TransitionObject},
+        types::{
+            Color, ColorChannels, Direction, EasingFunction, Length, Point, Rotation, StepJump,
+        },
+    },
+    render::blend,
+};
+
+use super::{
+    ast::{ConfigEntry, Expression, Field, Identifier, Script, SequenceStep, Statement},
+    diagnostic::{Code, Diagnostic, Span},
+    parser::parse_value,
+};
+
+pub const DEFAULT_WIDTH: usize = 100;
+pub const DEFAULT_HEIGHT: usize = 100;
+pub const DEFAULT_FPS: u32 = 25;
+/// Duration of an animation without a `delay` field, in seconds
+pub const DEFAULT_DURATION: f64 = 1.0;
+/// GIF delays are in centiseconds, which caps the frame rate
+pub const MAX_FPS: u32 = 100;
+/// Longest animation in seconds, so that its frames can be counted at any frame rate
+pub const MAX_DURATION: f64 = (u32::MAX / MAX_FPS) as f64;
+/// Recolors over more frames have the colors of their whole interpolation counted rather
+/// than the color of each frame, which takes at most a few thousand steps
+const MAX_COUNTED_FRAMES: u32 = 1024;
+
+const CONSTANT_COLORS: [(&str, Color); 9] = [
+    ("BLACK", Color::RGB(0, 0, 0)),
+    ("RED", Color::RGB(255, 0, 0)),
+    ("GREEN", Color::RGB(0, 255, 0)),
+    ("YELLOW", Color::RGB(255, 255, 0)),
+    ("BLUE", Color::RGB(0, 0, 255)),
+    ("MAGENTA", Color::RGB(255, 0, 255)),
+    ("CYAN", Color::RGB(0, 255, 255)),
+    ("WHITE", Color::RGB(255, 255, 255)),
+    ("TRANSPARENT", Color::RGBA(0, 0, 0, 0)),
+];
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub width: usize,
+    pub height: usize,
+    pub fps: u32,
+    /// Output file requested by the script
+    pub filename: Option<String>,
+    pub background: Color,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            fps: DEFAULT_FPS,
+            filename: None,
+            background: Color::RGB(0, 0, 0),
+        }
+    }
+}
+
+/// Change of a shape over time. Values are absolute, the start value being whatever the
+/// shape is at when the effect starts.
+#[derive(Debug, Clone, Copy)]
+pub enum EffectKind {
+    Move(Point),
+    Recolor(ColorChannels),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Effect {
+    /// Start in seconds
+    pub start: f64,
+    /// End in seconds, an effect where `start == end` happens instantly
+    pub end: f64,
+    pub kind: EffectKind,
+    pub easing: EasingFunction,
+}
+
+#[derive(Clone)]
+pub struct CompiledShape {
+    pub name: String,
+    pub rectangle: Rectangle,
+    pub effects: Vec<Effect>,
+}
+
+/// Compiled script, independent of the frame rate until turned into a `Scene`
+#[derive(Clone)]
+pub struct Program {
+    pub settings: Settings,
+    pub shapes: Vec<CompiledShape>,
+    /// Total duration in seconds
+    pub duration: f64,
+}
+
+impl Program {
+    pub fn frame_count(&self) -> u32 {
+        self.to_frame(self.duration).saturating_add(1)
+    }
+
+    /// Frame shown at a time in seconds, the last frame a `u32` can count for later times
+    pub fn to_frame(&self, seconds: f64) -> u32 {
+        // Casting saturates, and compiled programs last at most `MAX_DURATION`
+        (seconds * f64::from(self.settings.fps)).round() as u32
+    }
+
+    /// Upper bound of the number of colors of the rendered frames, found without rendering
+    /// them from the background and every color the shapes go through.
+    pub fn estimated_color_count(&self) -> usize {
+        let background = self.settings.background;
+        let mut colors = HashSet::new();
+        let mut insert = |color: Color| {
+            let pixel = blend(color, background);
+            colors.insert([pixel.r, pixel.g, pixel.b]);
+        };
+
+        insert(background);
+        for shape in &self.shapes {
+            let mut color = ColorChannels::from(shape.rectangle.fill_color);
+            insert(shape.rectangle.fill_color);
+
+            for effect in &shape.effects {
+                let EffectKind::Recolor(end) = effect.kind else {
+                    continue;
+                };
+
+                let start_frame = self.to_frame(effect.start);
+                let end_frame = self.to_frame(effect.end);
+                if end_frame - start_frame <= MAX_COUNTED_FRAMES {
+                    for frame in start_frame..end_frame {
+                        let progress =
+                            f64::from(frame - start_frame) / f64::from(end_frame - start_frame);
+                        insert(Color::from(
+                            color + (end - color) * effect.easing.apply(progress),
+                        ));
+                    }
+                } else {
+                    for progress in interpolation_steps(color, end, effect.easing.range()) {
+                        insert(Color::from(color + (end - color) * progress));
+                    }
+                }
+                insert(Color::from(end));
+
+                color = end;
+            }
+        }
+
+        colors.len()
+    }
+
+    /// Build the scene of the program at the current frame rate
+    pub fn scene(&self) -> Scene {
+        let mut scene = Scene::new(
+            self.settings.width,
+            self.settings.height,
+            self.frame_count(),
+        );
+
+        for shape in &self.shapes {
+            let mut rectangle = shape.rectangle.clone();
+            let mut transitions = Vec::with_capacity(shape.effects.len());
+
+            for effect in &shape.effects {
+                let end_frame = self.to_frame(effect.end);
+
+                // Instant effects at the very start are applied to the shape itself
+                if end_frame == 0 {
+                    match effect.kind {
+                        EffectKind::Move(position) => {
+                            let size = rectangle.p2 - rectangle.p1;
+                            rectangle.p1 = position;
+                            rectangle.p2 = position + size;
+                        }
+                        EffectKind::Recolor(color) => rectangle.fill_color = Color::from(color),
+                    }
+                    continue;
+                }
+
+                // A transition reaches its end value strictly after its start frame
+                let start_frame = self.to_frame(effect.start).min(end_frame - 1);
+                let transition = match effect.kind {
+                    EffectKind::Move(end_value) => Transition::Translate(TransitionDescriptor {
+                        end_value,
+                        start_frame,
+                        end_frame,
+                        play_count: 1,
+                        easing_function: effect.easing,
+                        direction: Direction::Normal,
+                    }),
+                    EffectKind::Recolor(end_value) => Transition::Recolor(TransitionDescriptor {
+                        end_value,
+                        start_frame,
+                        end_frame,
+                        play_count: 1,
+                        easing_function: effect.easing,
+                        direction: Direction::Normal,
+                    }),
+                };
+                transitions.push(transition);
+            }
+
+            scene.add_object(TransitionObject {
+                object: Box::new(rectangle),
+                transitions,
+            });
+        }
+
+        scene
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Animation {
+    movement: Option<Point>,
+    /// Optional starting color and ending color
+    color: Option<(Option<Color>, Color)>,
+    duration: f64,
+    /// Delays given by `transition` for a single field, overriding `duration` for it
+    move_duration: Option<f64>,
+    color_duration: Option<f64>,
+    easing: EasingFunction,
+}
+
+impl Animation {
+    fn move_duration(&self) -> f64 {
+        self.move_duration.unwrap_or(self.duration)
+    }
+
+    fn color_duration(&self) -> f64 {
+        self.color_duration.unwrap_or(self.duration)
+    }
+
+    /// Time until the last of its fields is done
+    fn length(&self) -> f64 {
+        let durations = [
+            self.movement.map(|_| self.move_duration()),
+            self.color.map(|_| self.color_duration()),
+        ];
+        durations
+            .into_iter()
+            .flatten()
+            .reduce(f64::max)
+            .unwrap_or(self.duration)
+    }
+}
+
+/// State of a shape while scheduling the sequences
+struct ShapeState {
+    position: Point,
+    color: Color,
+}
+
+/// Compile a parsed script. A program is only returned when no error was found.
+pub fn compile(script: &Script) -> (Option<Program>, Vec<Diagnostic>) {
+    let mut compiler = Compiler {
+        diagnostics: Vec::new(),
+        colors: HashMap::new(),
+        shapes: Vec::new(),
+        shape_indexes: HashMap::new(),
+        animations: HashMap::new(),
+        states: Vec::new(),
+        reverse_colors: HashMap::new(),
+    };
+
+    let settings = compiler.settings(&script.config);
+
+    let mut sequences = Vec::new();
+    for statement in &script.statements {
+        match statement {
+            Statement::Assignment { name, value } => compiler.assignment(name, value),
+            Statement::Shape { name, kind, fields } => compiler.shape(name, kind, fields),
+            Statement::Animation { name, fields } => compiler.animation(name, fields),
+            Statement::Sequence(steps) => sequences.push(steps),
+            Statement::Import { span, .. } => compiler.error(
+                Code::Import,
+                "imports are only available when loading a script from a file",
+                *span,
+            ),
+        }
+    }
+
+    compiler.states = compiler
+        .shapes
+        .iter()
+        .map(|shape| ShapeState {
+            position: shape.rectangle.p1,
+            color: shape.rectangle.fill_color,
+        })
+        .collect();
+
+    // Sequences are played one after the other
+    let mut duration = 0.0;
+    for steps in sequences {
+        let end = compiler.schedule(steps, duration);
+        if end > MAX_DURATION
+            && duration <= MAX_DURATION
+            && let (Some(first), Some(last)) = (steps.first(), steps.last())
+        {
+            compiler.error(
+                Code::Sequence,
+                format!("the animation lasts more than the maximum of {MAX_DURATION}s"),
+                first.span().to(last.span()),
+            );
+        }
+        duration = end;
+    }
+
+    let diagnostics = compiler.diagnostics;
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return (None, diagnostics);
+    }
+
+    let program = Program {
+        settings,
+        shapes: compiler.shapes,
+        duration,
+    };
+
+    (Some(program), diagnostics)
+}
+
+struct Compiler {
+    diagnostics: Vec<Diagnostic>,
+    colors: HashMap<String, Color>,
+    shapes: Vec<CompiledShape>,
+    shape_indexes: HashMap<String, usize>,
+    animations: HashMap<String, Animation>,
+    states: Vec<ShapeState>,
+    /// Colors shapes had before `color <color>` animations, used to reverse them
+    reverse_colors: HashMap<(usize, String), Vec<Color>>,
+}
+
+impl Compiler {
+    fn error(&mut self, code: Code, message: impl Into<String>, span: Span) {
+        self.diagnostics
+            .push(Diagnostic::error(code, message, span));
+    }
+
+    fn settings(&mut self, config: &[ConfigEntry]) -> Settings {
+        let mut settings = Settings::default();
+
+        for entry in config {
+            let span = entry.value_span;
+            match entry.key.as_str() {
+                "format" => {
+                    if entry.value != "gif" {
+                        self.error(
+                            Code::Config,
+                            format!(
+                                "unsupported format `{}`, only `gif` is available",
+                                entry.value
+                            ),
+                            span,
+                        );
+                    }
+                }
+                "filename" => settings.filename = Some(entry.value.clone()),
+                "width" | "height" => {
+                    match entry.value.parse::<usize>() {
+                        Ok(value) if (1..=u16::MAX as usize).contains(&value) => {
+                            if entry.key == "width" {
+                                settings.width = value;
+                            } else {
+                                settings.height = value;
+                            }
+                        }
+                        _ => self.error(
+                            Code::Config,
+                            format!("{} must be a number between 1 and {}", entry.key, u16::MAX),
+                            span,
+                        ),
+                    };
+                }
+                "fps" => match entry.value.parse::<u32>() {
+                    Ok(value) if (1..=MAX_FPS).contains(&value) => settings.fps = value,
+                    _ => self.error(
+                        Code::Config,
+                        format!("fps must be a number between 1 and {MAX_FPS}"),
+                        span,
+                    ),
+                },
+                "background" => match parse_value(&entry.value, span.start) {
+                    Ok(expression) => {
+                        if let Some(color) = self.color(&expression) {
+                            settings.background = color;
+                        }
+                    }
+                    Err(diagnostic) => self.diagnostics.push(diagnostic),
+                },
+                key => self.diagnostics.push(Diagnostic::warning(
+                    Code::Config,
+                    format!("unknown config key `{key}`"),
+                    entry.key_span,
+                )),
+            }
+        }
+
+        settings
+    }
+
+    fn assignment(&mut self, name: &Identifier, value: &Expression) {
+        if self.is_defined(&name.name) {
+            self.error(
+                Code::DuplicateName,
+                format!("`{}` is already defined", name.name),
+                name.span,
+            );
+            return;
+        }
+
+        // `y = x` copies the shape `x`
+        if let Expression::Identifier(target) = value
+            && let Some(&index) = self.shape_indexes.get(&target.name)
+        {
+            let mut shape = self.shapes[index].clone();
+            shape.name = name.name.clone();
+            self.shape_indexes
+                .insert(name.name.clone(), self.shapes.len());
+            self.shapes.push(shape);
+            return;
+        }
+
+        if let Some(color) = self.color(value) {
+            self.colors.insert(name.name.clone(), color);
+        }
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        self.colors.contains_key(name)
+            || self.shape_indexes.contains_key(name)
+            || self.animations.contains_key(name)
+    }
+
+    fn shape(&mut self, name: &Identifier, kind: &Identifier, fields: &[Field]) {
+        if self.is_defined(&name.name) {
+            self.error(
+                Code::DuplicateName,
+                format!("`{}` is already defined", name.name),
+                name.span,
+            );
+            return;
+        }
+        if kind.name != "RECT" {
+            self.error(
+                Code::UnknownName,
+                format!("unknown shape `{}`", kind.name),
+                kind.span,
+            );
+            return;
+        }
+
+        let (mut width, mut height) = (1.0, 1.0);
+        let mut position = Point { x: 0.0, y: 0.0 };
+        let mut fill_color = Color::RGB(255, 255, 255);
+        let mut z_index = 0;
+
+        for field in fields {
+            match field.name.name.as_str() {
+                "width" => width = self.single_length(field).unwrap_or(width),
+                "height" => height = self.single_length(field).unwrap_or(height),
+                "position" => position = self.single_position(field).unwrap_or(position),
+                "color" => {
+                    if let Some(color) = self.single_argument(field).and_then(|e| self.color(e)) {
+                        fill_color = color;
+                    }
+                }
+                "z-index" => {
+                    if let Some(value) = self.single_argument(field).and_then(|e| self.number(e)) {
+                        z_index = value as i32;
+                    }
+                }
+                other => self.error(
+                    Code::UnknownField,
+                    format!("unknown rectangle field `{other}`"),
+                    field.name.span,
+                ),
+            }
+        }
+
+        if width <= 0.0 || height <= 0.0 {
+            self.error(
+                Code::InvalidValue,
+                "rectangle width and height must be positive",
+                name.span,
+            );
+            return;
+        }
+
+        // Rectangles are drawn with both corners included
+        let rectangle = Rectangle {
+            p1: position,
+            p2: position
+                + Point {
+                    x: width - 1.0,
+                    y: height - 1.0,
+                },
+            rotation: Rotation::Turn(0.0),
+            z_index,
+            fill_color,
+            border_color: fill_color,
+            border_size: Length::Pixel(0),
+            outline_color: fill_color,
+            outline_size: Length::Pixel(0),
+        };
+
+        self.shape_indexes
+            .insert(name.name.clone(), self.shapes.len());
+        self.shapes.push(CompiledShape {
+            name: name.name.clone(),
+            rectangle,
+            effects: Vec::new(),
+        });
+    }
+
+    fn animation(&mut self, name: &Identifier, fields: &[Field]) {
+        if self.is_defined(&name.name) {
+            self.error(
+                Code::DuplicateName,
+                format!("`{}` is already defined", name.name),
+                name.span,
+            );
+            return;
+        }
+
+        let mut animation = Animation {
+            movement: None,
+            color: None,
+            duration: DEFAULT_DURATION,
+            move_duration: None,
+            color_duration: None,
+            easing: EasingFunction::Linear,
+        };
+        // Fields given a delay of their own by `transition`
+        let mut field_delays: Vec<(&Identifier, f64)> = Vec::new();
+
+        for field in fields {
+            match field.name.name.as_str() {
+                "move" => animation.movement = self.single_point(field).or(animation.movement),
+                "color" => {
+                    let Some(argument) = self.single_argument(field) else {
+                        continue;
+                    };
+                    let argument = self.color(argument);
+                    let target = field.target.as_ref().and_then(|target| self.color(target));
+
+                    animation.color = match (argument, &field.target, target) {
+                        // `color <start> -> <end>`
+                        (Some(start), Some(_), Some(end)) => Some((Some(start), end)),
+                        // `color <end>`
+                        (Some(end), None, _) => Some((None, end)),
+                        _ => animation.color,
+                    };
+                }
+                "delay" => {
+                    if let Some(duration) =
+                        self.single_argument(field).and_then(|e| self.duration(e))
+                    {
+                        animation.duration = duration;
+                    }
+                }
+                "transition" => {
+                    let [easing, rest @ ..] = field.arguments.as_slice() else {
+                        self.error(Code::InvalidValue, "expected a transition type", field.span);
+                        continue;
+                    };
+                    if let Some(easing) = self.easing(easing) {
+                        animation.easing = easing;
+                    }
+                    match rest {
+                        [] => {}
+                        // `transition <type> <delay>` sets the delay of every field
+                        [duration @ Expression::Number { .. }] => {
+                            if let Some(duration) = self.duration(duration) {
+                                animation.duration = duration;
+                            }
+                        }
+                        groups => {
+                            for group in groups {
+                                if let Some(field_delay) = self.field_delay(group) {
+                                    field_delays.push(field_delay);
+                                }
+                            }
+                        }
+                    }
+                }
+                other => self.error(
+                    Code::UnknownField,
+                    format!("unknown animation field `{other}`"),
+                    field.name.span,
+                ),
+            }
+        }
+
+        for (field, duration) in field_delays {
+            let (animated, field_duration) = match field.name.as_str() {
+                "move" => (animation.movement.is_some(), &mut animation.move_duration),
+                "color" => (animation.color.is_some(), &mut animation.color_duration),
+                other => {
+                    self.error(
+                        Code::UnknownField,
+                        format!("`{other}` has no delay, expected `move` or `color`"),
+                        field.span,
+                    );
+                    continue;
+                }
+            };
+            *field_duration = Some(duration);
+            if !animated {
+                self.diagnostics.push(Diagnostic::warning(
+                    Code::NoEffect,
+                    format!(
+                        "the delay of `{}` has no effect, `{}` does not change it",
+                        field.name, name.name
+                    ),
+                    field.span,
+                ));
+            }
+        }
+
+        if animation.movement.is_none() && animation.color.is_none() {
+            self.diagnostics.push(Diagnostic::warning(
+                Code::NoEffect,
+                format!("animation `{}` has no effect", name.name),
+                name.span,
+            ));
+        }
+
+        self.animations.insert(name.name.clone(), animation);
+    }
+
+    /// Field and delay of `(<field> <delay>)` in a transition
+    fn field_delay<'a>(&mut self, group: &'a Expression) -> Option<(&'a Identifier, f64)> {
+        match group {
+            Expression::Group { items, .. } => match items.as_slice() {
+                [Expression::Identifier(field), delay] => Some((field, self.duration(delay)?)),
+                _ => {
+                    self.error(
+                        Code::InvalidValue,
+                        "expected a field and its delay such as `(color 1s)`",
+                        group.span(),
+                    );
+                    None
+                }
+            },
+            _ => {
+                self.error(
+                    Code::InvalidValue,
+                    "expected a delay such as `2s`, or fields and their delays such as \
+                     `(color 1s)`",
+                    group.span(),
+                );
+                None
+            }
+        }
+    }
+
+    /// Schedule steps one after the other starting at `start`, returns the end time
+    fn schedule(&mut self, steps: &[SequenceStep], start: f64) -> f64 {
+        let mut time = start;
+
+        for step in steps {
+            time = match step {
+                SequenceStep::Apply {
+                    animation,
+                    target,
+                    reversed,
+                    ..
+                } => self.apply(animation, target, *reversed, time),
+                SequenceStep::Concurrent { branches, .. } => {
+                    // Concurrent branches cannot share a shape
+                    let mut used = HashSet::new();
+                    for branch in branches {
+                        for (name, span) in targets(branch) {
+                            if !used.insert(name) {
+                                self.error(
+                                    Code::Sequence,
+                                    format!(
+                                        "`{name}` is animated by multiple concurrent animations"
+                                    ),
+                                    span,
+                                );
+                            }
+                        }
+                    }
+
+                    branches
+                        .iter()
+                        .map(|branch| self.schedule(branch, time))
+                        .fold(time, f64::max)
+                }
+            };
+        }
+
+        time
+    }
+
+    fn apply(
+        &mut self,
+        animation_name: &Identifier,
+        target: &Identifier,
+        reversed: bool,
+        start: f64,
+    ) -> f64 {
+        let Some(animation) = self.animations.get(&animation_name.name).cloned() else {
+            self.error(
+                Code::UnknownName,
+                format!("unknown animation `{}`", animation_name.name),
+                animation_name.span,
+            );
+            return start;
+        };
+        let Some(&index) = self.shape_indexes.get(&target.name) else {
+            self.error(
+                Code::UnknownName,
+                format!("unknown shape `{}`", target.name),
+                target.span,
+            );
+            return start;
+        };
+
+        let end = start + animation.length();
+        let easing = if reversed {
+            reverse_easing(animation.easing)
+        } else {
+            animation.easing
+        };
+
+        let state = &mut self.states[index];
+        let mut effects = Vec::new();
+
+        if let Some(movement) = animation.movement {
+            let movement = if reversed { movement * -1.0 } else { movement };
+            state.position = state.position + movement;
+            effects.push(Effect {
+                start,
+                end: start + animation.move_duration(),
+                kind: EffectKind::Move(state.position),
+                easing,
+            });
+        }
+
+        if let Some((from, to)) = animation.color {
+            let colors = match (from, reversed) {
+                (Some(from), false) => Some((Some(from), to)),
+                (Some(from), true) => Some((Some(to), from)),
+                (None, false) => {
+                    self.reverse_colors
+                        .entry((index, animation_name.name.clone()))
+                        .or_default()
+                        .push(state.color);
+                    Some((None, to))
+                }
+                (None, true) => self
+                    .reverse_colors
+                    .get_mut(&(index, animation_name.name.clone()))
+                    .and_then(|colors| colors.pop())
+                    .map(|previous| (None, previous)),
+            };
+
+            match colors {
+                Some((from, to)) => {
+                    if let Some(from) = from {
+                        effects.push(Effect {
+                            start,
+                            end: start,
+                            kind: EffectKind::Recolor(ColorChannels::from(from)),
+                            easing,
+                        });
+                    }
+                    state.color = to;
+                    effects.push(Effect {
+                        start,
+                        end: start + animation.color_duration(),
+                        kind: EffectKind::Recolor(ColorChannels::from(to)),
+                        easing,
+                    });
+                }
+                None => {
+                    let message = format!(
+                        "cannot reverse `{}` on `{}` before it has been applied",
+                        animation_name.name, target.name
+                    );
+                    self.error(Code::Sequence, message, animation_name.span);
+                }
+            }
+        }
+
+        self.shapes[index].effects.extend(effects);
+
+        end
+    }
+
+    fn single_argument<'a>(&mut self, field: &'a Field) -> Option<&'a Expression> {
+        if let Some(target) = &field.target
+            && field.name.name != "color"
+        {
+            self.error(Code::InvalidValue, "unexpected `->`", target.span());
+            return None;
+        }
+
+        match field.arguments.as_slice() {
+            [argument] => Some(argument),
+            [] => {
+                self.error(
+                    Code::InvalidValue,
+                    format!("expected a value for `{}`", field.name.name),
+                    field.span,
+                );
+                None
+            }
+            [_, extra, ..] => {
+                self.error(Code::InvalidValue, "unexpected value", extra.span());
+                None
+            }
+        }
+    }
+
+    fn number(&mut self, expression: &Expression) -> Option<f64> {
+        match expression {
+            Expression::Number { value, unit, .. }
+                if unit.is_none() || unit.as_deref() == Some("px") =>
+            {
+                Some(*value)
+            }
+            _ => {
+                self.error(Code::InvalidValue, "expected a number", expression.span());
+                None
+            }
+        }
+    }
+
+    fn single_length(&mut self, field: &Field) -> Option<f64> {
+        let argument = self.single_argument(field)?;
+        self.number(argument)
+    }
+
+    /// Movement given as `[x, y]`
+    fn single_point(&mut self, field: &Field) -> Option<Point> {
+        let (x, y) = self.single_pair(field, "a movement such as `[x, y]`")?;
+        Some(Point { x, y })
+    }
+
+    /// Top left corner of a shape, given as `[top, left]`
+    fn single_position(&mut self, field: &Field) -> Option<Point> {
+        let (top, left) = self.single_pair(field, "a position such as `[top, left]`")?;
+        Some(Point { x: left, y: top })
+    }
+
+    fn single_pair(&mut self, field: &Field, expected: &str) -> Option<(f64, f64)> {
+        let argument = self.single_argument(field)?;
+        match argument {
+            Expression::List { items, .. } if items.len() == 2 => {
+                let first = self.number(&items[0]);
+                let second = self.number(&items[1]);
+                Some((first?, second?))
+            }
+            _ => {
+                self.error(
+                    Code::InvalidValue,
+                    format!("expected {expected}"),
+                    argument.span(),
+                );
+                None
+            }
+        }
+    }
+
+    fn duration(&mut self, expression: &Expression) -> Option<f64> {
+        match expression {
+            Expression::Number {
+                value,
+                unit: Some(unit),
+                ..
+            } if (unit == "s" || unit == "ms") && *value >= 0.0 => {
+                let seconds = if unit == "ms" { value / 1000.0 } else { *value };
+                if seconds > MAX_DURATION {
+                    self.error(
+                        Code::InvalidValue,
+                        format!("durations are limited to {MAX_DURATION}s"),
+                        expression.span(),
+                    );
+                    return None;
+                }
+                Some(seconds)
+            }
+            _ => {
+                self.error(
+                    Code::InvalidValue,
+                    "expected a duration such as `2s` or `500ms`",
+                    expression.span(),
+                );
+                None
+            }
+        }
+    }
+
+    fn easing(&mut self, expression: &Expression) -> Option<EasingFunction> {
+        let easing = match expression {
+            Expression::Identifier(identifier) => match identifier.name.as_str() {
+                "linear" => Some(EasingFunction::Linear),
+                "ease" => Some(EasingFunction::CubicBezier(0.25, 0.1, 0.25, 1.0)),
+                "ease-in" => Some(EasingFunction::CubicBezier(0.42, 0.0, 1.0, 1.0)),
+                "ease-out" => Some(EasingFunction::CubicBezier(0.0, 0.0, 0.58, 1.0)),
+                "ease-in-out" => Some(EasingFunction::CubicBezier(0.42, 0.0, 0.58, 1.0)),
+                "step-start" => Some(EasingFunction::Steps(1, StepJump::JumpStart)),
+                "step-end" => Some(EasingFunction::Steps(1, StepJump::JumpEnd)),
+                _ => None,
+            },
+            Expression::Call {
+                name, arguments, ..
+            } if name.name == "cubic-bezier" && arguments.len() == 4 => {
+                let values: Vec<Option<f64>> = arguments.iter().map(|a| self.number(a)).collect();
+                match values.as_slice() {
+                    [Some(x1), Some(y1), Some(x2), Some(y2)] => {
+                        Some(EasingFunction::CubicBezier(*x1, *y1, *x2, *y2))
+                    }
+                    _ => return None,
+                }
+            }
+            _ => None,
+        };
+
+        if easing.is_none() {
+            self.error(
+                Code::InvalidValue,
+                "unknown transition type, expected `linear`, `ease`, `ease-in`, `ease-out`, \
+                 `ease-in-out`, `step-start`, `step-end` or `cubic-bezier(x1, y1, x2, y2)`",
+                expression.span(),
+            );
+        }
+
+        easing
+    }
+
+    fn color(&mut self, expression: &Expression) -> Option<Color> {
+        match expression {
+            Expression::HexColor { value, .. } => Some(Color::HEX(*value)),
+            Expression::Identifier(identifier) => {
+                let name = &identifier.name;
+                let color = self.colors.get(name).copied().or_else(|| {
+                    CONSTANT_COLORS
+                        .iter()
+                        .find(|(constant, _)| constant.eq_ignore_ascii_case(name))
+                        .map(|(_, color)| *color)
+                });
+
+                if color.is_none() {
+                    self.error(
+                        Code::UnknownName,
+                        format!("unknown color `{name}`"),
+                        identifier.span,
+                    );
+                }
+                color
+            }
+            Expression::Call {
+                name,
+                arguments,
+                span,
+            } => {
+                let expected = match name.name.as_str() {
+                    "rgb" | "hsl" => 3,
+                    "rgba" | "hsla" => 4,
+                    other => {
+                        self.error(
+                            Code::UnknownName,
+                            format!("unknown color function `{other}`"),
+                            name.span,
+                        );
+                        return None;
+                    }
+                };
+                if arguments.len() != expected {
+                    self.error(
+                        Code::InvalidValue,
+                        format!("`{}` expects {expected} arguments", name.name),
+                        *span,
+                    );
+                    return None;
+                }
+
+                let mut components = Vec::with_capacity(expected);
+                for argument in arguments {
+                    let value = self.number(argument)?;
+                    let maximum = if name.name.starts_with("rgb") {
+                        255.0
+                    } else {
+                        360.0
+                    };
+                    if !(0.0..=maximum).contains(&value) {
+                        self.error(
+                            Code::InvalidValue,
+                            format!("color component must be between 0 and {maximum}"),
+                            argument.span(),
+                        );
+                        return None;
+                    }
+                    components.push(value as u32);
+                }
+
+                let c = components;
+                Some(match name.name.as_str() {
+                    "rgb" => Color::RGB(c[0] as u8, c[1] as u8, c[2] as u8),
+                    "rgba" => Color::RGBA(c[0] as u8, c[1] as u8, c[2] as u8, c[3] as u8),
+                    "hsl" => Color::HSL(c[0], c[1], c[2]),
+                    _ => Color::HSLA(c[0], c[1], c[2], c[3]),
+                })
+            }
+            _ => {
+                self.error(Code::InvalidValue, "expected a color", expression.span());
+                None
+            }
+        }
+    }
+}
+
+/// Names of the shapes animated by the steps
+fn targets(steps: &[SequenceStep]) -> Vec<(&str, Span)> {
+    let mut names = Vec::new();
+    let mut seen = HashSet::new();
+
+    for step in steps {
+        match step {
+            SequenceStep::Apply { target, .. } => {
+                if seen.insert(target.name.as_str()) {
+                    names.push((target.name.as_str(), target.span));
+                }
+            }
+            SequenceStep::Concurrent { branches, .. } => {
+                for branch in branches {
+                    for (name, span) in targets(branch) {
+                        if seen.insert(name) {
+                            names.push((name, span));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    names
+}
+
+/// Progress from `low` to `high` at which the interpolation from `from` to `to` gives each
+/// of the colors it goes through, found where a channel rounds to the next value
+fn interpolation_steps(
+    from: ColorChannels,
+    to: ColorChannels,
+    (low, high): (f64, f64),
+) -> Vec<f64> {
+    let mut bounds = vec![low, high];
+    for (from, to) in [
+        (from.r, to.r),
+        (from.g, to.g),
+        (from.b, to.b),
+        (from.a, to.a),
+    ] {
+        if from == to {
+            continue;
+        }
+        bounds.extend(
+            (0..255)
+                .map(|value| (f64::from(value) + 0.5 - from) / (to - from))
+                .filter(|&progress| low < progress && progress < high),
+        );
+    }
+    bounds.sort_by(f64::total_cmp);
+    // Channels rounding at the same progress, up to the precision of the division
+    bounds.dedup_by(|next, bound| *next - *bound < 1e-9);
+
+    // Both ends, and between two bounds where every channel keeps its value
+    let middles: Vec<f64> = bounds
+        .windows(2)
+        .map(|pair| (pair[0] + pair[1]) / 2.0)
+        .collect();
+    [low, high].into_iter().chain(middles).collect()
+}
+
+/// Easing of an animation played backward (eg. `ease-in` becomes `ease-out`)
+fn reverse_easing(easing: EasingFunction) -> EasingFunction {
+    match easing {
+        EasingFunction::Linear => EasingFunction::Linear,
+        EasingFunction::CubicBezier(x1, y1, x2, y2) => {
+            EasingFunction::CubicBezier(1.0 - x2, 1.0 - y2, 1.0 - x1, 1.0 - y1)
+        }
+        EasingFunction::Steps(count, jump) => EasingFunction::Steps(
+            count,
+            match jump {
+                StepJump::JumpStart => StepJump::JumpEnd,
+                StepJump::JumpEnd => StepJump::JumpStart,
+                other => other,
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{animator::scene::Frame, script::parser::parse};
+
+    fn compile_source(source: &str) -> (Option<Program>, Vec<Diagnostic>) {
+        let (script, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        compile(&script)
+    }
+
+    fn compile_ok(source: &str) -> Program {
+        let (program, diagnostics) = compile_source(source);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        program.unwrap()
+    }
+
+    const SCRIPT: &str = "\
+---
+width: 20
+height: 10
+fps: 10
+---
+
+to_red ANIM
+    color red
+    delay 1s
+
+slide ANIM
+    move [5, 0]
+    transition ease-in 500ms
+
+r1 RECT
+    width 4
+    height 2
+    position [1, 2]
+    color blue
+
+r2 = r1
+
+to_red(r1) -> [slide(r1), slide(r2)] -> 'slide(r2)
+";
+
+    #[test]
+    fn compile_settings_test() {
+        let program = compile_ok(SCRIPT);
+
+        assert_eq!(program.settings.width, 20);
+        assert_eq!(program.settings.height, 10);
+        assert_eq!(program.settings.fps, 10);
+    }
+
+    #[test]
+    fn compile_schedule_test() {
+        let program = compile_ok(SCRIPT);
+
+        assert_eq!(program.duration, 2.0);
+        assert_eq!(program.frame_count(), 21);
+        assert_eq!(program.shapes.len(), 2);
+
+        let r1 = &program.shapes[0];
+        assert_eq!(r1.effects.len(), 2);
+        assert_eq!((r1.effects[1].start, r1.effects[1].end), (1.0, 1.5));
+
+        let r2 = &program.shapes[1];
+        let EffectKind::Move(position) = r2.effects[1].kind else {
+            panic!("expected a move");
+        };
+        assert_eq!((position.x, position.y), (2.0, 1.0));
+    }
+
+    #[test]
+    fn compile_scene_test() {
+        let frames = compile_ok(SCRIPT).scene().render();
+
+        assert_eq!(frames.len(), 21);
+        assert_eq!(frames[0].buffer[1][2].to_rgba(), [0, 0, 255, 255]);
+        // Both rectangles moved 5 pixels to the right, r2 being drawn above r1
+        assert_eq!(frames[15].buffer[1][2].to_rgba(), [0, 0, 0, 0]);
+        assert_eq!(frames[15].buffer[1][7].to_rgba(), [0, 0, 255, 255]);
+        // r2 moved back on top of its starting position while r1 stayed in place
+        assert_eq!(frames[20].buffer[1][2].to_rgba(), [0, 0, 255, 255]);
+        assert_eq!(frames[20].buffer[1][10].to_rgba(), [255, 0, 0, 255]);
+
+        // A single frame is the same as when rendering the whole animation
+        let frame = compile_ok(SCRIPT).scene().render_frame(15);
+        let rgba = |frame: &Frame| -> Vec<[u8; 4]> {
+            frame.buffer.iter().flatten().map(Color::to_rgba).collect()
+        };
+        assert_eq!(rgba(&frame), rgba(&frames[15]));
+    }
+
+    #[test]
+    fn compile_estimated_color_count_test() {
+        let program = compile_ok(
+            "---\nfps: 10\n---\nfade ANIM\n    color red\n    delay 1s\nr1 RECT\n    color blue\nfade(r1)\n",
+        );
+
+        let mut colors = HashSet::new();
+        for frame in program.scene().render() {
+            for color in frame.buffer.iter().flatten() {
+                let pixel = blend(*color, program.settings.background);
+                colors.insert([pixel.r, pixel.g, pixel.b]);
+            }
+        }
+
+        // Background, blue, red and the 9 colors in between
+        assert_eq!(colors.len(), 12);
+        assert_eq!(program.estimated_color_count(), 12);
+
+        // Shapes hidden behind others are still counted
+        assert_eq!(compile_ok(SCRIPT).estimated_color_count(), 12);
+        // A recolor over billions of frames goes through the 256 colors from blue to red
+        // once, counted without going through its frames
+        for easing in ["linear", "ease-in-out"] {
+            let program = compile_ok(&format!(
+                "---\nfps: 100\n---\nfade ANIM\n    color red\n    delay 40000000s\n    \
+                 transition {easing}\nr1 RECT\n    color blue\nfade(r1)\n"
+            ));
+            assert_eq!(program.estimated_color_count(), 257);
+        }
+    }
+
+    #[test]
+    fn compile_errors_test() {
+        let (program, diagnostics) = compile_source(
+            "a ANIM\n    move [1, 1]\n\nr RECT\n    width ten\n\n[a(r), a(r)] -> b(r) -> a(s)\n",
+        );
+
+        assert!(program.is_none());
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "expected a number",
+                "`r` is animated by multiple concurrent animations",
+                "unknown animation `b`",
+                "unknown shape `s`",
+            ]
+        );
+        let codes: Vec<Code> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                Code::InvalidValue,
+                Code::Sequence,
+                Code::UnknownName,
+                Code::UnknownName
+            ]
+        );
+    }
+
+    #[test]
+    fn compile_duration_limit_test() {
+        let (program, diagnostics) =
+            compile_source("a ANIM\n    move [1, 1]\n    delay 99999999999s\n\nr RECT\n\na(r)\n");
+        assert!(program.is_none());
+        assert_eq!(diagnostics[0].code, Code::InvalidValue);
+
+        // Durations within the limit that add up past it
+        let half = MAX_DURATION / 2.0 + 1.0;
+        let (program, diagnostics) = compile_source(&format!(
+            "a ANIM\n    move [1, 1]\n    delay {half}s\n\nr RECT\n\na(r) -> 'a(r)\na(r)\n"
+        ));
+        assert!(program.is_none());
+        let codes: Vec<Code> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec![Code::Sequence]);
+    }
+
+    #[test]
+    fn compile_transition_delays_test() {
+        let program = compile_ok(
+            "a ANIM\n    color red\n    move [2, 0]\n    delay 2s\n    \
+             transition ease (color 1s) (move 500ms)\n\nr RECT\n\na(r) -> a(r)\n",
+        );
+        let ends: Vec<f64> = program.shapes[0]
+            .effects
+            .iter()
+            .map(|effect| effect.end)
+            .collect();
+        assert_eq!(ends, vec![0.5, 1.0, 1.5, 2.0]);
+        // The animation lasts as long as its longest field
+        assert_eq!(program.duration, 2.0);
+
+        let (program, diagnostics) = compile_source(
+            "a ANIM\n    color red\n    transition ease (move 1s) (size 1s) (color)\n\n\
+             r RECT\n\na(r)\n",
+        );
+        assert!(program.is_none());
+        let codes: Vec<Code> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            vec![Code::InvalidValue, Code::NoEffect, Code::UnknownField]
+        );
+    }
+
+    #[test]
+    fn compile_reverse_color_before_apply_test() {
+        let (program, diagnostics) = compile_source("a ANIM\n    color red\n\nr RECT\n\n'a(r)\n");
+
+        assert!(program.is_none());
+        assert_eq!(diagnostics.len(), 1);
+    }
+}
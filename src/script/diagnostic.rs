@@ -0,0 +1,191 @@
+use std::fmt::Display;
+
+/// Byte range inside of a script source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    pub fn shift(self, offset: usize) -> Self {
+        Span {
+            start: self.start + offset,
+            end: self.end + offset,
+        }
+    }
+
+    /// Smallest span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Self {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Kind of problem a diagnostic reports. Its name is stable, so that tools can rely on it
+/// rather than on the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    /// Invalid token or statement
+    Syntax,
+    /// Invalid or unknown config entry
+    Config,
+    /// Name declared twice
+    DuplicateName,
+    /// Shape, animation, color or function that does not exist
+    UnknownName,
+    /// Field that does not exist on a shape or an animation
+    UnknownField,
+    /// Value of the wrong kind or out of range
+    InvalidValue,
+    /// Animation that changes nothing
+    NoEffect,
+    /// Invalid animation sequence
+    Sequence,
+    /// Import that cannot be resolved
+    Import,
+    /// Part of the language that is not implemented yet
+    Unsupported,
+}
+
+impl Code {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Code::Syntax => "syntax",
+            Code::Config => "config",
+            Code::DuplicateName => "duplicate-name",
+            Code::UnknownName => "unknown-name",
+            Code::UnknownField => "unknown-field",
+            Code::InvalidValue => "invalid-value",
+            Code::NoEffect => "no-effect",
+            Code::Sequence => "sequence",
+            Code::Import => "import",
+            Code::Unsupported => "unsupported",
+        }
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Code,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn error(code: Code, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+
+    pub fn warning(code: Code, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Format the diagnostic as `filename:line:column: severity: message` followed by
+    /// the offending line of the source and a marker under the span.
+    pub fn display(&self, filename: &str, source: &str) -> String {
+        let (line, column) = line_column(source, self.span.start);
+        let line_start = self.span.start.min(source.len()) - (column - 1);
+        let line_content = source[line_start..].lines().next().unwrap_or("");
+
+        let marker_width = (self.span.end.saturating_sub(self.span.start))
+            .clamp(1, line_content.len().saturating_sub(column - 1).max(1));
+
+        format!(
+            "{filename}:{line}:{column}: {}: {}\n{line_content}\n{}{}",
+            self.severity,
+            self.message,
+            " ".repeat(column - 1),
+            "^".repeat(marker_width),
+        )
+    }
+}
+
+/// 1-based line and column of a byte offset
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+
+    let line = before.matches('\n').count() + 1;
+    let column = match before.rfind('\n') {
+        Some(index) => offset - index,
+        None => offset + 1,
+    };
+
+    (line, column)
+}
+
+/// Whether any of the diagnostics is an error
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(Diagnostic::is_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_column_test() {
+        let source = "ab\ncd\n\nef";
+
+        assert_eq!(line_column(source, 0), (1, 1));
+        assert_eq!(line_column(source, 1), (1, 2));
+        assert_eq!(line_column(source, 3), (2, 1));
+        assert_eq!(line_column(source, 7), (4, 1));
+        assert_eq!(line_column(source, 100), (4, 3));
+    }
+
+    #[test]
+    fn diagnostic_display_test() {
+        let source = "r1 RECT\n    width ten\n";
+        let diagnostic =
+            Diagnostic::error(Code::InvalidValue, "expected a number", Span::new(18, 21));
+
+        assert_eq!(
+            diagnostic.display("test.gas", source),
+            "test.gas:2:11: error: expected a number\n    width ten\n          ^^^"
+        );
+    }
+}
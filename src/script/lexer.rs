@@ -0,0 +1,307 @@
+use super::diagnostic::{Code, Diagnostic, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Identifier(String),
+    /// Number with an optional unit directly attached to it (eg. `2s`, `500ms`)
+    Number {
+        value: f64,
+        unit: Option<String>,
+    },
+    HexColor(u32),
+    String(String),
+    Equal,
+    Arrow,
+    Comma,
+    Quote,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Newline,
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+    /// Whether the token is the first of its line and is preceded by whitespace
+    pub indented: bool,
+}
+
+pub struct Lexer<'a> {
+    source: &'a str,
+    /// Offset of `source` inside of the whole script
+    offset: usize,
+    position: usize,
+    line_start: bool,
+    indented: bool,
+    tokens: Vec<Token>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Lexer<'a> {
+    /// Create a lexer over `source`, which starts at byte `offset` of the script. This
+    /// keeps spans relative to the whole file when skipping the config block.
+    pub fn new(source: &'a str, offset: usize) -> Self {
+        Lexer {
+            source,
+            offset,
+            position: 0,
+            line_start: true,
+            indented: false,
+            tokens: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn tokenize(mut self) -> (Vec<Token>, Vec<Diagnostic>) {
+        while let Some(c) = self.peek() {
+            let start = self.position;
+
+            match c {
+                '\n' => {
+                    self.position += 1;
+                    // Consecutive and leading newlines carry no information
+                    if !self.line_start {
+                        self.push(TokenKind::Newline, start);
+                    }
+                    self.line_start = true;
+                    self.indented = false;
+                    continue;
+                }
+                ' ' | '\t' | '\r' => {
+                    self.position += 1;
+                    if self.line_start {
+                        self.indented = true;
+                    }
+                    continue;
+                }
+                '/' if self.source[self.position..].starts_with("//") => {
+                    self.skip_while(|c| c != '\n');
+                    continue;
+                }
+                '=' => self.single(TokenKind::Equal),
+                ',' => self.single(TokenKind::Comma),
+                '\'' => self.single(TokenKind::Quote),
+                '(' => self.single(TokenKind::LeftParen),
+                ')' => self.single(TokenKind::RightParen),
+                '[' => self.single(TokenKind::LeftBracket),
+                ']' => self.single(TokenKind::RightBracket),
+                '-' if self.source[self.position..].starts_with("->") => {
+                    self.position += 2;
+                    self.push(TokenKind::Arrow, start);
+                }
+                '-' if self
+                    .peek_nth(1)
+                    .is_some_and(|c| c.is_ascii_digit() || c == '.') =>
+                {
+                    self.position += 1;
+                    self.number(start);
+                }
+                '#' => self.hex_color(start),
+                '"' => self.string(start),
+                c if c.is_ascii_digit() || c == '.' => self.number(start),
+                c if c.is_alphabetic() || c == '_' => self.identifier(start),
+                c => {
+                    self.position += c.len_utf8();
+                    self.diagnostics.push(Diagnostic::error(
+                        Code::Syntax,
+                        format!("unexpected character `{c}`"),
+                        self.span(start),
+                    ));
+                }
+            }
+
+            self.line_start = false;
+        }
+
+        if !self.line_start {
+            self.push(TokenKind::Newline, self.position);
+        }
+        self.push(TokenKind::Eof, self.position);
+
+        (self.tokens, self.diagnostics)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.source[self.position..].chars().nth(n)
+    }
+
+    fn skip_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while let Some(c) = self.peek() {
+            if !predicate(c) {
+                break;
+            }
+            self.position += c.len_utf8();
+        }
+    }
+
+    fn span(&self, start: usize) -> Span {
+        Span::new(self.offset + start, self.offset + self.position)
+    }
+
+    fn push(&mut self, kind: TokenKind, start: usize) {
+        let indented = self.line_start && self.indented;
+        self.tokens.push(Token {
+            kind,
+            span: self.span(start),
+            indented,
+        });
+    }
+
+    fn single(&mut self, kind: TokenKind) {
+        let start = self.position;
+        self.position += 1;
+        self.push(kind, start);
+    }
+
+    fn identifier(&mut self, start: usize) {
+        // Hyphens are part of identifiers when followed by a letter (eg. `ease-in`)
+        loop {
+            self.skip_while(|c| c.is_alphanumeric() || c == '_');
+
+            let continues =
+                self.peek() == Some('-') && self.peek_nth(1).is_some_and(|c| c.is_alphabetic());
+            if !continues {
+                break;
+            }
+            self.position += 1;
+        }
+
+        let name = self.source[start..self.position].to_string();
+        self.push(TokenKind::Identifier(name), start);
+    }
+
+    fn number(&mut self, start: usize) {
+        self.skip_while(|c| c.is_ascii_digit() || c == '.');
+        let number_end = self.position;
+
+        self.skip_while(|c| c.is_alphabetic() || c == '%');
+        let unit = &self.source[number_end..self.position];
+        let unit = (!unit.is_empty()).then(|| unit.to_string());
+
+        match self.source[start..number_end].parse::<f64>() {
+            Ok(value) => self.push(TokenKind::Number { value, unit }, start),
+            Err(_) => self.diagnostics.push(Diagnostic::error(
+                Code::Syntax,
+                format!("invalid number `{}`", &self.source[start..number_end]),
+                self.span(start),
+            )),
+        }
+    }
+
+    fn hex_color(&mut self, start: usize) {
+        self.position += 1;
+        self.skip_while(|c| c.is_ascii_alphanumeric());
+
+        let digits = &self.source[start + 1..self.position];
+        match u32::from_str_radix(digits, 16) {
+            Ok(value) if digits.len() == 6 => self.push(TokenKind::HexColor(value), start),
+            _ => self.diagnostics.push(Diagnostic::error(
+                Code::Syntax,
+                format!("invalid hexadecimal color `#{digits}`, expected 6 hexadecimal digits"),
+                self.span(start),
+            )),
+        }
+    }
+
+    fn string(&mut self, start: usize) {
+        self.position += 1;
+        self.skip_while(|c| c != '"' && c != '\n');
+
+        if self.peek() == Some('"') {
+            let value = self.source[start + 1..self.position].to_string();
+            self.position += 1;
+            self.push(TokenKind::String(value), start);
+        } else {
+            self.diagnostics.push(Diagnostic::error(
+                Code::Syntax,
+                "unterminated string",
+                self.span(start),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        let (tokens, diagnostics) = Lexer::new(source, 0).tokenize();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        tokens.into_iter().map(|token| token.kind).collect()
+    }
+
+    fn identifier(name: &str) -> TokenKind {
+        TokenKind::Identifier(name.to_string())
+    }
+
+    #[test]
+    fn lexer_field_test() {
+        assert_eq!(
+            kinds("    color red -> #00FF00 // comment"),
+            vec![
+                identifier("color"),
+                identifier("red"),
+                TokenKind::Arrow,
+                TokenKind::HexColor(0x00FF00),
+                TokenKind::Newline,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_number_test() {
+        assert_eq!(
+            kinds("move [-2, 1.5] 500ms ease-in"),
+            vec![
+                identifier("move"),
+                TokenKind::LeftBracket,
+                TokenKind::Number {
+                    value: -2.0,
+                    unit: None
+                },
+                TokenKind::Comma,
+                TokenKind::Number {
+                    value: 1.5,
+                    unit: None
+                },
+                TokenKind::RightBracket,
+                TokenKind::Number {
+                    value: 500.0,
+                    unit: Some("ms".to_string())
+                },
+                identifier("ease-in"),
+                TokenKind::Newline,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_indentation_test() {
+        let (tokens, _) = Lexer::new("\n\nr1 RECT\n  width 10\n", 0).tokenize();
+        let indented: Vec<bool> = tokens.iter().map(|token| token.indented).collect();
+
+        // r1, RECT, newline, width, 10, newline, eof
+        assert_eq!(
+            indented,
+            vec![false, false, false, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn lexer_invalid_character_test() {
+        let (_, diagnostics) = Lexer::new("a ? b", 0).tokenize();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Span::new(2, 3));
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    ast::{Script, Statement},
+    compiler::{Program, compile},
+    diagnostic::{Code, Diagnostic},
+    parser::parse_file,
+    source::SourceMap,
+};
+
+/// Script loaded from the filesystem along with every file it imports
+pub struct Loaded {
+    pub sources: SourceMap,
+    /// Only present when no error was found
+    pub program: Option<Program>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Imported files that could not be read, eg. not created yet
+    pub unreadable_imports: Vec<PathBuf>,
+}
+
+/// Read, parse and compile a script and its imports
+pub fn load(path: &Path) -> io::Result<Loaded> {
+    let source = fs::read_to_string(path)?;
+    Ok(load_source(path.to_path_buf(), source))
+}
+
+/// Parse and compile a script whose content was already read. Imports are resolved
+/// relative to `path`.
+pub fn load_source(path: PathBuf, source: String) -> Loaded {
+    let mut loader = Loader {
+        sources: SourceMap::new(),
+        diagnostics: Vec::new(),
+        stack: Vec::new(),
+        imported: HashSet::new(),
+        unreadable_imports: Vec::new(),
+    };
+
+    let script = loader.file(path, source);
+    let (program, diagnostics) = compile(&script);
+    loader.diagnostics.extend(diagnostics);
+
+    let program = program.filter(|_| !loader.diagnostics.iter().any(Diagnostic::is_error));
+
+    Loaded {
+        sources: loader.sources,
+        program,
+        diagnostics: loader.diagnostics,
+        unreadable_imports: loader.unreadable_imports,
+    }
+}
+
+struct Loader {
+    sources: SourceMap,
+    diagnostics: Vec<Diagnostic>,
+    /// Files currently being imported, used to detect cycles
+    stack: Vec<PathBuf>,
+    /// Files already imported, which are only imported once
+    imported: HashSet<PathBuf>,
+    unreadable_imports: Vec<PathBuf>,
+}
+
+impl Loader {
+    /// Parse a file, replacing its imports by the statements of the imported files
+    fn file(&mut self, path: PathBuf, source: String) -> Script {
+        let offset = self.sources.add(path.clone(), source);
+        let source = &self.sources.files().last().unwrap().source;
+
+        let (script, diagnostics) = parse_file(source, offset);
+        self.diagnostics.extend(diagnostics);
+
+        let identity = canonical(&path);
+        self.imported.insert(identity.clone());
+        self.stack.push(identity);
+
+        let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut statements = Vec::with_capacity(script.statements.len());
+
+        for statement in script.statements {
+            let Statement::Import { path, span } = statement else {
+                statements.push(statement);
+                continue;
+            };
+
+            let import_path = directory.join(&path);
+            let identity = canonical(&import_path);
+            if self.stack.contains(&identity) {
+                self.diagnostics.push(Diagnostic::error(
+                    Code::Import,
+                    format!("`{path}` imports itself"),
+                    span,
+                ));
+                continue;
+            }
+            if self.imported.contains(&identity) {
+                continue;
+            }
+
+            match fs::read_to_string(&import_path) {
+                Ok(source) => {
+                    let imported = self.file(import_path, source);
+                    if let Some(entry) = imported.config.first() {
+                        self.diagnostics.push(Diagnostic::warning(
+                            Code::Config,
+                            "config of imported files is ignored",
+                            entry.key_span,
+                        ));
+                    }
+                    statements.extend(imported.statements);
+                }
+                Err(error) => {
+                    self.diagnostics.push(Diagnostic::error(
+                        Code::Import,
+                        format!("cannot import `{path}`: {error}"),
+                        span,
+                    ));
+                    self.unreadable_imports.push(import_path);
+                }
+            }
+        }
+
+        self.stack.pop();
+
+        Script {
+            config: script.config,
+            statements,
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_files(directory: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(directory);
+        fs::create_dir_all(&directory).unwrap();
+        for (name, content) in files {
+            fs::write(directory.join(name), content).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn load_imports_test() {
+        let directory = write_files(
+            "gas_load_imports_test",
+            &[
+                (
+                    "main.gas",
+                    "import \"shapes.gas\"\nimport \"colors.gas\"\n\nslide(r1)\n",
+                ),
+                (
+                    "shapes.gas",
+                    "import \"colors.gas\"\n\nr1 RECT\n    color orange\n\nslide ANIM\n    move [1, 0]\n",
+                ),
+                ("colors.gas", "orange = rgb(255, 165, 0)\n"),
+            ],
+        );
+
+        let loaded = load(&directory.join("main.gas")).unwrap();
+
+        assert!(loaded.diagnostics.is_empty(), "{:?}", loaded.diagnostics);
+        assert_eq!(loaded.sources.files().len(), 3);
+        assert_eq!(loaded.program.unwrap().shapes.len(), 1);
+    }
+
+    #[test]
+    fn load_import_errors_test() {
+        let directory = write_files(
+            "gas_load_import_errors_test",
+            &[
+                ("main.gas", "import \"missing.gas\"\nimport \"cycle.gas\"\n"),
+                ("cycle.gas", "import \"main.gas\"\n"),
+            ],
+        );
+
+        let loaded = load(&directory.join("main.gas")).unwrap();
+        let messages: Vec<String> = loaded
+            .diagnostics
+            .iter()
+            .map(|diagnostic| loaded.sources.display(diagnostic))
+            .collect();
+
+        assert!(loaded.program.is_none());
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("main.gas:1:1: error: cannot import `missing.gas`"));
+        assert!(messages[1].contains("cycle.gas:1:1: error: `main.gas` imports itself"));
+        assert_eq!(
+            loaded.unreadable_imports,
+            vec![directory.join("missing.gas")]
+        );
+    }
+}
@@ -0,0 +1,24 @@
+pub mod ast;
+pub mod compiler;
+pub mod diagnostic;
+pub mod lexer;
+pub mod loader;
+pub mod parser;
+pub mod source;
+
+use compiler::{Program, compile};
+use diagnostic::Diagnostic;
+use parser::parse;
+
+/// Parse and compile a script, the program is only returned when no error was found
+pub fn compile_source(source: &str) -> (Option<Program>, Vec<Diagnostic>) {
+    let (script, mut diagnostics) = parse(source);
+    let (program, compile_diagnostics) = compile(&script);
+    diagnostics.extend(compile_diagnostics);
+
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return (None, diagnostics);
+    }
+
+    (program, diagnostics)
+}
@@ -0,0 +1,682 @@
+use super::{
+    ast::{ConfigEntry, Expression, Field, Identifier, Script, SequenceStep, Statement},
+    diagnostic::{Code, Diagnostic, Span},
+    lexer::{Lexer, Token, TokenKind},
+};
+
+const CONFIG_DELIMITER: &str = "---";
+
+pub const SHAPE_KEYWORDS: [&str; 1] = ["RECT"];
+pub const ANIMATION_KEYWORD: &str = "ANIM";
+
+fn is_keyword(name: &str) -> bool {
+    name == ANIMATION_KEYWORD || SHAPE_KEYWORDS.contains(&name)
+}
+
+pub const IMPORT_KEYWORD: &str = "import";
+
+/// Parse a whole script. Parsing does not stop at the first error, every statement that
+/// could be parsed is returned alongside the diagnostics of the ones that could not.
+pub fn parse(source: &str) -> (Script, Vec<Diagnostic>) {
+    parse_file(source, 0)
+}
+
+/// Parse a script whose spans start at `offset`, see `SourceMap`
+pub fn parse_file(source: &str, offset: usize) -> (Script, Vec<Diagnostic>) {
+    let (mut config, body_start, mut diagnostics) = parse_config(source);
+    for entry in &mut config {
+        entry.key_span = entry.key_span.shift(offset);
+        entry.value_span = entry.value_span.shift(offset);
+    }
+    for diagnostic in &mut diagnostics {
+        diagnostic.span = diagnostic.span.shift(offset);
+    }
+
+    let (tokens, lexer_diagnostics) =
+        Lexer::new(&source[body_start..], offset + body_start).tokenize();
+    diagnostics.extend(lexer_diagnostics);
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        diagnostics,
+    };
+    let statements = parser.statements();
+
+    (Script { config, statements }, parser.diagnostics)
+}
+
+/// Parse a single value, such as the value of a config entry found at byte `offset`
+pub fn parse_value(value: &str, offset: usize) -> Result<Expression, Diagnostic> {
+    let (tokens, diagnostics) = Lexer::new(value, offset).tokenize();
+    if let Some(diagnostic) = diagnostics.into_iter().next() {
+        return Err(diagnostic);
+    }
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        diagnostics: Vec::new(),
+    };
+    let expression = parser.expression()?;
+    parser.expect_end_of_line()?;
+
+    Ok(expression)
+}
+
+/// Parse the config block at the start of the file, if any. Returns the entries and the
+/// byte offset at which the rest of the script starts.
+fn parse_config(source: &str) -> (Vec<ConfigEntry>, usize, Vec<Diagnostic>) {
+    let mut entries = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    let mut lines = source.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line))
+    });
+
+    // Config block must be the first non empty line
+    let opening = lines.by_ref().find(|(_, line)| !line.trim().is_empty());
+    let Some((opening_start, opening_line)) = opening else {
+        return (entries, 0, diagnostics);
+    };
+    if opening_line.trim() != CONFIG_DELIMITER {
+        return (entries, 0, diagnostics);
+    }
+
+    for (start, line) in lines {
+        let trimmed = line.trim();
+        if trimmed == CONFIG_DELIMITER {
+            return (entries, start + line.len(), diagnostics);
+        }
+        if trimmed.is_empty() || trimmed.starts_with("//") || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let line_start = start + (line.len() - line.trim_start().len());
+        let Some((key, value)) = trimmed.split_once(':') else {
+            diagnostics.push(Diagnostic::error(
+                Code::Syntax,
+                "expected `key: value` in config",
+                Span::new(line_start, line_start + trimmed.len()),
+            ));
+            continue;
+        };
+
+        let (key, value) = (key.trim_end(), value.trim());
+        // `config:` is only used to group entries
+        if value.is_empty() && key == "config" {
+            continue;
+        }
+
+        let value_start = line_start + trimmed.len() - value.len();
+        entries.push(ConfigEntry {
+            key: key.to_string(),
+            key_span: Span::new(line_start, line_start + key.len()),
+            value: value.to_string(),
+            value_span: Span::new(value_start, value_start + value.len()),
+        });
+    }
+
+    let opening_end = opening_start + opening_line.trim_end().len();
+    diagnostics.push(Diagnostic::error(
+        Code::Syntax,
+        "config block is never closed, expected `---`",
+        Span::new(opening_start, opening_end),
+    ));
+
+    (entries, source.len(), diagnostics)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position.min(self.tokens.len() - 1)]
+    }
+
+    fn peek_kind(&self) -> &TokenKind {
+        &self.peek().kind
+    }
+
+    fn peek_nth_kind(&self, n: usize) -> &TokenKind {
+        &self.tokens[(self.position + n).min(self.tokens.len() - 1)].kind
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error_at_current(&self, expected: &str) -> Diagnostic {
+        let token = self.peek();
+        let found = match &token.kind {
+            TokenKind::Identifier(name) => format!("`{name}`"),
+            TokenKind::Number { .. } => "a number".to_string(),
+            TokenKind::HexColor(_) => "a color".to_string(),
+            TokenKind::String(_) => "a string".to_string(),
+            TokenKind::Equal => "`=`".to_string(),
+            TokenKind::Arrow => "`->`".to_string(),
+            TokenKind::Comma => "`,`".to_string(),
+            TokenKind::Quote => "`'`".to_string(),
+            TokenKind::LeftParen => "`(`".to_string(),
+            TokenKind::RightParen => "`)`".to_string(),
+            TokenKind::LeftBracket => "`[`".to_string(),
+            TokenKind::RightBracket => "`]`".to_string(),
+            TokenKind::Newline => "end of line".to_string(),
+            TokenKind::Eof => "end of file".to_string(),
+        };
+
+        Diagnostic::error(
+            Code::Syntax,
+            format!("expected {expected}, found {found}"),
+            token.span,
+        )
+    }
+
+    fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<Token, Diagnostic> {
+        if *self.peek_kind() == kind {
+            Ok(self.advance())
+        } else {
+            Err(self.error_at_current(expected))
+        }
+    }
+
+    fn expect_end_of_line(&mut self) -> Result<(), Diagnostic> {
+        match self.peek_kind() {
+            TokenKind::Newline => {
+                self.advance();
+                Ok(())
+            }
+            TokenKind::Eof => Ok(()),
+            _ => Err(self.error_at_current("end of line")),
+        }
+    }
+
+    fn identifier(&mut self, expected: &str) -> Result<Identifier, Diagnostic> {
+        match self.peek_kind().clone() {
+            TokenKind::Identifier(name) => {
+                let span = self.advance().span;
+                Ok(Identifier { name, span })
+            }
+            _ => Err(self.error_at_current(expected)),
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while *self.peek_kind() == TokenKind::Newline {
+            self.advance();
+        }
+    }
+
+    /// Skip the rest of the current line
+    fn skip_line(&mut self) {
+        while !matches!(self.peek_kind(), TokenKind::Newline | TokenKind::Eof) {
+            self.advance();
+        }
+        self.advance();
+    }
+
+    /// Skip tokens until the start of the next top level statement
+    fn synchronize(&mut self) {
+        self.skip_line();
+        while self.peek().indented {
+            self.skip_line();
+        }
+    }
+
+    fn statements(&mut self) -> Vec<Statement> {
+        let mut statements = Vec::new();
+
+        while *self.peek_kind() != TokenKind::Eof {
+            match self.statement() {
+                Ok(statement) => statements.push(statement),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize();
+                }
+            }
+        }
+
+        statements
+    }
+
+    fn statement(&mut self) -> Result<Statement, Diagnostic> {
+        if self.peek().indented {
+            return Err(Diagnostic::error(
+                Code::Syntax,
+                "unexpected indentation, fields must follow a declaration",
+                self.peek().span,
+            ));
+        }
+
+        let statement = match (self.peek_kind(), self.peek_nth_kind(1)) {
+            (TokenKind::Identifier(keyword), TokenKind::String(path))
+                if keyword == IMPORT_KEYWORD =>
+            {
+                let path = path.clone();
+                let start = self.advance().span;
+                let end = self.advance().span;
+                Statement::Import {
+                    path,
+                    span: start.to(end),
+                }
+            }
+            (TokenKind::Quote | TokenKind::LeftBracket, _)
+            | (TokenKind::Identifier(_), TokenKind::LeftParen) => {
+                Statement::Sequence(self.sequence(false)?)
+            }
+            (TokenKind::Identifier(_), TokenKind::Equal) => {
+                let name = self.identifier("a name")?;
+                self.advance();
+                let value = self.expression()?;
+                Statement::Assignment { name, value }
+            }
+            (TokenKind::Identifier(first), TokenKind::Identifier(second))
+                if is_keyword(first) || is_keyword(second) =>
+            {
+                let first = self.identifier("a name")?;
+                let second = self.identifier("a name")?;
+                // Both `r1 RECT` and `RECT r1` are accepted
+                let (name, keyword) = if is_keyword(&second.name) {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                // Fields consume their own end of line
+                return self.declaration(name, keyword);
+            }
+            (TokenKind::Identifier(_), TokenKind::Identifier(second)) => {
+                let span = self.tokens[self.position + 1].span;
+                return Err(Diagnostic::error(
+                    Code::Syntax,
+                    format!("unknown declaration keyword `{second}`, expected `RECT` or `ANIM`"),
+                    span,
+                ));
+            }
+            _ => return Err(self.error_at_current("a declaration or an animation sequence")),
+        };
+
+        self.expect_end_of_line()?;
+
+        Ok(statement)
+    }
+
+    fn declaration(
+        &mut self,
+        name: Identifier,
+        keyword: Identifier,
+    ) -> Result<Statement, Diagnostic> {
+        self.expect_end_of_line()?;
+
+        let mut fields = Vec::new();
+        while self.peek().indented {
+            match self.field() {
+                Ok(field) => fields.push(field),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.skip_line();
+                }
+            }
+        }
+
+        if keyword.name == ANIMATION_KEYWORD {
+            Ok(Statement::Animation { name, fields })
+        } else {
+            Ok(Statement::Shape {
+                name,
+                kind: keyword,
+                fields,
+            })
+        }
+    }
+
+    fn field(&mut self) -> Result<Field, Diagnostic> {
+        let name = self.identifier("a field name")?;
+        let mut span = name.span;
+
+        let mut arguments = Vec::new();
+        let mut target = None;
+        loop {
+            match self.peek_kind() {
+                TokenKind::Newline | TokenKind::Eof => break,
+                // Commas between arguments are optional (eg. `transition ease-in 2s,`)
+                TokenKind::Comma => {
+                    self.advance();
+                }
+                TokenKind::Arrow => {
+                    self.advance();
+                    let expression = self.expression()?;
+                    span = span.to(expression.span());
+                    target = Some(expression);
+                    self.expect_end_of_line()?;
+                    return Ok(Field {
+                        name,
+                        arguments,
+                        target,
+                        span,
+                    });
+                }
+                _ => {
+                    let expression = self.expression()?;
+                    span = span.to(expression.span());
+                    arguments.push(expression);
+                }
+            }
+        }
+        self.expect_end_of_line()?;
+
+        Ok(Field {
+            name,
+            arguments,
+            target,
+            span,
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expression, Diagnostic> {
+        let expression = match self.peek_kind().clone() {
+            TokenKind::Identifier(_) => {
+                let name = self.identifier("a name")?;
+                // The arguments of a call follow its name, a group being apart from it
+                if *self.peek_kind() != TokenKind::LeftParen
+                    || self.peek().span.start != name.span.end
+                {
+                    return Ok(Expression::Identifier(name));
+                }
+
+                self.advance();
+                let arguments = self.expression_list(TokenKind::RightParen, "`)`")?;
+                let end = self.advance().span;
+                Expression::Call {
+                    span: name.span.to(end),
+                    name,
+                    arguments,
+                }
+            }
+            TokenKind::Number { value, unit } => Expression::Number {
+                value,
+                unit,
+                span: self.advance().span,
+            },
+            TokenKind::HexColor(value) => Expression::HexColor {
+                value,
+                span: self.advance().span,
+            },
+            TokenKind::String(value) => Expression::String {
+                value,
+                span: self.advance().span,
+            },
+            TokenKind::LeftBracket => {
+                let start = self.advance().span;
+                let items = self.expression_list(TokenKind::RightBracket, "`]`")?;
+                let end = self.advance().span;
+                Expression::List {
+                    items,
+                    span: start.to(end),
+                }
+            }
+            TokenKind::LeftParen => {
+                let start = self.advance().span;
+                let mut items = Vec::new();
+                while *self.peek_kind() != TokenKind::RightParen {
+                    if matches!(self.peek_kind(), TokenKind::Newline | TokenKind::Eof) {
+                        return Err(self.error_at_current("`)`"));
+                    }
+                    items.push(self.expression()?);
+                }
+                let end = self.advance().span;
+                Expression::Group {
+                    items,
+                    span: start.to(end),
+                }
+            }
+            _ => return Err(self.error_at_current("a value")),
+        };
+
+        Ok(expression)
+    }
+
+    /// Comma separated expressions up to `closing`, which is left for the caller to consume
+    fn expression_list(
+        &mut self,
+        closing: TokenKind,
+        expected: &str,
+    ) -> Result<Vec<Expression>, Diagnostic> {
+        let mut expressions = Vec::new();
+
+        while *self.peek_kind() != closing {
+            expressions.push(self.expression()?);
+
+            match self.peek_kind() {
+                TokenKind::Comma => {
+                    self.advance();
+                }
+                kind if *kind == closing => {}
+                _ => return Err(self.error_at_current(&format!("`,` or {expected}"))),
+            }
+        }
+
+        Ok(expressions)
+    }
+
+    /// Steps separated by `->`. Inside of `[]` (`nested`), steps may span multiple lines.
+    fn sequence(&mut self, nested: bool) -> Result<Vec<SequenceStep>, Diagnostic> {
+        let mut steps = vec![self.sequence_step()?];
+
+        loop {
+            if nested {
+                self.skip_newlines();
+            }
+            if *self.peek_kind() != TokenKind::Arrow {
+                break;
+            }
+
+            self.advance();
+            self.skip_newlines();
+            steps.push(self.sequence_step()?);
+        }
+
+        Ok(steps)
+    }
+
+    fn sequence_step(&mut self) -> Result<SequenceStep, Diagnostic> {
+        if *self.peek_kind() == TokenKind::LeftBracket {
+            let start = self.advance().span;
+
+            let mut branches = Vec::new();
+            loop {
+                self.skip_newlines();
+                branches.push(self.sequence(true)?);
+                self.skip_newlines();
+
+                match self.peek_kind() {
+                    TokenKind::Comma => {
+                        self.advance();
+                    }
+                    TokenKind::RightBracket => break,
+                    _ => return Err(self.error_at_current("`,` or `]`")),
+                }
+            }
+            let end = self.advance().span;
+
+            return Ok(SequenceStep::Concurrent {
+                branches,
+                span: start.to(end),
+            });
+        }
+
+        let quote = (*self.peek_kind() == TokenKind::Quote).then(|| self.advance().span);
+
+        let animation = self.identifier("an animation name")?;
+        self.expect(TokenKind::LeftParen, "`(`")?;
+        let target = self.identifier("a shape name")?;
+        if *self.peek_kind() == TokenKind::LeftParen {
+            return Err(Diagnostic::error(
+                Code::Unsupported,
+                "merging animations is not supported yet",
+                target.span,
+            ));
+        }
+        let end = self.expect(TokenKind::RightParen, "`)`")?.span;
+
+        Ok(SequenceStep::Apply {
+            span: quote.unwrap_or(animation.span).to(end),
+            animation,
+            target,
+            reversed: quote.is_some(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_ok(source: &str) -> Script {
+        let (script, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        script
+    }
+
+    fn names(steps: &[SequenceStep]) -> Vec<String> {
+        steps
+            .iter()
+            .map(|step| match step {
+                SequenceStep::Apply {
+                    animation,
+                    target,
+                    reversed,
+                    ..
+                } => {
+                    let quote = if *reversed { "'" } else { "" };
+                    format!("{quote}{}({})", animation.name, target.name)
+                }
+                SequenceStep::Concurrent { branches, .. } => {
+                    let branches: Vec<String> =
+                        branches.iter().map(|b| names(b).join(" -> ")).collect();
+                    format!("[{}]", branches.join(", "))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_config_test() {
+        let script = parse_ok("---\nwidth:    10\nfilename: ./out.gif\n---\n");
+
+        assert_eq!(script.config.len(), 2);
+        assert_eq!(script.config[0].key, "width");
+        assert_eq!(script.config[0].value, "10");
+        assert_eq!(script.config[0].value_span, Span::new(14, 16));
+        assert_eq!(script.config[1].value, "./out.gif");
+    }
+
+    #[test]
+    fn parse_nested_config_test() {
+        let script = parse_ok("---\nconfig:\n    width: 20\n---\n");
+
+        assert_eq!(script.config.len(), 1);
+        assert_eq!(script.config[0].key, "width");
+    }
+
+    #[test]
+    fn parse_unclosed_config_test() {
+        let (_, diagnostics) = parse("---\nwidth: 10\n");
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn parse_declarations_test() {
+        let script = parse_ok(
+            "red = rgb(255, 0, 0)\n\
+             \n\
+             r_to_g ANIM\n\
+             \x20   color red -> green\n\
+             \x20   transition ease-in 2s,\n\
+             \n\
+             RECT r1\n\
+             \x20   width 10\n\
+             \x20   position [0, 5]\n",
+        );
+
+        assert_eq!(script.statements.len(), 3);
+        assert!(matches!(
+            &script.statements[0],
+            Statement::Assignment { value: Expression::Call { arguments, .. }, .. } if arguments.len() == 3
+        ));
+
+        let Statement::Animation { name, fields } = &script.statements[1] else {
+            panic!("expected an animation");
+        };
+        assert_eq!(name.name, "r_to_g");
+        assert_eq!(fields.len(), 2);
+        assert!(fields[0].target.is_some());
+        assert_eq!(fields[1].arguments.len(), 2);
+
+        let Statement::Shape { name, kind, fields } = &script.statements[2] else {
+            panic!("expected a shape");
+        };
+        assert_eq!((name.name.as_str(), kind.name.as_str()), ("r1", "RECT"));
+        assert!(
+            matches!(&fields[1].arguments[0], Expression::List { items, .. } if items.len() == 2)
+        );
+    }
+
+    #[test]
+    fn parse_group_test() {
+        let script = parse_ok("a ANIM\n    transition ease (color 1s) (move 2s)\n");
+
+        let Statement::Animation { fields, .. } = &script.statements[0] else {
+            panic!("expected an animation");
+        };
+        // A name followed by a space and `(` is not a call
+        let [Expression::Identifier(_), groups @ ..] = fields[0].arguments.as_slice() else {
+            panic!("expected a transition type");
+        };
+        assert_eq!(groups.len(), 2);
+        assert!(matches!(&groups[0], Expression::Group { items, .. } if items.len() == 2));
+    }
+
+    #[test]
+    fn parse_sequence_test() {
+        let script = parse_ok("a(x) -> 'b(x) ->\n  [c(y), d(z) -> e(z)]\n");
+
+        let Statement::Sequence(steps) = &script.statements[0] else {
+            panic!("expected a sequence");
+        };
+        assert_eq!(names(steps), vec!["a(x)", "'b(x)", "[c(y), d(z) -> e(z)]"]);
+    }
+
+    #[test]
+    fn parse_import_test() {
+        let (script, diagnostics) = parse_file("---\nwidth: 1\n---\nimport \"shapes.gas\"\n", 10);
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(script.config[0].key_span, Span::new(14, 19));
+        assert_eq!(
+            script.statements,
+            vec![Statement::Import {
+                path: "shapes.gas".to_string(),
+                span: Span::new(27, 46),
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_recovers_after_error_test() {
+        let (script, diagnostics) =
+            parse("r1 RECT\n    width 10 ->\n    height 10\n\nr2 FOO\n    width 1\n\nr3 RECT\n");
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(script.statements.len(), 2);
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+
+use super::diagnostic::{Diagnostic, Span};
+
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub source: String,
+    /// Offset of the file in the spans of the source map
+    pub start: usize,
+}
+
+/// Files making up a script. Every file gets its own range of offsets so that spans of
+/// different files never overlap and can be traced back to their file.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    /// Add a file, returns the offset its spans start at
+    pub fn add(&mut self, path: PathBuf, source: String) -> usize {
+        // Leave a gap so that a span at the end of a file is not mistaken for the next one
+        let start = self
+            .files
+            .last()
+            .map_or(0, |file| file.start + file.source.len() + 1);
+
+        self.files.push(SourceFile {
+            path,
+            source,
+            start,
+        });
+
+        start
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|file| file.path.as_path())
+    }
+
+    /// File containing the offset
+    pub fn file(&self, offset: usize) -> Option<&SourceFile> {
+        self.files.iter().rev().find(|file| file.start <= offset)
+    }
+
+    /// Span relative to the start of its own file
+    pub fn local_span(&self, span: Span) -> Span {
+        let start = self.file(span.start).map_or(0, |file| file.start);
+        Span::new(span.start - start, span.end.saturating_sub(start))
+    }
+
+    /// Format a diagnostic with the line of the file it points to
+    pub fn display(&self, diagnostic: &Diagnostic) -> String {
+        let Some(file) = self.file(diagnostic.span.start) else {
+            return format!("{}: {}", diagnostic.severity, diagnostic.message);
+        };
+
+        let local = Diagnostic {
+            span: self.local_span(diagnostic.span),
+            ..diagnostic.clone()
+        };
+        local.display(&file.path.to_string_lossy(), &file.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::diagnostic::Code;
+
+    #[test]
+    fn source_map_test() {
+        let mut sources = SourceMap::new();
+        assert_eq!(sources.add(PathBuf::from("a.gas"), "abc".to_string()), 0);
+        assert_eq!(sources.add(PathBuf::from("b.gas"), "de\nf".to_string()), 4);
+
+        let file = sources.file(7).unwrap();
+        assert_eq!(file.path, PathBuf::from("b.gas"));
+        assert_eq!(sources.local_span(Span::new(7, 8)), Span::new(3, 4));
+
+        let diagnostic = Diagnostic::error(Code::Syntax, "oops", Span::new(7, 8));
+        assert_eq!(sources.display(&diagnostic), "b.gas:2:1: error: oops\nf\n^");
+    }
+}